@@ -0,0 +1,34 @@
+-- Stripe Connect account for sellers (set during payout onboarding)
+ALTER TABLE users ADD COLUMN stripe_account_id VARCHAR(255) UNIQUE;
+
+-- Create payout status enum type
+CREATE TYPE payout_status AS ENUM ('pending', 'transferred', 'failed');
+
+-- Track transfers of sale proceeds to sellers (one per sold ticket)
+CREATE TABLE payouts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    ticket_id UUID NOT NULL UNIQUE REFERENCES tickets(id) ON DELETE RESTRICT,
+    seller_id UUID NOT NULL REFERENCES users(id) ON DELETE RESTRICT,
+    payment_intent_id VARCHAR(255) NOT NULL REFERENCES payment_intents(id) ON DELETE RESTRICT,
+    amount INTEGER NOT NULL CHECK (amount >= 0), -- Amount captured from the buyer
+    platform_fee INTEGER NOT NULL CHECK (platform_fee >= 0 AND platform_fee <= amount),
+    currency VARCHAR(3) NOT NULL, -- ISO currency code (e.g., "usd")
+    status payout_status NOT NULL DEFAULT 'pending',
+    stripe_transfer_id VARCHAR(255), -- Stripe transfer ID (tr_xxx) once transferred
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_payouts_seller_id ON payouts(seller_id);
+
+-- Index for the payout retry job
+CREATE INDEX idx_payouts_pending_next_attempt
+ON payouts(next_attempt_at)
+WHERE status = 'pending';
+
+-- Create trigger to automatically update updated_at
+CREATE TRIGGER update_payouts_updated_at BEFORE UPDATE ON payouts
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,13 @@
+-- Paid tickets whose transfer never completes are refunded to the buyer
+-- refunding: refund requested, waiting on Stripe
+-- refunded: buyer refunded (terminal)
+ALTER TYPE ticket_status ADD VALUE 'refunding' AFTER 'sold';
+ALTER TYPE ticket_status ADD VALUE 'refunded' AFTER 'refunding';
+
+ALTER TYPE payment_intent_status ADD VALUE 'refunded';
+ALTER TYPE payment_intent_status ADD VALUE 'disputed';
+
+-- held: charge disputed, transfer paused
+-- cancelled: charge refunded before transfer, nothing to pay out
+ALTER TYPE payout_status ADD VALUE 'held';
+ALTER TYPE payout_status ADD VALUE 'cancelled';
@@ -0,0 +1,12 @@
+-- Refunded tickets are terminal, so they no longer hold the seat
+-- (kept separate from 007 since new enum values can't be used in the same transaction)
+DROP INDEX idx_tickets_unique_active_seat;
+
+CREATE UNIQUE INDEX idx_tickets_unique_active_seat
+ON tickets (game_id, level, seat_section, seat_row, seat_number)
+WHERE status NOT IN ('sold', 'cancelled', 'refunded');
+
+-- Index for the refund deadline job
+CREATE INDEX idx_tickets_paid_updated_at
+ON tickets(updated_at)
+WHERE status = 'paid';
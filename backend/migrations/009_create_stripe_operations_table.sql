@@ -0,0 +1,29 @@
+-- Create Stripe operation enum types
+CREATE TYPE stripe_operation_type AS ENUM ('capture', 'cancel');
+CREATE TYPE stripe_operation_status AS ENUM ('pending', 'succeeded', 'dead');
+
+-- Outbox of Stripe calls that must eventually happen (retried with backoff)
+-- dead: gave up after max attempts, needs manual intervention
+CREATE TABLE stripe_operations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    payment_intent_id VARCHAR(255) NOT NULL REFERENCES payment_intents(id) ON DELETE RESTRICT,
+    operation stripe_operation_type NOT NULL,
+    status stripe_operation_status NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (payment_intent_id, operation)
+);
+
+-- Index for the outbox worker
+CREATE INDEX idx_stripe_operations_pending_next_attempt
+ON stripe_operations(next_attempt_at)
+WHERE status = 'pending';
+
+CREATE INDEX idx_stripe_operations_status ON stripe_operations(status);
+
+-- Create trigger to automatically update updated_at
+CREATE TRIGGER update_stripe_operations_updated_at BEFORE UPDATE ON stripe_operations
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,2 @@
+-- failed: the buyer's payment attempt failed (payment_intent.payment_failed)
+ALTER TYPE payment_intent_status ADD VALUE 'failed' AFTER 'created';
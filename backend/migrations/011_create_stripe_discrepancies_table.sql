@@ -0,0 +1,32 @@
+-- Create discrepancy kind enum type
+CREATE TYPE discrepancy_kind AS ENUM (
+    'missing_locally',
+    'missing_at_stripe',
+    'amount_mismatch',
+    'currency_mismatch',
+    'status_mismatch',
+    'metadata_mismatch',
+    'ticket_status_mismatch'
+);
+
+-- Mismatches between payment_intents/tickets and Stripe found by reconciliation
+-- One open row per (payment intent, kind); resolved_at is set once a later run no longer sees it,
+-- or with expired once the intent has aged out of the lookback window unchecked
+CREATE TABLE stripe_discrepancies (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    payment_intent_id VARCHAR(255) NOT NULL,
+    ticket_id UUID REFERENCES tickets(id) ON DELETE SET NULL,
+    kind discrepancy_kind NOT NULL,
+    local_value TEXT,
+    stripe_value TEXT,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ,
+    expired BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE UNIQUE INDEX idx_stripe_discrepancies_open
+ON stripe_discrepancies(payment_intent_id, kind)
+WHERE resolved_at IS NULL;
+
+CREATE INDEX idx_stripe_discrepancies_last_seen_at ON stripe_discrepancies(last_seen_at);
@@ -0,0 +1,10 @@
+-- Store verification codes hashed, with an issue time and failed attempt tracking
+ALTER TABLE users
+    ADD COLUMN verification_code_hash VARCHAR(255),
+    ADD COLUMN verification_code_sent_at TIMESTAMPTZ,
+    ADD COLUMN verification_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN verification_locked_until TIMESTAMPTZ;
+
+-- Plaintext codes can't be carried over; unverified users request a new one
+DROP INDEX IF EXISTS idx_users_verification_code;
+ALTER TABLE users DROP COLUMN verification_code;
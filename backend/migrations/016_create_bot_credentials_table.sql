@@ -0,0 +1,30 @@
+-- Per-bot API credentials; only a SHA-256 hash of each key is stored
+CREATE TABLE bot_credentials (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) NOT NULL,
+    -- First characters of the key; identifies the credential on each request
+    key_prefix VARCHAR(16) NOT NULL UNIQUE,
+    key_hash VARCHAR(64) NOT NULL,
+    -- Key replaced by the last rotation, still accepted until previous_key_expires_at
+    previous_key_prefix VARCHAR(16),
+    previous_key_hash VARCHAR(64),
+    previous_key_expires_at TIMESTAMPTZ,
+    scopes TEXT[] NOT NULL,
+    expires_at TIMESTAMPTZ,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    last_used_at TIMESTAMPTZ,
+    rotated_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_bot_credentials_previous_key_prefix
+ON bot_credentials(previous_key_prefix)
+WHERE previous_key_prefix IS NOT NULL;
+
+-- Which bot performed each bot-driven transition (latest one wins)
+ALTER TABLE tickets
+    ADD COLUMN claimed_by_bot_id UUID REFERENCES bot_credentials(id) ON DELETE SET NULL,
+    ADD COLUMN verified_by_bot_id UUID REFERENCES bot_credentials(id) ON DELETE SET NULL,
+    ADD COLUMN unclaimed_by_bot_id UUID REFERENCES bot_credentials(id) ON DELETE SET NULL,
+    ADD COLUMN sold_by_bot_id UUID REFERENCES bot_credentials(id) ON DELETE SET NULL;
@@ -0,0 +1,75 @@
+-- Append-only history of ticket status changes, written by a trigger on tickets
+-- (no foreign key, so the history outlives tickets deleted by cleanup)
+CREATE TABLE ticket_events (
+    id BIGSERIAL PRIMARY KEY,
+    ticket_id UUID NOT NULL,
+    -- NULL from_status: ticket created; NULL to_status: ticket deleted
+    from_status ticket_status,
+    to_status ticket_status,
+    actor_type VARCHAR(16) NOT NULL CHECK (actor_type IN ('user', 'bot', 'admin', 'system')),
+    -- User id, bot credential id, or admin user/API key id
+    actor_id UUID,
+    -- Email, bot or API key name, or the system job
+    actor_label VARCHAR(255),
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ticket_events_ticket_id ON ticket_events(ticket_id, id);
+
+CREATE FUNCTION reject_ticket_event_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'ticket_events is append-only';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER ticket_events_append_only BEFORE UPDATE OR DELETE ON ticket_events
+    FOR EACH ROW EXECUTE FUNCTION reject_ticket_event_changes();
+
+-- Record a status change; the actor and reason come from transaction-local
+-- settings (ticket_events.*) set by the application, defaulting to 'system'
+CREATE FUNCTION record_ticket_event()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_ticket_id UUID;
+    v_from ticket_status;
+    v_to ticket_status;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        v_ticket_id := NEW.id;
+        v_to := NEW.status;
+    ELSIF TG_OP = 'UPDATE' THEN
+        IF NEW.status IS NOT DISTINCT FROM OLD.status THEN
+            RETURN NULL;
+        END IF;
+        v_ticket_id := NEW.id;
+        v_from := OLD.status;
+        v_to := NEW.status;
+    ELSE
+        v_ticket_id := OLD.id;
+        v_from := OLD.status;
+    END IF;
+
+    INSERT INTO ticket_events (ticket_id, from_status, to_status, actor_type, actor_id, actor_label, reason)
+    VALUES (
+        v_ticket_id,
+        v_from,
+        v_to,
+        COALESCE(NULLIF(current_setting('ticket_events.actor_type', true), ''), 'system'),
+        NULLIF(current_setting('ticket_events.actor_id', true), '')::UUID,
+        NULLIF(current_setting('ticket_events.actor_label', true), ''),
+        NULLIF(current_setting('ticket_events.reason', true), '')
+    );
+
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER record_ticket_status_change AFTER INSERT OR UPDATE OF status OR DELETE ON tickets
+    FOR EACH ROW EXECUTE FUNCTION record_ticket_event();
+
+-- Start the history of existing tickets from their current status
+INSERT INTO ticket_events (ticket_id, from_status, to_status, actor_type, actor_label, reason, created_at)
+SELECT id, NULL, status, 'system', 'migration', 'history started', updated_at
+FROM tickets;
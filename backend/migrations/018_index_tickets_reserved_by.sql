@@ -0,0 +1,2 @@
+-- Buyers' purchases and reservation counts look tickets up by who reserved them
+CREATE INDEX idx_tickets_reserved_by ON tickets(reserved_by) WHERE reserved_by IS NOT NULL;
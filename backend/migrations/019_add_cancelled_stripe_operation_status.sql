@@ -0,0 +1,2 @@
+-- cancelled: no longer needed, e.g. a capture dropped because the ticket is being refunded
+ALTER TYPE stripe_operation_status ADD VALUE 'cancelled';
@@ -0,0 +1,6 @@
+-- Refund job retries, with backoff like payouts
+-- refund_failed_at: gave up (max attempts or disputed charge), needs manual intervention
+ALTER TABLE tickets ADD COLUMN refund_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tickets ADD COLUMN refund_last_error TEXT;
+ALTER TABLE tickets ADD COLUMN refund_next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE tickets ADD COLUMN refund_failed_at TIMESTAMPTZ;
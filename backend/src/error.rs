@@ -26,9 +26,15 @@ pub enum AppError {
     #[error("Invalid verification code")]
     InvalidVerificationCode,
 
-    #[error("Verification code expired")]
+    #[error("Verification code expired, please request a new one")]
     VerificationCodeExpired,
 
+    #[error("Too many failed verification attempts, please try again later")]
+    VerificationLocked,
+
+    #[error("Invalid or expired password reset token")]
+    InvalidResetToken,
+
     #[error("Invalid email or password")]
     InvalidCredentials,
 
@@ -41,9 +47,21 @@ pub enum AppError {
     #[error("You can only update your own tickets")]
     Forbidden,
 
+    #[error("You don't have permission to perform this action")]
+    PermissionDenied,
+
     #[error("Invalid sport type")]
     InvalidSportType,
 
+    #[error("You can't reserve your own ticket")]
+    OwnTicketReservation,
+
+    #[error("Maximum {0} concurrent reservations allowed")]
+    ReservationLimitReached(i64),
+
+    #[error("{0}")]
+    BadRequest(String),
+
     #[error("Resource conflict: {0}")]
     Conflict(String),
 
@@ -66,11 +84,17 @@ impl IntoResponse for AppError {
             AppError::PasswordTooShort => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::InvalidVerificationCode => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::VerificationCodeExpired => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::VerificationLocked => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            AppError::InvalidResetToken => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::InvalidCredentials => (StatusCode::UNAUTHORIZED, self.to_string()),
             AppError::EmailNotVerified => (StatusCode::FORBIDDEN, self.to_string()),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
             AppError::Forbidden => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::PermissionDenied => (StatusCode::FORBIDDEN, self.to_string()),
             AppError::InvalidSportType => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::OwnTicketReservation => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::ReservationLimitReached(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::Conflict(_) => (StatusCode::CONFLICT, self.to_string()),
             AppError::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
@@ -1,5 +1,10 @@
+pub mod admin;
 pub mod auth;
 pub mod games;
+pub mod payouts;
+pub mod purchases;
+pub mod reconciliation;
+pub mod stripe_operations;
 pub mod tickets;
 pub mod webhooks;
 
@@ -0,0 +1,428 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::admin::{
+    AdminApiKey, AdminAuditEntry, BotCredential, BotCredentialKeyResponse, CreateApiKeyRequest,
+    CreateApiKeyResponse, CreateBotCredentialRequest, ListApiKeysResponse, ListAuditLogQuery,
+    ListAuditLogResponse, ListBotCredentialsResponse, UpdateUserRoleRequest, UserRoleResponse,
+};
+use crate::models::user::UserRole;
+use crate::utils::audit::record_admin_action;
+use crate::utils::auth::{
+    bot_key_rotation_grace_minutes, Actor, AdminCaller, ADMIN_API_KEY_PREFIX, BOT_KEY_PREFIX, KEY_PREFIX_LENGTH,
+};
+use crate::utils::password::{generate_secure_token, hash_secure_token};
+use crate::utils::permissions::{
+    role_has_permission, CanManageApiKeys, CanManageBots, CanManageUsers, CanViewAuditLog, Permission,
+};
+
+const BOT_CREDENTIAL_COLUMNS: &str = "id, name, key_prefix, scopes, expires_at, previous_key_expires_at, \
+    created_by, last_used_at, rotated_at, revoked_at, created_at";
+
+/// Parse role string to UserRole enum
+fn parse_user_role(s: &str) -> Result<UserRole> {
+    match s.to_lowercase().as_str() {
+        "user" => Ok(UserRole::User),
+        "moderator" => Ok(UserRole::Moderator),
+        "admin" => Ok(UserRole::Admin),
+        _ => Err(AppError::BadRequest(
+            "Invalid role. Must be one of: user, moderator, admin".to_string(),
+        )),
+    }
+}
+
+/// Check the display name of an API key or bot credential
+fn validate_key_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(AppError::BadRequest("Key name must be 1-100 characters".to_string()));
+    }
+    Ok(name)
+}
+
+/// Create a scoped admin API key (admin only)
+///
+/// The key is returned once and only its hash is stored. A caller can't grant
+/// scopes it doesn't have itself.
+pub async fn create_api_key(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageApiKeys>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>)> {
+    let name = validate_key_name(&req.name)?;
+
+    if req.scopes.is_empty() {
+        return Err(AppError::BadRequest("At least one scope is required".to_string()));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in &req.scopes {
+        let permission = Permission::parse(scope)?;
+        admin.actor.require(permission)?;
+        if !scopes.contains(&permission.as_str()) {
+            scopes.push(permission.as_str());
+        }
+    }
+
+    let key = format!("{}{}", ADMIN_API_KEY_PREFIX, generate_secure_token());
+    let created_by = match &admin.actor {
+        Actor::User { user_id, .. } => Some(*user_id),
+        Actor::ApiKey { .. } => None,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let api_key = sqlx::query_as::<_, AdminApiKey>(
+        r#"
+        INSERT INTO admin_api_keys (name, key_prefix, key_hash, scopes, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, name, key_prefix, scopes, created_by, last_used_at, revoked_at, created_at
+        "#,
+    )
+    .bind(name)
+    .bind(&key[..KEY_PREFIX_LENGTH])
+    .bind(hash_secure_token(&key))
+    .bind(&scopes)
+    .bind(created_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "api_key.create",
+        "api_key",
+        Some(api_key.id.to_string()),
+        json!({ "name": api_key.name, "scopes": api_key.scopes }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(CreateApiKeyResponse { key, api_key })))
+}
+
+/// List admin API keys, including revoked ones (admin only)
+pub async fn list_api_keys(
+    State(pool): State<PgPool>,
+    _admin: AdminCaller<CanManageApiKeys>,
+) -> Result<Json<ListApiKeysResponse>> {
+    let api_keys = sqlx::query_as::<_, AdminApiKey>(
+        r#"
+        SELECT id, name, key_prefix, scopes, created_by, last_used_at, revoked_at, created_at
+        FROM admin_api_keys
+        ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ListApiKeysResponse { api_keys }))
+}
+
+/// Revoke an admin API key (admin only)
+pub async fn revoke_api_key(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageApiKeys>,
+    Path(key_id): Path<Uuid>,
+) -> Result<Json<AdminApiKey>> {
+    let mut tx = pool.begin().await?;
+
+    let api_key = sqlx::query_as::<_, AdminApiKey>(
+        r#"
+        UPDATE admin_api_keys
+        SET revoked_at = NOW()
+        WHERE id = $1
+          AND revoked_at IS NULL
+        RETURNING id, name, key_prefix, scopes, created_by, last_used_at, revoked_at, created_at
+        "#,
+    )
+    .bind(key_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active API key not found".to_string()))?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "api_key.revoke",
+        "api_key",
+        Some(key_id.to_string()),
+        json!({ "name": api_key.name }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(api_key))
+}
+
+/// Change a user's role (admin only)
+///
+/// Admins can't change their own role, so the last admin can't lock everyone out.
+/// As with API key scopes, a caller can only grant a role whose permissions it
+/// has itself, so a scoped key can't make anyone an admin.
+pub async fn update_user_role(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageUsers>,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<UpdateUserRoleRequest>,
+) -> Result<Json<UserRoleResponse>> {
+    let role = parse_user_role(&req.role)?;
+
+    if matches!(&admin.actor, Actor::User { user_id: actor_id, .. } if *actor_id == user_id) {
+        return Err(AppError::BadRequest("You can't change your own role".to_string()));
+    }
+
+    for permission in Permission::ALL {
+        if role_has_permission(role, permission) {
+            admin.actor.require(permission)?;
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let previous_role = sqlx::query_scalar::<_, UserRole>("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let user = sqlx::query_as::<_, UserRoleResponse>(
+        r#"
+        UPDATE users
+        SET role = $2
+        WHERE id = $1
+        RETURNING id, email, role
+        "#,
+    )
+    .bind(user_id)
+    .bind(role)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "user.role_change",
+        "user",
+        Some(user_id.to_string()),
+        json!({ "email": user.email, "from": previous_role, "to": role }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    info!("User {} role changed from {:?} to {:?}", user_id, previous_role, role);
+
+    Ok(Json(user))
+}
+
+/// List recent admin actions, newest first (admin only)
+pub async fn list_audit_log(
+    State(pool): State<PgPool>,
+    _admin: AdminCaller<CanViewAuditLog>,
+    Query(query): Query<ListAuditLogQuery>,
+) -> Result<Json<ListAuditLogResponse>> {
+    let entries = sqlx::query_as::<_, AdminAuditEntry>(
+        r#"
+        SELECT id, actor_user_id, actor_api_key_id, actor_label, action,
+               target_type, target_id, details, created_at
+        FROM admin_audit_log
+        WHERE ($1::TEXT IS NULL OR action = $1)
+          AND ($2::TEXT IS NULL OR target_type = $2)
+          AND ($3::TEXT IS NULL OR target_id = $3)
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#,
+    )
+    .bind(&query.action)
+    .bind(&query.target_type)
+    .bind(&query.target_id)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ListAuditLogResponse { entries }))
+}
+
+/// Create a bot credential (admin only)
+///
+/// Each verification bot instance gets its own credential, so the tickets it
+/// claims, verifies and marks sold can be traced back to it.
+pub async fn create_bot_credential(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageBots>,
+    Json(req): Json<CreateBotCredentialRequest>,
+) -> Result<(StatusCode, Json<BotCredentialKeyResponse>)> {
+    let name = validate_key_name(&req.name)?;
+
+    if req.scopes.is_empty() {
+        return Err(AppError::BadRequest("At least one scope is required".to_string()));
+    }
+
+    let mut scopes = Vec::new();
+    for scope in &req.scopes {
+        let permission = Permission::parse_bot_scope(scope)?;
+        if !scopes.contains(&permission.as_str()) {
+            scopes.push(permission.as_str());
+        }
+    }
+
+    if req.expires_in_days.is_some_and(|days| days <= 0) {
+        return Err(AppError::BadRequest("expires_in_days must be positive".to_string()));
+    }
+
+    let key = format!("{}{}", BOT_KEY_PREFIX, generate_secure_token());
+    let created_by = match &admin.actor {
+        Actor::User { user_id, .. } => Some(*user_id),
+        Actor::ApiKey { .. } => None,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let bot_credential = sqlx::query_as::<_, BotCredential>(&format!(
+        r#"
+        INSERT INTO bot_credentials (name, key_prefix, key_hash, scopes, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, NOW() + INTERVAL '1 day' * $5, $6)
+        RETURNING {BOT_CREDENTIAL_COLUMNS}
+        "#
+    ))
+    .bind(name)
+    .bind(&key[..KEY_PREFIX_LENGTH])
+    .bind(hash_secure_token(&key))
+    .bind(&scopes)
+    .bind(req.expires_in_days)
+    .bind(created_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "bot_credential.create",
+        "bot_credential",
+        Some(bot_credential.id.to_string()),
+        json!({
+            "name": bot_credential.name,
+            "scopes": bot_credential.scopes,
+            "expires_at": bot_credential.expires_at,
+        }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(BotCredentialKeyResponse { key, bot_credential })))
+}
+
+/// List bot credentials, including revoked ones (admin only)
+pub async fn list_bot_credentials(
+    State(pool): State<PgPool>,
+    _admin: AdminCaller<CanManageBots>,
+) -> Result<Json<ListBotCredentialsResponse>> {
+    let bot_credentials = sqlx::query_as::<_, BotCredential>(&format!(
+        "SELECT {BOT_CREDENTIAL_COLUMNS} FROM bot_credentials ORDER BY created_at DESC"
+    ))
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ListBotCredentialsResponse { bot_credentials }))
+}
+
+/// Issue a new key for a bot credential (admin only)
+///
+/// The bot keeps its identity. Its previous key keeps working for
+/// `BOT_KEY_ROTATION_GRACE_MINUTES` so the bot can be redeployed without downtime.
+pub async fn rotate_bot_credential(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageBots>,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<BotCredentialKeyResponse>> {
+    let key = format!("{}{}", BOT_KEY_PREFIX, generate_secure_token());
+
+    let mut tx = pool.begin().await?;
+
+    let bot_credential = sqlx::query_as::<_, BotCredential>(&format!(
+        r#"
+        UPDATE bot_credentials
+        SET previous_key_prefix = key_prefix,
+            previous_key_hash = key_hash,
+            previous_key_expires_at = NOW() + INTERVAL '1 minute' * $4,
+            key_prefix = $2,
+            key_hash = $3,
+            rotated_at = NOW()
+        WHERE id = $1
+          AND revoked_at IS NULL
+        RETURNING {BOT_CREDENTIAL_COLUMNS}
+        "#
+    ))
+    .bind(bot_id)
+    .bind(&key[..KEY_PREFIX_LENGTH])
+    .bind(hash_secure_token(&key))
+    .bind(bot_key_rotation_grace_minutes())
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active bot credential not found".to_string()))?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "bot_credential.rotate",
+        "bot_credential",
+        Some(bot_id.to_string()),
+        json!({
+            "name": bot_credential.name,
+            "previous_key_expires_at": bot_credential.previous_key_expires_at,
+        }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(BotCredentialKeyResponse { key, bot_credential }))
+}
+
+/// Revoke a bot credential, including a previous key still in its grace period (admin only)
+pub async fn revoke_bot_credential(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManageBots>,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<BotCredential>> {
+    let mut tx = pool.begin().await?;
+
+    let bot_credential = sqlx::query_as::<_, BotCredential>(&format!(
+        r#"
+        UPDATE bot_credentials
+        SET revoked_at = NOW()
+        WHERE id = $1
+          AND revoked_at IS NULL
+        RETURNING {BOT_CREDENTIAL_COLUMNS}
+        "#
+    ))
+    .bind(bot_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Active bot credential not found".to_string()))?;
+
+    record_admin_action(
+        &mut *tx,
+        &admin.actor,
+        "bot_credential.revoke",
+        "bot_credential",
+        Some(bot_id.to_string()),
+        json!({ "name": bot_credential.name }),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(bot_credential))
+}
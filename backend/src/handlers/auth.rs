@@ -4,21 +4,36 @@ use axum::{
     response::Json,
 };
 use sqlx::PgPool;
+use std::sync::Arc;
 use tracing::{info, warn};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
 use crate::models::user::{
-    LoginRequest, LoginResponse, RegisterRequest, RegisterResponse, UserInfo,
+    ForgotPasswordRequest, ForgotPasswordResponse, LoginRequest, LoginResponse, LogoutResponse,
+    RefreshRequest, RefreshResponse, RegisterRequest, RegisterResponse, ResendVerificationRequest,
+    ResendVerificationResponse, ResetPasswordRequest, ResetPasswordResponse, UserInfo, UserRole,
     VerifyEmailRequest, VerifyEmailResponse,
 };
-use crate::utils::email::{generate_verification_code, validate_school_email};
+use crate::utils::email::{
+    generate_verification_code, hash_verification_code, validate_school_email, verification_code_ttl_minutes,
+    verification_lockout_minutes, verification_max_attempts, verification_resend_cooldown_seconds,
+    verify_verification_code,
+};
+use crate::utils::auth::{bootstrap_admin_email, AuthUser};
 use crate::utils::jwt::generate_token;
-use crate::utils::password::{hash_password, validate_password, verify_password};
+use crate::utils::mailer::{password_reset_email, send_in_background, verification_code_email, DevMode, Mailer};
+use crate::utils::password::{
+    generate_secure_token, hash_password, hash_secure_token, password_reset_cooldown_seconds,
+    password_reset_token_ttl_minutes, validate_password, verify_password,
+};
+use crate::utils::sessions::{create_session, revoke_all_sessions, revoke_session, rotate_session};
 
 /// Register a new user - creates account with unverified email and sends verification code
 pub async fn register(
     State(pool): State<PgPool>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    State(DevMode(dev_mode)): State<DevMode>,
     Json(req): Json<RegisterRequest>,
 ) -> Result<(StatusCode, Json<RegisterResponse>)> {
     // Validate email is an MSU email
@@ -40,34 +55,41 @@ pub async fn register(
     // Hash password
     let password_hash = hash_password(&req.password)?;
 
-    // Generate verification code
+    // Generate verification code (only its hash is stored)
     let verification_code = generate_verification_code();
+    let verification_code_hash = hash_verification_code(&verification_code)?;
+
+    // The bootstrap admin gets the admin role from the start
+    let role = if bootstrap_admin_email().is_some_and(|email| email == req.email.to_lowercase()) {
+        UserRole::Admin
+    } else {
+        UserRole::User
+    };
 
     // Create user record with email_verified = false
     let user_id = sqlx::query_scalar::<_, Uuid>(
         r#"
-        INSERT INTO users (email, password_hash, email_verified, verification_code)
-        VALUES ($1, $2, false, $3)
+        INSERT INTO users (email, password_hash, email_verified, role, verification_code_hash, verification_code_sent_at)
+        VALUES ($1, $2, false, $3, $4, NOW())
         RETURNING id
         "#,
     )
     .bind(&req.email)
     .bind(&password_hash)
-    .bind(&verification_code)
+    .bind(role)
+    .bind(&verification_code_hash)
     .fetch_one(&pool)
     .await?;
 
     info!("User registered: {} (ID: {})", req.email, user_id);
 
-    // TODO: Send verification code via email service
-    // For now, log it (in production, remove this logging and send email)
-    info!("Verification code for {}: {}", req.email, verification_code);
+    // Deliver the code by email without holding up the response
+    send_in_background(mailer, verification_code_email(&req.email, &verification_code));
 
-    // In development, we'll return the code in the response
-    // In production, remove this and only send via email
+    // Development mode returns the code so registration works without a mailbox
     let response = RegisterResponse {
         message: "Registration successful. Please check your email for verification code.".to_string(),
-        verification_code: Some(verification_code), // TODO: Remove in production
+        verification_code: dev_mode.then_some(verification_code),
     };
 
     Ok((StatusCode::CREATED, Json(response)))
@@ -76,42 +98,115 @@ pub async fn register(
 
 
 /// Verify email with verification code and activate account
+///
+/// Codes expire after VERIFICATION_CODE_TTL_MINUTES. After VERIFICATION_MAX_ATTEMPTS
+/// wrong guesses the code is invalidated and verification is locked for
+/// VERIFICATION_LOCKOUT_MINUTES; a new code must be requested afterwards.
 pub async fn verify_email(
     State(pool): State<PgPool>,
     Json(req): Json<VerifyEmailRequest>,
 ) -> Result<(StatusCode, Json<VerifyEmailResponse>)> {
-    // Find user by email and verification code
-    let user_result = sqlx::query_as::<_, (Uuid,)>(
+    let mut tx = pool.begin().await?;
+
+    // Lock the user row so concurrent guesses are counted one at a time
+    let user_result = sqlx::query_as::<_, (Uuid, Option<String>, i32, bool, bool)>(
         r#"
-        SELECT id FROM users
-        WHERE email = $1 AND verification_code = $2 AND email_verified = false
+        SELECT id,
+               verification_code_hash,
+               verification_attempts,
+               COALESCE(verification_locked_until > NOW(), false) AS locked,
+               COALESCE(verification_code_sent_at <= NOW() - INTERVAL '1 minute' * $2, true) AS expired
+        FROM users
+        WHERE email = $1 AND email_verified = false
+        FOR UPDATE
         "#,
     )
     .bind(&req.email)
-    .bind(&req.code)
-    .fetch_optional(&pool)
+    .bind(verification_code_ttl_minutes())
+    .fetch_optional(&mut *tx)
     .await?;
 
-    let user_id = match user_result {
-        Some((id,)) => id,
+    let (user_id, code_hash, attempts, locked, expired) = match user_result {
+        Some(user) => user,
         None => {
-            warn!("Invalid verification code for email: {}", req.email);
+            warn!("Verification attempt for unknown or verified email: {}", req.email);
             return Err(AppError::InvalidVerificationCode);
         }
     };
 
-    // Update user to mark email as verified and clear verification code
+    if locked {
+        warn!("Verification attempt while locked out for email: {}", req.email);
+        return Err(AppError::VerificationLocked);
+    }
+
+    let code_hash = match code_hash {
+        Some(code_hash) if !expired => code_hash,
+        _ => {
+            warn!("Expired verification code for email: {}", req.email);
+            return Err(AppError::VerificationCodeExpired);
+        }
+    };
+
+    if !verify_verification_code(&req.code, &code_hash)? {
+        let attempts = attempts + 1;
+        let max_attempts = verification_max_attempts();
+
+        if attempts >= max_attempts {
+            // Invalidate the code and lock verification for a while
+            sqlx::query(
+                r#"
+                UPDATE users
+                SET verification_code_hash = NULL,
+                    verification_attempts = 0,
+                    verification_locked_until = NOW() + INTERVAL '1 minute' * $2
+                WHERE id = $1
+                "#,
+            )
+            .bind(user_id)
+            .bind(verification_lockout_minutes())
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            warn!(
+                "Verification locked for email {} after {} failed attempts",
+                req.email, attempts
+            );
+            return Err(AppError::VerificationLocked);
+        }
+
+        sqlx::query("UPDATE users SET verification_attempts = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(attempts)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        warn!(
+            "Invalid verification code for email: {} (attempt {}/{})",
+            req.email, attempts, max_attempts
+        );
+        return Err(AppError::InvalidVerificationCode);
+    }
+
+    // Mark email as verified and clear verification state
     sqlx::query(
         r#"
         UPDATE users
-        SET email_verified = true, verification_code = NULL
+        SET email_verified = true,
+            verification_code_hash = NULL,
+            verification_code_sent_at = NULL,
+            verification_attempts = 0,
+            verification_locked_until = NULL
         WHERE id = $1
         "#,
     )
     .bind(user_id)
-    .execute(&pool)
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     info!("Email verified for user: {} (ID: {})", req.email, user_id);
 
     let response = VerifyEmailResponse {
@@ -122,15 +217,101 @@ pub async fn verify_email(
     Ok((StatusCode::OK, Json(response)))
 }
 
-/// Login user - validates credentials and returns JWT token
+/// Send a new verification code, replacing the previous one
+///
+/// Limited to one email per VERIFICATION_RESEND_COOLDOWN_SECONDS and skipped while
+/// verification is locked out. Unknown or already verified emails, and skipped
+/// resends, get the same response as a successful resend so accounts can't be
+/// enumerated.
+pub async fn resend_verification(
+    State(pool): State<PgPool>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    State(DevMode(dev_mode)): State<DevMode>,
+    Json(req): Json<ResendVerificationRequest>,
+) -> Result<(StatusCode, Json<ResendVerificationResponse>)> {
+    let message = "If the account exists and is not yet verified, a new verification code has been sent.".to_string();
+
+    let mut tx = pool.begin().await?;
+
+    let user_result = sqlx::query_as::<_, (Uuid, bool, bool)>(
+        r#"
+        SELECT id,
+               COALESCE(verification_locked_until > NOW(), false) AS locked,
+               COALESCE(verification_code_sent_at > NOW() - INTERVAL '1 second' * $2, false) AS cooling_down
+        FROM users
+        WHERE email = $1 AND email_verified = false
+        FOR UPDATE
+        "#,
+    )
+    .bind(&req.email)
+    .bind(verification_resend_cooldown_seconds())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let not_sent = ResendVerificationResponse {
+        message: message.clone(),
+        verification_code: None,
+    };
+
+    let (user_id, locked, cooling_down) = match user_result {
+        Some(user) => user,
+        None => {
+            info!("Verification resend requested for unknown or verified email: {}", req.email);
+            return Ok((StatusCode::OK, Json(not_sent)));
+        }
+    };
+
+    if locked {
+        warn!("Verification resend for {} while verification is locked", req.email);
+        return Ok((StatusCode::OK, Json(not_sent)));
+    }
+
+    if cooling_down {
+        warn!("Verification resend for {} within cooldown", req.email);
+        return Ok((StatusCode::OK, Json(not_sent)));
+    }
+
+    let verification_code = generate_verification_code();
+    let verification_code_hash = hash_verification_code(&verification_code)?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET verification_code_hash = $2,
+            verification_code_sent_at = NOW(),
+            verification_attempts = 0,
+            verification_locked_until = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&verification_code_hash)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!("Verification code resent for user: {} (ID: {})", req.email, user_id);
+
+    send_in_background(mailer, verification_code_email(&req.email, &verification_code));
+
+    let response = ResendVerificationResponse {
+        message,
+        verification_code: dev_mode.then_some(verification_code),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Login user - validates credentials and returns an access token plus a refresh token
 pub async fn login(
     State(pool): State<PgPool>,
     Json(req): Json<LoginRequest>,
 ) -> Result<(StatusCode, Json<LoginResponse>)> {
     // Find user by email
-    let user = sqlx::query_as::<_, (Uuid, String, bool, String)>(
+    let user = sqlx::query_as::<_, (Uuid, String, bool, UserRole, String)>(
         r#"
-        SELECT id, email, email_verified, password_hash
+        SELECT id, email, email_verified, role, password_hash
         FROM users
         WHERE email = $1
         "#,
@@ -139,7 +320,7 @@ pub async fn login(
     .fetch_optional(&pool)
     .await?;
 
-    let (user_id, email, email_verified, password_hash) = match user {
+    let (user_id, email, email_verified, role, password_hash) = match user {
         Some(user) => user,
         None => {
             warn!("Login attempt with invalid email: {}", req.email);
@@ -160,20 +341,211 @@ pub async fn login(
         return Err(AppError::EmailNotVerified);
     }
 
-    // Generate JWT token
-    let token = generate_token(&user_id.to_string(), &email)?;
+    // Start a session and issue its access token
+    let (session_id, refresh_token) = create_session(&pool, user_id).await?;
+    let token = generate_token(&user_id.to_string(), &email, role, session_id)?;
 
-    info!("User logged in: {} (ID: {})", email, user_id);
+    info!("User logged in: {} (ID: {}, session {})", email, user_id, session_id);
 
     let response = LoginResponse {
         token,
+        refresh_token,
         user: UserInfo {
             id: user_id,
             email,
             email_verified,
+            role,
         },
     };
 
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// Email a single-use password reset link
+///
+/// Any earlier unused token is replaced. Unknown and unverified emails, and
+/// requests within PASSWORD_RESET_COOLDOWN_SECONDS of the last one, get the same
+/// response without an email so accounts can't be enumerated.
+pub async fn forgot_password(
+    State(pool): State<PgPool>,
+    State(mailer): State<Arc<dyn Mailer>>,
+    State(DevMode(dev_mode)): State<DevMode>,
+    Json(req): Json<ForgotPasswordRequest>,
+) -> Result<(StatusCode, Json<ForgotPasswordResponse>)> {
+    let message = "If an account exists for this email, a password reset link has been sent.".to_string();
+
+    let mut tx = pool.begin().await?;
+
+    let user_result = sqlx::query_as::<_, (Uuid, bool)>(
+        r#"
+        SELECT u.id,
+               EXISTS (
+                   SELECT 1
+                   FROM password_reset_tokens prt
+                   WHERE prt.user_id = u.id
+                     AND prt.created_at > NOW() - INTERVAL '1 second' * $2
+               ) AS cooling_down
+        FROM users u
+        WHERE u.email = $1 AND u.email_verified = true
+        FOR UPDATE OF u
+        "#,
+    )
+    .bind(&req.email)
+    .bind(password_reset_cooldown_seconds())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match user_result {
+        Some((user_id, false)) => user_id,
+        Some((_, true)) => {
+            warn!("Password reset for {} requested within cooldown", req.email);
+            return Ok((StatusCode::OK, Json(ForgotPasswordResponse { message, reset_token: None })));
+        }
+        None => {
+            info!("Password reset requested for unknown or unverified email: {}", req.email);
+            return Ok((StatusCode::OK, Json(ForgotPasswordResponse { message, reset_token: None })));
+        }
+    };
+
+    // Only the newest link works
+    sqlx::query("DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let reset_token = generate_secure_token();
+
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, NOW() + INTERVAL '1 minute' * $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_secure_token(&reset_token))
+    .bind(password_reset_token_ttl_minutes())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!("Password reset requested for user: {} (ID: {})", req.email, user_id);
+
+    send_in_background(mailer, password_reset_email(&req.email, &reset_token));
+
+    let response = ForgotPasswordResponse {
+        message,
+        reset_token: dev_mode.then_some(reset_token),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Set a new password using a reset token
+///
+/// The token is consumed and all of the user's sessions are revoked, so every
+/// device has to log in again.
+pub async fn reset_password(
+    State(pool): State<PgPool>,
+    Json(req): Json<ResetPasswordRequest>,
+) -> Result<(StatusCode, Json<ResetPasswordResponse>)> {
+    validate_password(&req.new_password)?;
+
+    let password_hash = hash_password(&req.new_password)?;
+
+    let mut tx = pool.begin().await?;
+
+    // Consume the token; a concurrent reset with the same token finds it used
+    let user_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1
+          AND used_at IS NULL
+          AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_secure_token(&req.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        warn!("Password reset attempted with invalid or expired token");
+        AppError::InvalidResetToken
+    })?;
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $2
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&password_hash)
+    .execute(&mut *tx)
+    .await?;
+
+    revoke_all_sessions(&mut *tx, user_id).await?;
+
+    tx.commit().await?;
+
+    info!("Password reset for user {}, existing sessions revoked", user_id);
+
+    let response = ResetPasswordResponse {
+        message: "Password reset successfully. Please log in with your new password.".to_string(),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Exchange a refresh token for a new access token and refresh token
+pub async fn refresh(
+    State(pool): State<PgPool>,
+    Json(req): Json<RefreshRequest>,
+) -> Result<(StatusCode, Json<RefreshResponse>)> {
+    let session = rotate_session(&pool, &req.refresh_token).await?;
+    let token = generate_token(&session.user_id.to_string(), &session.email, session.role, session.session_id)?;
+
+    let response = RefreshResponse {
+        token,
+        refresh_token: session.refresh_token,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Log out the session the access token belongs to
+pub async fn logout(
+    State(pool): State<PgPool>,
+    AuthUser { user_id, session_id, .. }: AuthUser,
+) -> Result<(StatusCode, Json<LogoutResponse>)> {
+    let mut conn = pool.acquire().await?;
+    let revoked = revoke_session(&mut conn, session_id).await?;
+
+    info!("User {} logged out of session {}", user_id, session_id);
+
+    let response = LogoutResponse {
+        message: "Logged out.".to_string(),
+        sessions_revoked: u64::from(revoked),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Log out every session of the current user
+pub async fn logout_all(
+    State(pool): State<PgPool>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<(StatusCode, Json<LogoutResponse>)> {
+    let sessions_revoked = revoke_all_sessions(&pool, user_id).await?;
+
+    info!("User {} logged out of all {} sessions", user_id, sessions_revoked);
+
+    let response = LogoutResponse {
+        message: "Logged out of all devices.".to_string(),
+        sessions_revoked,
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -1,9 +1,10 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::StatusCode,
     response::Json,
 };
 use chrono::{Duration, Utc};
+use serde_json::json;
 use sqlx::PgPool;
 use std::env;
 use tracing::{info, error};
@@ -11,10 +12,12 @@ use uuid::Uuid;
 
 use crate::error::{AppError, Result};
 use crate::models::game::{CreateGameRequest, Game, ListGamesResponse, SportType};
-use crate::utils::auth::validate_admin_key;
+use crate::utils::audit::record_admin_action;
+use crate::utils::auth::AdminCaller;
+use crate::utils::permissions::CanManageGames;
 
 /// Parse sport type string to SportType enum
-fn parse_sport_type(s: &str) -> Result<SportType> {
+pub fn parse_sport_type(s: &str) -> Result<SportType> {
     match s.to_lowercase().as_str() {
         "football" => Ok(SportType::Football),
         "basketball" => Ok(SportType::Basketball),
@@ -26,14 +29,10 @@ fn parse_sport_type(s: &str) -> Result<SportType> {
 /// Create a new game (admin endpoint)
 pub async fn create_game(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    admin: AdminCaller<CanManageGames>,
     Json(req): Json<CreateGameRequest>,
 ) -> Result<(StatusCode, Json<Game>)> {
     info!("Received create game request: sport_type={}, name={}", req.sport_type, req.name);
-    
-    // Validate admin API key
-    validate_admin_key(&headers)?;
-    info!("Admin key validated");
 
     // Validate inputs
     if req.name.trim().is_empty() {
@@ -80,10 +79,10 @@ pub async fn create_game(
         RETURNING id, sport_type, name, game_time, cutoff_time
         "#,
     )
-    .bind(&sport_type)
+    .bind(sport_type)
     .bind(&req.name)
-    .bind(&req.game_time)
-    .bind(&cutoff_time)
+    .bind(req.game_time)
+    .bind(cutoff_time)
     .fetch_one(&pool)
     .await
     .map_err(|e| {
@@ -93,18 +92,25 @@ pub async fn create_game(
 
     info!("Game created: {} ({})", game.name, game.id);
 
+    record_admin_action(
+        &pool,
+        &admin.actor,
+        "game.create",
+        "game",
+        Some(game.id.to_string()),
+        json!({ "name": game.name, "game_time": game.game_time }),
+    )
+    .await?;
+
     Ok((StatusCode::CREATED, Json(game)))
 }
 
 /// Delete a game by ID (admin endpoint)
 pub async fn delete_game(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    admin: AdminCaller<CanManageGames>,
     Path(game_id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    // Validate admin API key
-    validate_admin_key(&headers)?;
-
     // Check if game exists
     let game = sqlx::query_scalar::<_, Option<Uuid>>(
         "SELECT id FROM games WHERE id = $1",
@@ -125,6 +131,8 @@ pub async fn delete_game(
 
     info!("Game deleted: {}", game_id);
 
+    record_admin_action(&pool, &admin.actor, "game.delete", "game", Some(game_id.to_string()), json!({})).await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
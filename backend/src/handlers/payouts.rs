@@ -0,0 +1,97 @@
+use axum::{
+    extract::State,
+    response::Json,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::error::{AppError, Result};
+use crate::models::payout::{ListPayoutsResponse, Payout, PayoutOnboardingResponse};
+use crate::utils::auth::AuthUser;
+use crate::utils::payment_gateway::PaymentGateway;
+
+/// Start (or resume) Stripe Connect onboarding for a seller
+///
+/// Creates the seller's connected account on first use and returns a fresh
+/// onboarding link. Sale proceeds are only transferred once onboarding is done.
+pub async fn start_onboarding(
+    State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<PayoutOnboardingResponse>> {
+    let (email, existing_account_id) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT email, stripe_account_id FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let stripe_account_id = match existing_account_id {
+        Some(account_id) => account_id,
+        None => {
+            let account_id = payments.create_connected_account(&email).await?;
+
+            // Only the first concurrent request gets to store its account
+            let stored = sqlx::query_scalar::<_, String>(
+                r#"
+                UPDATE users
+                SET stripe_account_id = $2
+                WHERE id = $1
+                  AND stripe_account_id IS NULL
+                RETURNING stripe_account_id
+                "#,
+            )
+            .bind(user_id)
+            .bind(&account_id)
+            .fetch_optional(&pool)
+            .await?;
+
+            match stored {
+                Some(account_id) => {
+                    info!("Stored connected account {} for user {}", account_id, user_id);
+                    account_id
+                }
+                None => sqlx::query_scalar::<_, String>(
+                    "SELECT stripe_account_id FROM users WHERE id = $1",
+                )
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await?,
+            }
+        }
+    };
+
+    let onboarding_url = payments.create_account_onboarding_link(&stripe_account_id).await?;
+
+    info!("Created onboarding link for user {} (account {})", user_id, stripe_account_id);
+
+    Ok(Json(PayoutOnboardingResponse {
+        stripe_account_id,
+        onboarding_url,
+    }))
+}
+
+/// List the seller's payouts (authenticated endpoint)
+pub async fn list_payouts(
+    State(pool): State<PgPool>,
+    AuthUser { user_id, .. }: AuthUser,
+) -> Result<Json<ListPayoutsResponse>> {
+    let payouts = sqlx::query_as::<_, Payout>(
+        r#"
+        SELECT id, ticket_id, seller_id, amount, platform_fee, currency,
+               status, stripe_transfer_id, created_at, updated_at
+        FROM payouts
+        WHERE seller_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await?;
+
+    info!("Listed {} payouts for seller {}", payouts.len(), user_id);
+
+    Ok(Json(ListPayoutsResponse { payouts }))
+}
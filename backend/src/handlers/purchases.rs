@@ -0,0 +1,84 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::error::{AppError, Result};
+use crate::handlers::tickets::total_reservation_window_minutes;
+use crate::models::purchase::{ListPurchasesResponse, Purchase, PurchasesQuery};
+use crate::models::ticket::TicketStatus;
+use crate::utils::auth::AuthUser;
+
+/// List the caller's reservations and purchases (authenticated endpoint)
+///
+/// Built from the tickets the caller reserved, with the payment intent of that
+/// reservation and when the ticket was transferred. Reservations that lapsed
+/// unpaid are left out. Newest reservation first.
+pub async fn list_purchases(
+    State(pool): State<PgPool>,
+    AuthUser { user_id: buyer_id, .. }: AuthUser,
+    Query(params): Query<PurchasesQuery>,
+) -> Result<Json<ListPurchasesResponse>> {
+    let status = match params.status.as_deref().map(str::to_lowercase).as_deref() {
+        None => None,
+        Some("reserved") => Some(TicketStatus::Reserved),
+        Some("paid") => Some(TicketStatus::Paid),
+        Some("sold") => Some(TicketStatus::Sold),
+        Some("refunding") => Some(TicketStatus::Refunding),
+        Some("refunded") => Some(TicketStatus::Refunded),
+        Some(_) => {
+            return Err(AppError::BadRequest(
+                "Invalid status filter. Must be one of: reserved, paid, sold, refunding, refunded".to_string(),
+            ))
+        }
+    };
+
+    let purchases = sqlx::query_as::<_, Purchase>(
+        r#"
+        SELECT t.id AS ticket_id, t.game_id, t.event_name, t.event_date,
+               t.level, t.seat_section, t.seat_row, t.seat_number,
+               COALESCE(t.price_at_reservation, t.price) AS price,
+               t.status, t.reserved_at,
+               r.expires_at AS reservation_expires_at,
+               GREATEST(CEIL(EXTRACT(EPOCH FROM r.expires_at - NOW())), 0)::BIGINT AS reservation_seconds_left,
+               pi.status AS payment_status,
+               (
+                   SELECT MAX(e.created_at)
+                   FROM ticket_events e
+                   WHERE e.ticket_id = t.id
+                     AND e.to_status = 'sold'
+               ) AS sold_at
+        FROM tickets t
+        CROSS JOIN LATERAL (
+            SELECT CASE
+                       WHEN t.status = 'reserved' THEN t.reserved_at + INTERVAL '1 minute' * $3::BIGINT
+                   END AS expires_at
+        ) r
+        LEFT JOIN LATERAL (
+            SELECT status
+            FROM payment_intents
+            WHERE ticket_id = t.id
+              AND buyer_id = t.reserved_by
+              AND created_at >= t.reserved_at
+            ORDER BY created_at DESC
+            LIMIT 1
+        ) pi ON TRUE
+        WHERE t.reserved_by = $1
+          AND t.reserved_at IS NOT NULL
+          AND ($2::ticket_status IS NULL OR t.status = $2)
+          AND (r.expires_at IS NULL OR r.expires_at > NOW())
+        ORDER BY t.reserved_at DESC
+        "#,
+    )
+    .bind(buyer_id)
+    .bind(status)
+    .bind(total_reservation_window_minutes())
+    .fetch_all(&pool)
+    .await?;
+
+    info!("Listed {} purchases for buyer {}", purchases.len(), buyer_id);
+
+    Ok(Json(ListPurchasesResponse { purchases }))
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::error::{AppError, Result};
+use crate::models::stripe_discrepancy::{
+    ListDiscrepanciesQuery, ListDiscrepanciesResponse, ReconciliationSummary, RunReconciliationQuery,
+    StripeDiscrepancy,
+};
+use crate::utils::audit::record_admin_action;
+use crate::utils::auth::AdminCaller;
+use crate::utils::payment_gateway::PaymentGateway;
+use crate::utils::permissions::CanManagePayments;
+use crate::utils::reconciliation::{reconcile_payment_intents, reconciliation_lookback_hours};
+
+/// Run Stripe reconciliation now (admin only)
+pub async fn run_reconciliation(
+    State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    admin: AdminCaller<CanManagePayments>,
+    Query(query): Query<RunReconciliationQuery>,
+) -> Result<Json<ReconciliationSummary>> {
+    let lookback_hours = match query.lookback_hours {
+        Some(hours) if hours <= 0 => {
+            return Err(AppError::BadRequest("lookback_hours must be positive".to_string()));
+        }
+        Some(hours) => hours,
+        None => reconciliation_lookback_hours(),
+    };
+
+    info!("Admin triggered Stripe reconciliation (lookback: {} hours)", lookback_hours);
+
+    let summary = reconcile_payment_intents(&pool, payments.as_ref(), lookback_hours).await?;
+
+    record_admin_action(
+        &pool,
+        &admin.actor,
+        "reconciliation.run",
+        "reconciliation",
+        None,
+        json!({
+            "lookback_hours": lookback_hours,
+            "checked": summary.checked,
+            "discrepancies": summary.discrepancies,
+            "resolved": summary.resolved,
+            "expired": summary.expired,
+        }),
+    )
+    .await?;
+
+    Ok(Json(summary))
+}
+
+/// List Stripe discrepancies found by reconciliation (admin only)
+///
+/// Only open discrepancies unless `include_resolved=true`.
+pub async fn list_discrepancies(
+    State(pool): State<PgPool>,
+    _admin: AdminCaller<CanManagePayments>,
+    Query(query): Query<ListDiscrepanciesQuery>,
+) -> Result<Json<ListDiscrepanciesResponse>> {
+    let discrepancies = sqlx::query_as::<_, StripeDiscrepancy>(
+        r#"
+        SELECT id, payment_intent_id, ticket_id, kind, local_value, stripe_value,
+               first_seen_at, last_seen_at, resolved_at, expired
+        FROM stripe_discrepancies
+        WHERE $1 OR resolved_at IS NULL
+        ORDER BY last_seen_at DESC
+        LIMIT 200
+        "#,
+    )
+    .bind(query.include_resolved.unwrap_or(false))
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ListDiscrepanciesResponse { discrepancies }))
+}
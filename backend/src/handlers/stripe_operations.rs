@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::stripe_operation::{
+    ListStripeOperationsQuery, ListStripeOperationsResponse, StripeOperation, StripeOperationStatus,
+};
+use crate::utils::audit::record_admin_action;
+use crate::utils::auth::AdminCaller;
+use crate::utils::permissions::CanManagePayments;
+
+/// Parse Stripe operation status string to StripeOperationStatus enum
+fn parse_operation_status(s: &str) -> Result<StripeOperationStatus> {
+    match s.to_lowercase().as_str() {
+        "pending" => Ok(StripeOperationStatus::Pending),
+        "succeeded" => Ok(StripeOperationStatus::Succeeded),
+        "dead" => Ok(StripeOperationStatus::Dead),
+        "cancelled" => Ok(StripeOperationStatus::Cancelled),
+        _ => Err(AppError::BadRequest(
+            "Invalid status. Must be one of: pending, succeeded, dead, cancelled".to_string(),
+        )),
+    }
+}
+
+/// List Stripe outbox operations (admin only)
+///
+/// Defaults to dead-lettered operations, which need manual follow-up.
+pub async fn list_stripe_operations(
+    State(pool): State<PgPool>,
+    _admin: AdminCaller<CanManagePayments>,
+    Query(query): Query<ListStripeOperationsQuery>,
+) -> Result<Json<ListStripeOperationsResponse>> {
+    let status = match query.status.as_deref() {
+        Some(status) => parse_operation_status(status)?,
+        None => StripeOperationStatus::Dead,
+    };
+
+    let operations = sqlx::query_as::<_, StripeOperation>(
+        r#"
+        SELECT id, payment_intent_id, operation, status, attempts, last_error,
+               next_attempt_at, created_at, updated_at
+        FROM stripe_operations
+        WHERE status = $1
+        ORDER BY updated_at DESC
+        LIMIT 200
+        "#,
+    )
+    .bind(status)
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(Json(ListStripeOperationsResponse { operations }))
+}
+
+/// Put a dead-lettered Stripe operation back in the queue (admin only)
+pub async fn retry_stripe_operation(
+    State(pool): State<PgPool>,
+    admin: AdminCaller<CanManagePayments>,
+    Path(operation_id): Path<Uuid>,
+) -> Result<Json<StripeOperation>> {
+    let operation = sqlx::query_as::<_, StripeOperation>(
+        r#"
+        UPDATE stripe_operations
+        SET status = 'pending',
+            attempts = 0,
+            next_attempt_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1
+          AND status = 'dead'
+        RETURNING id, payment_intent_id, operation, status, attempts, last_error,
+                  next_attempt_at, created_at, updated_at
+        "#,
+    )
+    .bind(operation_id)
+    .fetch_optional(&pool)
+    .await?;
+
+    match operation {
+        Some(operation) => {
+            info!("Stripe operation {} requeued by admin", operation_id);
+            record_admin_action(
+                &pool,
+                &admin.actor,
+                "stripe_operation.retry",
+                "stripe_operation",
+                Some(operation_id.to_string()),
+                json!({
+                    "payment_intent_id": operation.payment_intent_id,
+                    "operation": operation.operation,
+                }),
+            )
+            .await?;
+            Ok(Json(operation))
+        }
+        None => {
+            let exists = sqlx::query_scalar::<_, Uuid>("SELECT id FROM stripe_operations WHERE id = $1")
+                .bind(operation_id)
+                .fetch_optional(&pool)
+                .await?;
+
+            match exists {
+                Some(_) => Err(AppError::Conflict(
+                    "Only dead-lettered operations can be retried".to_string(),
+                )),
+                None => Err(AppError::NotFound("Stripe operation not found".to_string())),
+            }
+        }
+    }
+}
@@ -3,30 +3,43 @@ use axum::{
     http::{HeaderMap, StatusCode},
     response::Json,
 };
-use sqlx::PgPool;
-use std::env;
+use serde_json::json;
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use std::sync::Arc;
 use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
+use crate::handlers::games::parse_sport_type;
+use crate::models::game::{Game, SportType};
+use crate::models::payment_intent::PaymentIntentStatus;
+use crate::models::stripe_operation::StripeOperationType;
 use crate::models::ticket::{
-    ClaimTicketRequest, ClaimTicketResponse, CreateTicketRequest, ListTicketsResponse,
-    MyListingsQuery, ReserveTicketResponse, Ticket, TicketStatus, TicketStatusResponse,
+    CheckoutResponse, ClaimTicketRequest, ClaimTicketResponse, CreateTicketRequest, ListTicketsQuery,
+    ListTicketsResponse, MyListingsQuery, ReserveTicketResponse, Ticket, TicketAvailability, TicketDetailResponse, TicketSearchResponse,
+    TicketStatus, TicketStatusResponse, UpdateTicketRequest,
 };
-use crate::utils::auth::{acquire_bot_permit, validate_bot_key};
-use crate::utils::jwt::extract_user_id;
-use chrono::Utc;
+use crate::models::ticket_event::{TicketEvent, TicketTimelineResponse};
+use crate::utils::audit::record_admin_action;
+use crate::utils::auth::{Actor, AdminOrBotCaller, AuthUser, BotCaller};
+use crate::utils::config::env_or;
+use crate::utils::payment_gateway::PaymentGateway;
+use crate::utils::permissions::{CanMarkTicketsSold, CanVerifyTickets, Permission};
+use crate::utils::payouts::{process_payout, queue_payouts};
+use crate::utils::refunds::{process_refund, request_refund};
+use crate::utils::stripe_outbox::{enqueue_stripe_operation, process_stripe_operation};
+use crate::utils::ticket_events::{begin_ticket_transition, TicketActor};
+use crate::utils::ticket_state::{apply_transition, apply_transition_on, Transition};
+use chrono::{DateTime, Utc};
 
 /// Create a new ticket listing
 pub async fn create_ticket(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    AuthUser { user_id: seller_id, .. }: AuthUser,
     Json(req): Json<CreateTicketRequest>,
 ) -> Result<(StatusCode, Json<Ticket>)> {
     info!("Received create ticket request for game_id: {}", req.game_id);
 
-    // Extract user_id from JWT token
-    let seller_id = extract_user_id(&headers)?;
     info!("Seller ID: {}", seller_id);
 
     // Validate price
@@ -49,7 +62,7 @@ pub async fn create_ticket(
     let game_result = sqlx::query_as::<_, (String, chrono::DateTime<chrono::Utc>)>(
         "SELECT name, game_time FROM games WHERE id = $1",
     )
-    .bind(&req.game_id)
+    .bind(req.game_id)
     .fetch_optional(&pool)
     .await?;
 
@@ -61,10 +74,9 @@ pub async fn create_ticket(
     info!("Found game: {} at {}", event_name, event_date);
 
     // Get transfer deadline hours from environment variable (default: 24 hours)
-    let transfer_deadline_hours: i64 = env::var("TRANSFER_DEADLINE_HOURS")
-        .unwrap_or_else(|_| "24".to_string())
-        .parse()
-        .unwrap_or(24);
+    let transfer_deadline_hours: i64 = env_or("TRANSFER_DEADLINE_HOURS", 24);
+
+    let mut tx = begin_ticket_transition(&pool, &TicketActor::Seller(seller_id), "listed by seller").await?;
 
     // Insert ticket with status='unverified' and calculate transfer_deadline
     let ticket = sqlx::query_as::<_, Ticket>(
@@ -80,61 +92,407 @@ pub async fn create_ticket(
                   transfer_deadline, price_at_reservation, reserved_at, reserved_by, created_at, updated_at
         "#,
     )
-    .bind(&seller_id)
-    .bind(&req.game_id)
+    .bind(seller_id)
+    .bind(req.game_id)
     .bind(&event_name)
-    .bind(&event_date)
+    .bind(event_date)
     .bind(&req.level)
     .bind(&req.seat_section)
     .bind(&req.seat_row)
     .bind(&req.seat_number)
-    .bind(&req.price)
-    .bind(&TicketStatus::Unverified)
-    .bind(&transfer_deadline_hours)
-    .fetch_one(&pool)
+    .bind(req.price)
+    .bind(TicketStatus::Unverified)
+    .bind(transfer_deadline_hours)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         error!("Failed to create ticket: {}", e);
         e
     })?;
 
+    tx.commit().await?;
+
     info!("Ticket created: {} for game {}", ticket.id, ticket.game_id);
 
     Ok((StatusCode::CREATED, Json(ticket)))
 }
 
-/// List all verified tickets (public endpoint, no authentication required)
+/// How long a reservation holds a ticket, from TOTAL_RESERVATION_WINDOW_MINUTES (default: 7)
+pub fn total_reservation_window_minutes() -> i64 {
+    env_or("TOTAL_RESERVATION_WINDOW_MINUTES", 7)
+}
+
+/// Page size of the ticket search when `limit` isn't given
+const DEFAULT_TICKET_PAGE_SIZE: i64 = 50;
+
+/// Largest `limit` the ticket search accepts
+const MAX_TICKET_PAGE_SIZE: i64 = 100;
+
+/// Sort orders of the ticket search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TicketSort {
+    /// Soonest event first
+    Date,
+    /// Cheapest first
+    Price,
+    /// Most expensive first
+    PriceDesc,
+    /// Most recently listed first
+    Newest,
+}
+
+impl TicketSort {
+    fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "date" => Ok(TicketSort::Date),
+            "price" => Ok(TicketSort::Price),
+            "price_desc" => Ok(TicketSort::PriceDesc),
+            "newest" => Ok(TicketSort::Newest),
+            _ => Err(AppError::BadRequest(
+                "Invalid sort. Must be one of: date, price, price_desc, newest".to_string(),
+            )),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            TicketSort::Date => "date",
+            TicketSort::Price => "price",
+            TicketSort::PriceDesc => "price_desc",
+            TicketSort::Newest => "newest",
+        }
+    }
+
+    /// Sort column, and whether it is sorted descending
+    ///
+    /// Ties are broken by ticket ID in the same direction, which makes every
+    /// position in the order unique so a cursor can resume right after it.
+    fn column(self) -> (&'static str, bool) {
+        match self {
+            TicketSort::Date => ("event_date", false),
+            TicketSort::Price => ("price", false),
+            TicketSort::PriceDesc => ("price", true),
+            TicketSort::Newest => ("created_at", true),
+        }
+    }
+}
+
+/// Position after the last ticket of a page: `<sort>.<sort value>.<ticket id>`
+///
+/// Dates are encoded as Unix microseconds, prices as cents.
+fn encode_ticket_cursor(sort: TicketSort, ticket: &Ticket) -> String {
+    let value = match sort {
+        TicketSort::Date => ticket.event_date.timestamp_micros(),
+        TicketSort::Price | TicketSort::PriceDesc => ticket.price.into(),
+        TicketSort::Newest => ticket.created_at.timestamp_micros(),
+    };
+
+    format!("{}.{}.{}", sort.as_str(), value, ticket.id)
+}
+
+/// Parse a cursor, which must have been issued for the same sort order
+fn decode_ticket_cursor(sort: TicketSort, cursor: &str) -> Result<(i64, Uuid)> {
+    let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+
+    let mut parts = cursor.splitn(3, '.');
+    let (cursor_sort, value, id) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(cursor_sort), Some(value), Some(id)) => (cursor_sort, value, id),
+        _ => return Err(invalid()),
+    };
+
+    if cursor_sort != sort.as_str() {
+        return Err(AppError::BadRequest("Cursor was issued for a different sort order".to_string()));
+    }
+
+    let value = value.parse::<i64>().map_err(|_| invalid())?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+
+    Ok((value, id))
+}
+
+/// Validated ticket search filters
+struct TicketFilters {
+    name_pattern: Option<String>,
+    game_id: Option<Uuid>,
+    sport_type: Option<SportType>,
+    min_price: Option<i32>,
+    max_price: Option<i32>,
+    level: Option<String>,
+    section: Option<String>,
+    date_from: Option<DateTime<Utc>>,
+    date_to: Option<DateTime<Utc>>,
+}
+
+impl TicketFilters {
+    fn from_query(params: &ListTicketsQuery) -> Result<Self> {
+        if let (Some(min), Some(max)) = (params.min_price, params.max_price) {
+            if min > max {
+                return Err(AppError::BadRequest("min_price cannot exceed max_price".to_string()));
+            }
+        }
+
+        if let (Some(from), Some(to)) = (params.date_from, params.date_to) {
+            if from > to {
+                return Err(AppError::BadRequest("date_from cannot be after date_to".to_string()));
+            }
+        }
+
+        let non_empty = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+
+        // LIKE wildcards in the search text are matched literally
+        let name_pattern = non_empty(&params.q).map(|q| {
+            let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        });
+
+        Ok(TicketFilters {
+            name_pattern,
+            game_id: params.game_id,
+            sport_type: non_empty(&params.sport_type)
+                .map(|sport_type| parse_sport_type(&sport_type))
+                .transpose()?,
+            min_price: params.min_price,
+            max_price: params.max_price,
+            level: non_empty(&params.level),
+            section: non_empty(&params.section),
+            date_from: params.date_from,
+            date_to: params.date_to,
+        })
+    }
+
+    /// Append `FROM tickets WHERE ...` for tickets on sale that match
+    ///
+    /// The status is a literal rather than a bind parameter so the planner can use
+    /// the partial `idx_tickets_verified_status` index.
+    fn push_from<'a>(&'a self, query: &mut QueryBuilder<'a, Postgres>) {
+        query.push(" FROM tickets WHERE status = 'verified'");
+
+        if let Some(name_pattern) = &self.name_pattern {
+            query.push(" AND event_name ILIKE ").push_bind(name_pattern);
+        }
+        if let Some(game_id) = self.game_id {
+            query.push(" AND game_id = ").push_bind(game_id);
+        }
+        if let Some(sport_type) = self.sport_type {
+            query
+                .push(" AND game_id IN (SELECT id FROM games WHERE sport_type = ")
+                .push_bind(sport_type)
+                .push(")");
+        }
+        if let Some(min_price) = self.min_price {
+            query.push(" AND price >= ").push_bind(min_price);
+        }
+        if let Some(max_price) = self.max_price {
+            query.push(" AND price <= ").push_bind(max_price);
+        }
+        if let Some(level) = &self.level {
+            query.push(" AND level = ").push_bind(level);
+        }
+        if let Some(section) = &self.section {
+            query.push(" AND seat_section = ").push_bind(section);
+        }
+        if let Some(date_from) = self.date_from {
+            query.push(" AND event_date >= ").push_bind(date_from);
+        }
+        if let Some(date_to) = self.date_to {
+            query.push(" AND event_date <= ").push_bind(date_to);
+        }
+    }
+}
+
+/// Search tickets available for sale (public endpoint)
+///
+/// Filters by event name, game, sport, price, level, section and event date, and
+/// pages through the results with a cursor. `total` counts every matching ticket.
 pub async fn list_tickets(
     State(pool): State<PgPool>,
-) -> Result<Json<ListTicketsResponse>> {
-    // Get all tickets with status='verified' (available for sale)
-    let tickets = sqlx::query_as::<_, Ticket>(
+    Query(params): Query<ListTicketsQuery>,
+) -> Result<Json<TicketSearchResponse>> {
+    let filters = TicketFilters::from_query(&params)?;
+
+    let sort = match params.sort.as_deref() {
+        Some(sort) => TicketSort::parse(sort)?,
+        None => TicketSort::Date,
+    };
+
+    let limit = params.limit.unwrap_or(DEFAULT_TICKET_PAGE_SIZE);
+    if !(1..=MAX_TICKET_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_TICKET_PAGE_SIZE
+        )));
+    }
+
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_ticket_cursor(sort, cursor))
+        .transpose()?;
+
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*)");
+    filters.push_from(&mut count_query);
+    let total = count_query.build_query_scalar::<i64>().fetch_one(&pool).await?;
+
+    let mut query = QueryBuilder::new(
         r#"
         SELECT id, seller_id, game_id, event_name, event_date,
                level, seat_section, seat_row, seat_number, price, status,
                transfer_deadline, price_at_reservation, reserved_at, reserved_by, created_at, updated_at
-        FROM tickets
-        WHERE status = $1
-        ORDER BY event_date ASC, created_at ASC
+        "#,
+    );
+    filters.push_from(&mut query);
+
+    let (column, descending) = sort.column();
+    if let Some((value, id)) = cursor {
+        query.push(format!(" AND ({}, id) {} (", column, if descending { "<" } else { ">" }));
+        match sort {
+            TicketSort::Date | TicketSort::Newest => {
+                let time = DateTime::<Utc>::from_timestamp_micros(value)
+                    .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?;
+                query.push_bind(time);
+            }
+            TicketSort::Price | TicketSort::PriceDesc => {
+                let price = i32::try_from(value).map_err(|_| AppError::BadRequest("Invalid cursor".to_string()))?;
+                query.push_bind(price);
+            }
+        }
+        query.push(", ").push_bind(id).push(")");
+    }
+
+    let direction = if descending { "DESC" } else { "ASC" };
+    query.push(format!(" ORDER BY {} {}, id {}", column, direction, direction));
+    // One extra row tells whether there is a next page
+    query.push(" LIMIT ").push_bind(limit + 1);
+
+    let mut tickets = query.build_query_as::<Ticket>().fetch_all(&pool).await?;
+
+    let next_cursor = if tickets.len() as i64 > limit {
+        tickets.truncate(limit as usize);
+        tickets.last().map(|ticket| encode_ticket_cursor(sort, ticket))
+    } else {
+        None
+    };
+
+    info!("Listed {} of {} verified tickets", tickets.len(), total);
+
+    Ok(Json(TicketSearchResponse {
+        tickets,
+        total,
+        next_cursor,
+    }))
+}
+
+#[derive(FromRow)]
+struct TicketDetailRow {
+    id: Uuid,
+    seller_id: Uuid,
+    level: String,
+    seat_section: String,
+    seat_row: String,
+    seat_number: String,
+    price: i32,
+    status: TicketStatus,
+    price_at_reservation: Option<i32>,
+    reserved_at: Option<DateTime<Utc>>,
+    reserved_by: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    game_id: Uuid,
+    sport_type: SportType,
+    game_name: String,
+    game_time: DateTime<Utc>,
+    cutoff_time: DateTime<Utc>,
+}
+
+/// Get a single ticket with its game (public endpoint, authentication optional)
+///
+/// Listings that never went on sale are only visible to their seller. The seller
+/// and the current reserver also see the raw status and reservation; everyone
+/// else only sees whether the ticket can be had.
+pub async fn get_ticket(
+    State(pool): State<PgPool>,
+    caller: Option<AuthUser>,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<TicketDetailResponse>> {
+    let ticket = sqlx::query_as::<_, TicketDetailRow>(
+        r#"
+        SELECT t.id, t.seller_id, t.level, t.seat_section, t.seat_row, t.seat_number, t.price, t.status,
+               t.price_at_reservation, t.reserved_at, t.reserved_by, t.created_at,
+               g.id AS game_id, g.sport_type, g.name AS game_name, g.game_time, g.cutoff_time
+        FROM tickets t
+        JOIN games g ON g.id = t.game_id
+        WHERE t.id = $1
         "#,
     )
-    .bind(&TicketStatus::Verified)
-    .fetch_all(&pool)
-    .await?;
+    .bind(ticket_id)
+    .fetch_optional(&pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Ticket not found".to_string()))?;
 
-    info!("Listed {} verified tickets", tickets.len());
+    let caller_id = caller.map(|user| user.user_id);
+    let is_seller = caller_id == Some(ticket.seller_id);
 
-    Ok(Json(ListTicketsResponse { tickets }))
+    let reservation_expires_at = match (ticket.status, ticket.reserved_at) {
+        (TicketStatus::Reserved, Some(reserved_at)) => {
+            Some(reserved_at + chrono::Duration::minutes(total_reservation_window_minutes()))
+        }
+        _ => None,
+    };
+    let reservation_lapsed = reservation_expires_at.is_some_and(|expires_at| expires_at <= Utc::now());
+
+    let availability = match ticket.status {
+        TicketStatus::Verified => TicketAvailability::Available,
+        TicketStatus::Reserved if reservation_lapsed => TicketAvailability::Available,
+        TicketStatus::Reserved => TicketAvailability::Reserved,
+        TicketStatus::Paid | TicketStatus::Sold => TicketAvailability::Sold,
+        TicketStatus::Unverified | TicketStatus::Verifying | TicketStatus::Cancelled if !is_seller => {
+            return Err(AppError::NotFound("Ticket not found".to_string()));
+        }
+        _ => TicketAvailability::Unavailable,
+    };
+
+    // A lapsed reservation no longer belongs to anyone, even before cleanup clears it
+    let is_reserver = caller_id.is_some() && caller_id == ticket.reserved_by && !reservation_lapsed;
+    let private = is_seller || is_reserver;
+
+    Ok(Json(TicketDetailResponse {
+        id: ticket.id,
+        seller_id: ticket.seller_id,
+        game: Game {
+            id: ticket.game_id,
+            sport_type: ticket.sport_type,
+            name: ticket.game_name,
+            game_time: ticket.game_time,
+            cutoff_time: ticket.cutoff_time,
+        },
+        level: ticket.level,
+        seat_section: ticket.seat_section,
+        seat_row: ticket.seat_row,
+        seat_number: ticket.seat_number,
+        price: ticket.price,
+        availability,
+        created_at: ticket.created_at,
+        is_seller,
+        is_reserver,
+        status: private.then_some(ticket.status),
+        reserved_by: ticket.reserved_by.filter(|_| private),
+        reserved_at: ticket.reserved_at.filter(|_| private),
+        price_at_reservation: ticket.price_at_reservation.filter(|_| private),
+        reservation_expires_at: reservation_expires_at.filter(|_| private),
+    }))
 }
 
 /// List user's own tickets (authenticated endpoint)
 pub async fn my_listings(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    AuthUser { user_id, .. }: AuthUser,
     Query(params): Query<MyListingsQuery>,
 ) -> Result<Json<ListTicketsResponse>> {
-    // Extract user_id from JWT token
-    let user_id = extract_user_id(&headers)?;
     info!("Listing tickets for seller_id: {}", user_id);
 
     // Build query based on optional status filter
@@ -147,6 +505,8 @@ pub async fn my_listings(
             "reserved" => TicketStatus::Reserved,
             "paid" => TicketStatus::Paid,
             "sold" => TicketStatus::Sold,
+            "refunding" => TicketStatus::Refunding,
+            "refunded" => TicketStatus::Refunded,
             "cancelled" => TicketStatus::Cancelled,
             _ => {
                 error!("Invalid status filter: {}", status_str);
@@ -164,8 +524,8 @@ pub async fn my_listings(
             ORDER BY created_at DESC
             "#,
         )
-        .bind(&user_id)
-        .bind(&status)
+        .bind(user_id)
+        .bind(status)
         .fetch_all(&pool)
         .await?
     } else {
@@ -180,7 +540,7 @@ pub async fn my_listings(
             ORDER BY created_at DESC
             "#,
         )
-        .bind(&user_id)
+        .bind(user_id)
         .fetch_all(&pool)
         .await?
     };
@@ -190,56 +550,221 @@ pub async fn my_listings(
     Ok(Json(ListTicketsResponse { tickets }))
 }
 
-/// Bot claim ticket (unverified → verifying)
-pub async fn claim_ticket(
+/// Status history of a ticket, oldest first (seller or staff)
+///
+/// Staff with `tickets:history` can read any ticket's timeline, including tickets
+/// already deleted by cleanup. Sellers can read their own listings' timelines,
+/// without the identity of the buyer, bot or admin behind each change.
+pub async fn get_ticket_events(
     State(pool): State<PgPool>,
     headers: HeaderMap,
-    Json(req): Json<ClaimTicketRequest>,
-) -> Result<Json<ClaimTicketResponse>> {
-    validate_bot_key(&headers)?;
-    let _permit = acquire_bot_permit().await?;
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<TicketTimelineResponse>> {
+    let actor = Actor::authenticate(&pool, &headers).await?;
+    let staff = actor.has_permission(Permission::ViewTicketHistory);
+
+    if !staff {
+        let seller_id = sqlx::query_scalar::<_, Uuid>("SELECT seller_id FROM tickets WHERE id = $1")
+            .bind(ticket_id)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Ticket not found".to_string()))?;
+
+        if !matches!(&actor, Actor::User { user_id, .. } if *user_id == seller_id) {
+            info!("{} attempted to read the history of ticket {}", actor.label(), ticket_id);
+            return Err(AppError::Forbidden);
+        }
+    }
 
-    let result = sqlx::query_as::<_, ClaimTicketResponse>(
+    let mut events = sqlx::query_as::<_, TicketEvent>(
         r#"
-        UPDATE tickets t
-        SET status = 'verifying',
-            updated_at = NOW()
-        FROM (
-            SELECT id
-            FROM tickets
-            WHERE status = 'unverified'
-              AND event_name = $1
-              AND seat_section = $2
-              AND seat_row = $3
-              AND seat_number = $4
-              AND transfer_deadline > NOW()
-            ORDER BY created_at ASC
-            FOR UPDATE SKIP LOCKED
-            LIMIT 1
-        ) candidate
-        WHERE t.id = candidate.id
-        RETURNING
-            t.id AS ticket_id,
-            t.seller_id,
-            t.event_name,
-            t.seat_section,
-            t.seat_row,
-            t.seat_number,
-            t.status
+        SELECT id, ticket_id, from_status, to_status, actor_type, actor_id, actor_label, reason, created_at
+        FROM ticket_events
+        WHERE ticket_id = $1
+        ORDER BY id ASC
         "#,
     )
-    .bind(&req.event_name)
-    .bind(&req.seat_section)
-    .bind(&req.seat_row)
-    .bind(&req.seat_number)
+    .bind(ticket_id)
+    .fetch_all(&pool)
+    .await?;
+
+    if events.is_empty() {
+        return Err(AppError::NotFound("Ticket not found".to_string()));
+    }
+
+    if !staff {
+        for event in events.iter_mut().filter(|e| e.actor_type != "system") {
+            event.actor_id = None;
+            event.actor_label = None;
+        }
+    }
+
+    Ok(Json(TicketTimelineResponse { ticket_id, events }))
+}
+
+/// Seller update ticket (reprice and/or cancel)
+///
+/// Only `unverified` and `verified` listings can be changed. Once a buyer holds the
+/// ticket (`reserved`/`paid`) or it has been settled, the listing is frozen. Tickets in
+/// `verifying` are also locked since a bot is mid-transfer on them.
+/// Cancelling moves the ticket to `cancelled`, which drops it out of
+/// `idx_tickets_unique_active_seat` so the seat can be listed again.
+pub async fn update_ticket(
+    State(pool): State<PgPool>,
+    AuthUser { user_id: seller_id, .. }: AuthUser,
+    Path(ticket_id): Path<Uuid>,
+    Json(req): Json<UpdateTicketRequest>,
+) -> Result<Json<Ticket>> {
+    info!("Update request for ticket {} by seller {}", ticket_id, seller_id);
+
+    if req.status.is_none() && req.price.is_none() {
+        return Err(AppError::BadRequest(
+            "At least one of status or price must be provided".to_string(),
+        ));
+    }
+
+    // Sellers may only move a listing to cancelled
+    let cancel = match req.status.as_deref().map(|s| s.to_lowercase()) {
+        None => false,
+        Some(s) if s == "cancelled" => true,
+        Some(s) => {
+            error!("Invalid status update: {}", s);
+            return Err(AppError::BadRequest(
+                "Sellers can only set status to cancelled".to_string(),
+            ));
+        }
+    };
+
+    if let Some(price) = req.price {
+        if price < 0 {
+            error!("Invalid price: {}", price);
+            return Err(AppError::BadRequest("Price must be >= 0".to_string()));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let updated = if cancel {
+        let transition = Transition::Cancel {
+            ticket_id,
+            price: req.price,
+        };
+        !apply_transition_on(&mut tx, &TicketActor::Seller(seller_id), &transition)
+            .await?
+            .is_empty()
+    } else {
+        // Price-only change: owner check and status guard in one statement so a buyer
+        // reserving concurrently can never see the price change underneath them
+        sqlx::query(
+            r#"
+            UPDATE tickets
+            SET price = $3,
+                updated_at = NOW()
+            WHERE id = $1
+              AND seller_id = $2
+              AND status IN ('unverified', 'verified')
+            "#,
+        )
+        .bind(ticket_id)
+        .bind(seller_id)
+        .bind(req.price)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0
+    };
+
+    let result = if updated {
+        let ticket = sqlx::query_as::<_, Ticket>(
+            r#"
+            SELECT id, seller_id, game_id, event_name, event_date,
+                   level, seat_section, seat_row, seat_number, price, status,
+                   transfer_deadline, price_at_reservation, reserved_at, reserved_by, created_at, updated_at
+            FROM tickets
+            WHERE id = $1
+            "#,
+        )
+        .bind(ticket_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        Some(ticket)
+    } else {
+        None
+    };
+
+    tx.commit().await?;
+
+    if let Some(ticket) = result {
+        info!(
+            "Ticket {} updated by seller {} (price: {}, status: {:?})",
+            ticket.id, seller_id, ticket.price, ticket.status
+        );
+        return Ok(Json(ticket));
+    }
+
+    // Nothing updated - work out why so the caller gets a useful error
+    let existing = sqlx::query_as::<_, (Uuid, TicketStatus)>(
+        "SELECT seller_id, status FROM tickets WHERE id = $1",
+    )
+    .bind(ticket_id)
     .fetch_optional(&pool)
     .await?;
 
+    match existing {
+        None => Err(AppError::NotFound("Ticket not found".to_string())),
+        Some((owner_id, _)) if owner_id != seller_id => {
+            info!("User {} attempted to update ticket {} owned by {}", seller_id, ticket_id, owner_id);
+            Err(AppError::Forbidden)
+        }
+        Some((_, status)) => {
+            info!("Ticket {} in {:?} state cannot be updated", ticket_id, status);
+            Err(AppError::Conflict(
+                "Cannot update ticket. Only unverified or verified tickets can be updated.".to_string(),
+            ))
+        }
+    }
+}
+
+/// Bot claim ticket (unverified → verifying)
+pub async fn claim_ticket(
+    State(pool): State<PgPool>,
+    BotCaller { bot, .. }: BotCaller<CanVerifyTickets>,
+    Json(req): Json<ClaimTicketRequest>,
+) -> Result<Json<ClaimTicketResponse>> {
+    let transition = Transition::Claim {
+        event_name: &req.event_name,
+        seat_section: &req.seat_section,
+        seat_row: &req.seat_row,
+        seat_number: &req.seat_number,
+    };
+
+    let mut tx = pool.begin().await?;
+    let claimed = apply_transition_on(&mut tx, &TicketActor::Bot(&bot), &transition).await?;
+
+    let result = match claimed.first() {
+        Some(ticket_id) => {
+            let resp = sqlx::query_as::<_, ClaimTicketResponse>(
+                r#"
+                SELECT id AS ticket_id, seller_id, event_name, seat_section, seat_row, seat_number, status
+                FROM tickets
+                WHERE id = $1
+                "#,
+            )
+            .bind(ticket_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            Some(resp)
+        }
+        None => None,
+    };
+
+    tx.commit().await?;
+
     match result {
         Some(resp) => {
             info!(
-                "Ticket {} claimed by bot for event {} seat {}-{}-{}",
-                resp.ticket_id, resp.event_name, resp.seat_section, resp.seat_row, resp.seat_number
+                "Ticket {} claimed by bot {} for event {} seat {}-{}-{}",
+                resp.ticket_id, bot.name, resp.event_name, resp.seat_section, resp.seat_row, resp.seat_number
             );
             Ok(Json(resp))
         }
@@ -256,30 +781,18 @@ pub async fn claim_ticket(
 /// Bot verify ticket (verifying → verified)
 pub async fn verify_ticket(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    BotCaller { bot, .. }: BotCaller<CanVerifyTickets>,
     Path(ticket_id): Path<Uuid>,
 ) -> Result<Json<TicketStatusResponse>> {
-    validate_bot_key(&headers)?;
-    let _permit = acquire_bot_permit().await?;
-
-    let result = sqlx::query_as::<_, TicketStatusResponse>(
-        r#"
-        UPDATE tickets
-        SET status = 'verified',
-            updated_at = NOW()
-        WHERE id = $1
-          AND status = 'verifying'
-        RETURNING id AS ticket_id, status
-        "#,
-    )
-    .bind(&ticket_id)
-    .fetch_optional(&pool)
-    .await?;
+    let verified = apply_transition(&pool, &TicketActor::Bot(&bot), &Transition::Verify { ticket_id }).await?;
 
-    match result {
-        Some(resp) => {
-            info!("Ticket {} moved to verified", ticket_id);
-            Ok(Json(resp))
+    match verified.first() {
+        Some(_) => {
+            info!("Ticket {} moved to verified by bot {}", ticket_id, bot.name);
+            Ok(Json(TicketStatusResponse {
+                ticket_id,
+                status: TicketStatus::Verified,
+            }))
         }
         None => {
             info!("Ticket {} not in verifying state for verification", ticket_id);
@@ -291,30 +804,18 @@ pub async fn verify_ticket(
 /// Bot unclaim (verifying → unverified)
 pub async fn unclaim_ticket(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    BotCaller { bot, .. }: BotCaller<CanVerifyTickets>,
     Path(ticket_id): Path<Uuid>,
 ) -> Result<Json<TicketStatusResponse>> {
-    validate_bot_key(&headers)?;
-    let _permit = acquire_bot_permit().await?;
+    let released = apply_transition(&pool, &TicketActor::Bot(&bot), &Transition::Unclaim { ticket_id }).await?;
 
-    let result = sqlx::query_as::<_, TicketStatusResponse>(
-        r#"
-        UPDATE tickets
-        SET status = 'unverified',
-            updated_at = NOW()
-        WHERE id = $1
-          AND status = 'verifying'
-        RETURNING id AS ticket_id, status
-        "#,
-    )
-    .bind(&ticket_id)
-    .fetch_optional(&pool)
-    .await?;
-
-    match result {
-        Some(resp) => {
-            info!("Ticket {} rolled back to unverified", ticket_id);
-            Ok(Json(resp))
+    match released.first() {
+        Some(_) => {
+            info!("Ticket {} rolled back to unverified by bot {}", ticket_id, bot.name);
+            Ok(Json(TicketStatusResponse {
+                ticket_id,
+                status: TicketStatus::Unverified,
+            }))
         }
         None => {
             info!("Ticket {} not in verifying state for rollback", ticket_id);
@@ -324,32 +825,40 @@ pub async fn unclaim_ticket(
 }
 
 /// Bot mark ticket as sold (paid → sold)
+///
+/// Also queues the seller payout and attempts the transfer in the background.
+/// Failed transfers are retried by the payout job in `utils::cleanup`.
 pub async fn mark_sold(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    BotCaller { bot, .. }: BotCaller<CanMarkTicketsSold>,
     Path(ticket_id): Path<Uuid>,
 ) -> Result<Json<TicketStatusResponse>> {
-    validate_bot_key(&headers)?;
-    let _permit = acquire_bot_permit().await?;
+    let sold = apply_transition(&pool, &TicketActor::Bot(&bot), &Transition::MarkSold { ticket_id }).await?;
 
-    let result = sqlx::query_as::<_, TicketStatusResponse>(
-        r#"
-        UPDATE tickets
-        SET status = 'sold',
-            updated_at = NOW()
-        WHERE id = $1
-          AND status = 'paid'
-        RETURNING id AS ticket_id, status
-        "#,
-    )
-    .bind(&ticket_id)
-    .fetch_optional(&pool)
-    .await?;
+    match sold.first() {
+        Some(_) => {
+            info!("Ticket {} marked as sold (transferred to buyer) by bot {}", ticket_id, bot.name);
 
-    match result {
-        Some(resp) => {
-            info!("Ticket {} marked as sold (transferred to buyer)", ticket_id);
-            Ok(Json(resp))
+            match queue_payouts(&pool, Some(ticket_id)).await {
+                Ok(payout_ids) => {
+                    for payout_id in payout_ids {
+                        let pool = pool.clone();
+                        let payments = payments.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = process_payout(&pool, payments.as_ref(), payout_id).await {
+                                error!("Processing payout {} failed: {}", payout_id, e);
+                            }
+                        });
+                    }
+                }
+                Err(e) => error!("Failed to queue payout for ticket {}: {}", ticket_id, e),
+            }
+
+            Ok(Json(TicketStatusResponse {
+                ticket_id,
+                status: TicketStatus::Sold,
+            }))
         }
         None => {
             info!("Ticket {} not in paid state for marking sold", ticket_id);
@@ -358,33 +867,87 @@ pub async fn mark_sold(
     }
 }
 
+/// Admin/bot refund ticket (paid → refunding → refunded)
+///
+/// Used when the ticket can't be transferred to the buyer. If the Stripe refund
+/// fails the ticket stays 'refunding' and the refund job retries it.
+pub async fn refund_ticket(
+    State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    caller: AdminOrBotCaller,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<TicketStatusResponse>> {
+    let actor = match &caller {
+        AdminOrBotCaller::Admin(actor) => TicketActor::Admin(actor),
+        AdminOrBotCaller::Bot { bot, .. } => TicketActor::Bot(bot),
+    };
+
+    if !request_refund(&pool, &actor, ticket_id).await? {
+        info!("Ticket {} not in paid state for refund", ticket_id);
+        return Err(AppError::Conflict("Ticket not in paid state".to_string()));
+    }
+
+    info!("Ticket {} moved to refunding", ticket_id);
+
+    let refunded = process_refund(&pool, payments.as_ref(), &actor, ticket_id).await;
+
+    // Recorded either way, as the ticket stays 'refunding' for the refund job to retry
+    match &caller {
+        AdminOrBotCaller::Admin(actor) => {
+            let details = match &refunded {
+                Ok(()) => json!({ "outcome": "refunded" }),
+                Err(e) => json!({ "outcome": "refund_pending", "error": e.to_string() }),
+            };
+            record_admin_action(&pool, actor, "ticket.refund", "ticket", Some(ticket_id.to_string()), details).await?;
+        }
+        AdminOrBotCaller::Bot { bot, .. } => info!("Refund of ticket {} requested by bot {}", ticket_id, bot.name),
+    }
+
+    refunded?;
+
+    Ok(Json(TicketStatusResponse {
+        ticket_id,
+        status: TicketStatus::Refunded,
+    }))
+}
+
 /// Reserve a ticket (verified → reserved)
+///
+/// Each buyer's reservations run one at a time under a transaction-scoped advisory
+/// lock, so parallel requests can't all pass the `MAX_RESERVATIONS_PER_USER` check
+/// before any of them reserves. Sellers can't reserve their own listings.
 pub async fn reserve_ticket(
     State(pool): State<PgPool>,
-    headers: HeaderMap,
+    AuthUser { user_id: buyer_id, .. }: AuthUser,
     Path(ticket_id): Path<Uuid>,
 ) -> Result<Json<ReserveTicketResponse>> {
-    // Extract buyer_id from JWT token
-    let buyer_id = extract_user_id(&headers)?;
     info!("Reserve request for ticket {} by buyer {}", ticket_id, buyer_id);
 
-    // Get total reservation window minutes from environment variable (default: 7 minutes)
-    let total_reservation_window_minutes: i64 = env::var("TOTAL_RESERVATION_WINDOW_MINUTES")
-        .unwrap_or_else(|_| "7".to_string())
-        .parse()
-        .unwrap_or(7);
-
-    // Calculate expiry time: NOW() - INTERVAL '1 minute' * TOTAL_RESERVATION_WINDOW_MINUTES
     // Tickets with reserved_at older than this are considered expired
-    let expiry_time = Utc::now() - chrono::Duration::minutes(total_reservation_window_minutes);
+    let expiry_time = Utc::now() - chrono::Duration::minutes(total_reservation_window_minutes());
+
+    let max_reservations: i64 = env_or("MAX_RESERVATIONS_PER_USER", 3);
+
+    let mut tx = pool.begin().await?;
+
+    // Held until commit/rollback, so the count below includes every reservation
+    // this buyer's earlier requests made
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('reserve:' || $1::TEXT, 0))")
+        .bind(buyer_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let seller_id = sqlx::query_scalar::<_, Uuid>("SELECT seller_id FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_optional(&mut *tx)
+        .await?;
 
-    // Check reservation limit per user
-    let max_reservations: i64 = env::var("MAX_RESERVATIONS_PER_USER")
-        .unwrap_or_else(|_| "3".to_string())
-        .parse()
-        .unwrap_or(3);
+    if seller_id == Some(buyer_id) {
+        info!("Seller {} attempted to reserve their own ticket {}", buyer_id, ticket_id);
+        return Err(AppError::OwnTicketReservation);
+    }
 
-    let active_count: (i64,) = sqlx::query_as(
+    let active_count = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT COUNT(*) FROM tickets
         WHERE reserved_by = $1
@@ -392,50 +955,41 @@ pub async fn reserve_ticket(
           AND reserved_at > $2
         "#,
     )
-    .bind(&buyer_id)
-    .bind(&expiry_time)
-    .fetch_one(&pool)
+    .bind(buyer_id)
+    .bind(expiry_time)
+    .fetch_one(&mut *tx)
     .await?;
 
-    if active_count.0 >= max_reservations {
+    if active_count >= max_reservations {
         info!(
             "User {} has {} active reservations, limit is {}",
-            buyer_id, active_count.0, max_reservations
+            buyer_id, active_count, max_reservations
         );
-        return Err(AppError::Conflict(format!(
-            "Maximum {} concurrent reservations allowed",
-            max_reservations
-        )));
+        return Err(AppError::ReservationLimitReached(max_reservations));
     }
 
-    // Atomic reservation query
-    // This updates the ticket to reserved status only if:
-    // - status is 'verified', OR
-    // - status is 'reserved' AND reserved_at < expiry_time (expired reservation)
-    let result = sqlx::query_as::<_, (Uuid, i32, chrono::DateTime<Utc>)>(
-        r#"
-        UPDATE tickets
-        SET status = 'reserved',
-            reserved_at = NOW(),
-            reserved_by = $1,
-            price_at_reservation = price,
-            updated_at = NOW()
-        WHERE id = $2
-          AND (
-            status = 'verified'
-            OR (
-              status = 'reserved'
-              AND reserved_at < $3
+    // Atomic reservation: only a verified ticket, or one whose reservation
+    // is older than expiry_time, can be reserved
+    let transition = Transition::Reserve {
+        ticket_id,
+        expired_before: expiry_time,
+    };
+    let reserved = apply_transition_on(&mut tx, &TicketActor::Buyer(buyer_id), &transition).await?;
+
+    let result = match reserved.first() {
+        Some(ticket_id) => {
+            let reservation = sqlx::query_as::<_, (Uuid, i32, chrono::DateTime<Utc>)>(
+                "SELECT id, price_at_reservation, reserved_at FROM tickets WHERE id = $1",
             )
-          )
-        RETURNING id, price_at_reservation, reserved_at
-        "#,
-    )
-    .bind(&buyer_id)
-    .bind(&ticket_id)
-    .bind(&expiry_time)
-    .fetch_optional(&pool)
-    .await?;
+            .bind(ticket_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            Some(reservation)
+        }
+        None => None,
+    };
+
+    tx.commit().await?;
 
     match result {
         Some((ticket_id, price_at_reservation, reserved_at)) => {
@@ -458,3 +1012,189 @@ pub async fn reserve_ticket(
     }
 }
 
+/// Release the caller's reservation (reserved → verified)
+///
+/// Puts the ticket straight back on sale instead of holding it until the window
+/// runs out. Payment intents created at checkout are cancelled through the Stripe
+/// outbox in the same transaction, so the buyer can't complete a payment for a
+/// ticket they gave up.
+pub async fn release_reservation(
+    State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    AuthUser { user_id: buyer_id, .. }: AuthUser,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<TicketStatusResponse>> {
+    info!("Release request for ticket {} by buyer {}", ticket_id, buyer_id);
+
+    let mut tx = pool.begin().await?;
+    let released = apply_transition_on(
+        &mut tx,
+        &TicketActor::Buyer(buyer_id),
+        &Transition::CancelReservation { ticket_id },
+    )
+    .await?;
+
+    if released.is_empty() {
+        info!("Buyer {} has no reservation on ticket {} to release", buyer_id, ticket_id);
+        return Err(AppError::Conflict("No active reservation for this ticket".to_string()));
+    }
+
+    // Intents that were never authorized; an authorized one already made the ticket paid
+    let payment_intent_ids = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT id FROM payment_intents
+        WHERE ticket_id = $1
+          AND buyer_id = $2
+          AND status IN ('created', 'failed')
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(buyer_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut operation_ids = Vec::new();
+    for payment_intent_id in &payment_intent_ids {
+        if let Some(operation_id) =
+            enqueue_stripe_operation(&mut *tx, payment_intent_id, StripeOperationType::Cancel).await?
+        {
+            operation_ids.push(operation_id);
+        }
+    }
+
+    tx.commit().await?;
+
+    info!(
+        "Ticket {} released by buyer {}, cancelling {} payment intents",
+        ticket_id,
+        buyer_id,
+        operation_ids.len()
+    );
+
+    // First attempt right away; the outbox worker retries anything that fails
+    for operation_id in operation_ids {
+        if let Err(e) = process_stripe_operation(&pool, payments.as_ref(), operation_id).await {
+            error!(
+                "Cancelling payment intent for released ticket {} failed, left for the outbox worker: {}",
+                ticket_id, e
+            );
+        }
+    }
+
+    Ok(Json(TicketStatusResponse {
+        ticket_id,
+        status: TicketStatus::Verified,
+    }))
+}
+
+/// Start checkout for a reserved ticket (buyer creates the Stripe payment intent)
+///
+/// The payment intent is created server-side from `price_at_reservation` so the amount
+/// and metadata the gatekeeper relies on cannot be tampered with. Only the client secret
+/// is returned. Calling this again during the same reservation reuses the existing intent.
+/// No lock is held across the Stripe call: the create is keyed by the reservation, so
+/// concurrent checkouts for one reservation get the same intent back and record it once.
+pub async fn checkout_ticket(
+    State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
+    AuthUser { user_id: buyer_id, .. }: AuthUser,
+    Path(ticket_id): Path<Uuid>,
+) -> Result<Json<CheckoutResponse>> {
+    info!("Checkout request for ticket {} by buyer {}", ticket_id, buyer_id);
+
+    let expiry_time = Utc::now() - chrono::Duration::minutes(total_reservation_window_minutes());
+
+    // Caller must hold a live reservation on this ticket
+    let reservation = sqlx::query_as::<_, (i32, chrono::DateTime<Utc>)>(
+        r#"
+        SELECT price_at_reservation, reserved_at
+        FROM tickets
+        WHERE id = $1
+          AND status = 'reserved'
+          AND reserved_by = $2
+          AND reserved_at > $3
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(buyer_id)
+    .bind(expiry_time)
+    .fetch_optional(&pool)
+    .await?;
+
+    let (price_at_reservation, reserved_at) = match reservation {
+        Some(reservation) => reservation,
+        None => {
+            info!("Buyer {} has no active reservation on ticket {}", buyer_id, ticket_id);
+            return Err(AppError::Conflict("No active reservation for this ticket".to_string()));
+        }
+    };
+
+    // Reuse the intent already created for this reservation, if any
+    let existing_intent_id = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT id FROM payment_intents
+        WHERE ticket_id = $1
+          AND buyer_id = $2
+          AND status = $3
+          AND created_at >= $4
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(buyer_id)
+    .bind(PaymentIntentStatus::Created)
+    .bind(reserved_at)
+    .fetch_optional(&pool)
+    .await?;
+
+    if let Some(payment_intent_id) = existing_intent_id {
+        info!(
+            "Reusing payment intent {} for ticket {} (buyer {})",
+            payment_intent_id, ticket_id, buyer_id
+        );
+        let client_secret = payments
+            .retrieve_payment_intent(&payment_intent_id)
+            .await?
+            .client_secret
+            .ok_or_else(|| {
+                AppError::Internal(anyhow::anyhow!("Payment intent {} has no client secret", payment_intent_id))
+            })?;
+        return Ok(Json(CheckoutResponse {
+            ticket_id,
+            client_secret,
+        }));
+    }
+
+    let payment_intent = payments
+        .create_payment_intent(price_at_reservation, ticket_id, buyer_id, reserved_at)
+        .await?;
+
+    // Record the intent so the webhook can match it against this reservation; a
+    // concurrent checkout for the same reservation may already have recorded it
+    sqlx::query(
+        r#"
+        INSERT INTO payment_intents (id, ticket_id, buyer_id, amount, currency, status)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+    )
+    .bind(&payment_intent.id)
+    .bind(ticket_id)
+    .bind(buyer_id)
+    .bind(price_at_reservation)
+    .bind(&payment_intent.currency)
+    .bind(PaymentIntentStatus::Created)
+    .execute(&pool)
+    .await?;
+
+    info!(
+        "Checkout started for ticket {} by buyer {} (payment intent {}, amount {})",
+        ticket_id, buyer_id, payment_intent.id, price_at_reservation
+    );
+
+    Ok(Json(CheckoutResponse {
+        ticket_id,
+        client_secret: payment_intent.client_secret,
+    }))
+}
@@ -3,22 +3,40 @@ use axum::{
     http::{HeaderMap, StatusCode},
     response::Json,
 };
-use chrono::Utc;
-use sqlx::PgPool;
-use std::env;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use std::sync::Arc;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
+use crate::handlers::tickets::total_reservation_window_minutes;
 use crate::models::payment_intent::{
-    PaymentIntent, PaymentIntentStatus, StripeWebhookEvent,
+    PaymentIntent, PaymentIntentStatus, StripeCharge, StripeDispute, StripePaymentIntent,
+    StripeWebhookEvent,
 };
-use crate::utils::stripe::{cancel_payment_intent, capture_payment_intent, verify_stripe_webhook_signature};
+use crate::models::stripe_operation::StripeOperationType;
+use crate::utils::payment_gateway::PaymentGateway;
+use crate::utils::refunds::mark_refunded;
+use crate::utils::stripe::verify_stripe_webhook_signature;
+use crate::utils::stripe_outbox::{enqueue_stripe_operation, process_stripe_operation};
+use crate::utils::ticket_events::TicketActor;
+use crate::utils::ticket_state::{apply_transition_on, Transition};
 
-/// Handle Stripe webhook for payment_intent.amount_capturable_updated event
-/// This event fires when funds are authorized and ready to capture
+/// Ticket status changes made by webhooks are attributed to this system actor
+const WEBHOOK_ACTOR: TicketActor<'static> = TicketActor::System("stripe_webhook");
+
+/// Handle Stripe webhooks
+///
+/// - payment_intent.amount_capturable_updated: funds authorized, run the gatekeeper
+/// - payment_intent.payment_failed: payment attempt failed, release the reservation
+/// - payment_intent.canceled: authorization released (by us, or expired at Stripe)
+/// - payment_intent.succeeded: funds captured, reconcile the local intent
+/// - charge.refunded: refund completed (ours or issued from the Stripe dashboard)
+/// - charge.dispute.created: buyer disputed the charge, hold the seller payout
 pub async fn handle_stripe_webhook(
     State(pool): State<PgPool>,
+    State(payments): State<Arc<dyn PaymentGateway>>,
     headers: HeaderMap,
     body: String,
 ) -> Result<(StatusCode, Json<serde_json::Value>)> {
@@ -36,35 +54,70 @@ pub async fn handle_stripe_webhook(
     verify_stripe_webhook_signature(&body, signature)?;
 
     // Parse webhook payload
-    let event: StripeWebhookEvent = serde_json::from_str(&body).map_err(|e| {
+    let payload: serde_json::Value = serde_json::from_str(&body).map_err(|e| {
         error!("Failed to parse webhook payload: {}", e);
         AppError::Internal(anyhow::anyhow!("Invalid webhook payload"))
     })?;
 
-    info!("Webhook event type: {}", event.event_type);
-
-    // Only process payment_intent.amount_capturable_updated events
-    // This event fires when funds are authorized and ready to capture
-    // NOTE: Verify this event type exists in Stripe - may need to use payment_intent.requires_capture instead
-    if event.event_type != "payment_intent.amount_capturable_updated" {
-        info!("Ignoring event type: {}", event.event_type);
-        return Ok((
-            StatusCode::OK,
-            Json(serde_json::json!({ "received": true })),
-        ));
-    }
+    let event_type = payload["type"].as_str().unwrap_or_default().to_string();
+    info!("Webhook event type: {}", event_type);
 
-    let payment_intent = &event.data.object;
+    let event: StripeWebhookEvent = serde_json::from_value(payload).map_err(|e| {
+        error!("Failed to parse {} webhook payload: {}", event_type, e);
+        AppError::Internal(anyhow::anyhow!("Invalid webhook payload"))
+    })?;
 
-    // Extract metadata
-    let ticket_id = Uuid::parse_str(&payment_intent.metadata.ticket_id).map_err(|e| {
-        error!("Invalid ticket_id in metadata: {}", e);
-        AppError::Internal(anyhow::anyhow!("Invalid ticket_id in metadata"))
+    match event {
+        StripeWebhookEvent::PaymentIntentAmountCapturableUpdated { data } => {
+            handle_amount_capturable_updated(&pool, payments.as_ref(), &data.object).await
+        }
+        StripeWebhookEvent::PaymentIntentPaymentFailed { data } => {
+            handle_payment_failed(&pool, &data.object).await
+        }
+        StripeWebhookEvent::PaymentIntentCanceled { data } => {
+            handle_payment_canceled(&pool, &data.object).await
+        }
+        StripeWebhookEvent::PaymentIntentSucceeded { data } => {
+            handle_payment_succeeded(&pool, &data.object).await
+        }
+        StripeWebhookEvent::ChargeRefunded { data } => handle_charge_refunded(&pool, &data.object).await,
+        StripeWebhookEvent::ChargeDisputeCreated { data } => {
+            handle_dispute_created(&pool, &data.object).await
+        }
+        StripeWebhookEvent::Other => {
+            info!("Ignoring event type: {}", event_type);
+            Ok((
+                StatusCode::OK,
+                Json(serde_json::json!({ "received": true })),
+            ))
+        }
+    }
+}
+
+fn parse_metadata_uuid(value: Option<&str>, field: &str) -> Result<Uuid> {
+    let value = value.ok_or_else(|| {
+        error!("Missing {} in metadata", field);
+        AppError::Internal(anyhow::anyhow!("Missing {} in metadata", field))
     })?;
 
-    let buyer_id = Uuid::parse_str(&payment_intent.metadata.buyer_id).map_err(|e| {
-        error!("Invalid buyer_id in metadata: {}", e);
-        AppError::Internal(anyhow::anyhow!("Invalid buyer_id in metadata"))
+    Uuid::parse_str(value).map_err(|e| {
+        error!("Invalid {} in metadata: {}", field, e);
+        AppError::Internal(anyhow::anyhow!("Invalid {} in metadata", field))
+    })
+}
+
+/// Funds are authorized and ready to capture (Stage 3 → Stage 4)
+async fn handle_amount_capturable_updated(
+    pool: &PgPool,
+    payments: &dyn PaymentGateway,
+    payment_intent: &StripePaymentIntent,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    // Extract metadata
+    let ticket_id = parse_metadata_uuid(payment_intent.metadata.ticket_id.as_deref(), "ticket_id")?;
+    let buyer_id = parse_metadata_uuid(payment_intent.metadata.buyer_id.as_deref(), "buyer_id")?;
+    let amount = i32::try_from(payment_intent.amount).map_err(|_| {
+        error!("Payment intent {} amount {} out of range", payment_intent.id, payment_intent.amount);
+        AppError::Internal(anyhow::anyhow!("Payment intent amount out of range"))
     })?;
 
     info!(
@@ -72,200 +125,600 @@ pub async fn handle_stripe_webhook(
         payment_intent.id, ticket_id, buyer_id
     );
 
+    // The intent insert, the ticket transition and the queued Stripe operation commit
+    // together, so a crash part way through leaves nothing behind to reconcile
+    let mut tx = pool.begin().await?;
+
     // Store payment intent record (for idempotency)
-    // Using ON CONFLICT DO NOTHING to handle duplicate webhook deliveries
-    let result = sqlx::query_as::<_, PaymentIntent>(
+    // Intents created by the checkout endpoint already exist as 'created' (or 'failed',
+    // if an earlier attempt was declined) and are moved to 'capturable' here; any other
+    // conflict is a redelivery of this event
+    let upserted = sqlx::query_as::<_, PaymentIntent>(
         r#"
         INSERT INTO payment_intents (id, ticket_id, buyer_id, amount, currency, status)
         VALUES ($1, $2, $3, $4, $5, $6)
-        ON CONFLICT (id) DO NOTHING
+        ON CONFLICT (id) DO UPDATE
+        SET status = EXCLUDED.status,
+            updated_at = NOW()
+        WHERE payment_intents.status IN ('created', 'failed')
         RETURNING id, ticket_id, buyer_id, amount, currency, status, created_at, updated_at
         "#,
     )
     .bind(&payment_intent.id)
-    .bind(&ticket_id)
-    .bind(&buyer_id)
-    .bind(&(payment_intent.amount as i32))
+    .bind(ticket_id)
+    .bind(buyer_id)
+    .bind(amount)
     .bind(&payment_intent.currency)
-    .bind(&PaymentIntentStatus::Capturable)
-    .fetch_optional(&pool)
+    .bind(PaymentIntentStatus::Capturable)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    match result {
-        Some(_) => {
+    let stored = match upserted {
+        Some(stored) => {
             // First time processing this payment intent
             info!(
                 "Payment intent {} stored successfully for ticket {}",
                 payment_intent.id, ticket_id
             );
-            
-            // Stage 4: Gatekeeper Check (reserved → paid)
-            perform_gatekeeper_check(
-                &pool,
-                &payment_intent.id,
-                ticket_id,
-                buyer_id,
-            ).await?;
-            
-            Ok((
-                StatusCode::OK,
-                Json(serde_json::json!({
-                    "received": true,
-                    "payment_intent_id": payment_intent.id
-                })),
-            ))
+            stored
         }
         None => {
-            // Duplicate webhook - already processed
-            info!(
-                "Payment intent {} already processed (idempotent)",
-                payment_intent.id
+            // Redelivery: resume if the gatekeeper never queued a capture/cancel
+            // (the conflicting row is already locked by the upsert above)
+            let unfinished = sqlx::query_as::<_, PaymentIntent>(
+                r#"
+                SELECT id, ticket_id, buyer_id, amount, currency, status, created_at, updated_at
+                FROM payment_intents pi
+                WHERE pi.id = $1
+                  AND pi.status = 'capturable'
+                  AND NOT EXISTS (
+                      SELECT 1 FROM stripe_operations so WHERE so.payment_intent_id = pi.id
+                  )
+                "#,
+            )
+            .bind(&payment_intent.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            match unfinished {
+                Some(stored) => {
+                    warn!(
+                        "Payment intent {} was stored but never settled, resuming gatekeeper",
+                        payment_intent.id
+                    );
+                    stored
+                }
+                None => {
+                    // Duplicate webhook - already processed
+                    info!(
+                        "Payment intent {} already processed (idempotent)",
+                        payment_intent.id
+                    );
+                    return Ok((
+                        StatusCode::OK,
+                        Json(serde_json::json!({ "received": true, "duplicate": true })),
+                    ));
+                }
+            }
+        }
+    };
+
+    // Intents created at checkout keep their server-side ticket/buyer, so
+    // metadata that disagrees with the stored row is never trusted
+    if stored.ticket_id != ticket_id || stored.buyer_id != buyer_id {
+        warn!(
+            "Payment intent {} metadata does not match checkout record (ticket {}, buyer {})",
+            payment_intent.id, stored.ticket_id, stored.buyer_id
+        );
+    }
+
+    // Stage 4: Gatekeeper Check (reserved → paid)
+    let operation = perform_gatekeeper_check(
+        &mut tx,
+        &payment_intent.id,
+        stored.ticket_id,
+        stored.buyer_id,
+        payment_intent.amount,
+    )
+    .await?;
+
+    let operation_id = enqueue_stripe_operation(&mut *tx, &payment_intent.id, operation).await?;
+
+    tx.commit().await?;
+
+    // First attempt right away; a failure doesn't fail the webhook (Stripe would
+    // redeliver it), the outbox worker keeps retrying with backoff instead
+    if let Some(operation_id) = operation_id {
+        info!(
+            "Queued Stripe {:?} of payment intent {} (operation {})",
+            operation, payment_intent.id, operation_id
+        );
+
+        if let Err(e) = process_stripe_operation(pool, payments, operation_id).await {
+            error!(
+                "Stripe {:?} of payment intent {} failed, left for the outbox worker: {}",
+                operation, payment_intent.id, e
             );
-            Ok((
-                StatusCode::OK,
-                Json(serde_json::json!({ "received": true, "duplicate": true })),
-            ))
         }
     }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent.id
+        })),
+    ))
 }
 
-/// Stage 4: Gatekeeper Check
-/// Determines if the "freeze" becomes a "charge" or a "release"
-/// 
-/// Happy Path: Ticket is still reserved by this buyer within the reservation window
-///   - Updates ticket status to 'paid'
-///   - Captures payment from buyer
-/// 
-/// Late Path: Reservation expired
-///   - Cancels payment intent (releases authorization hold)
-///   - Ticket status remains unchanged (will be reset to 'verified' by cleanup job)
-async fn perform_gatekeeper_check(
+/// A payment attempt failed (e.g. card declined)
+///
+/// Marks the intent 'failed' and releases the buyer's reservation right away so the
+/// ticket is back on sale. If the buyer retries and the intent is authorized after
+/// all, the gatekeeper finds no reservation and cancels it.
+async fn handle_payment_failed(
     pool: &PgPool,
-    payment_intent_id: &str,
-    ticket_id: Uuid,
-    buyer_id: Uuid,
-) -> Result<()> {
-    info!(
-        "Performing gatekeeper check for payment intent {} on ticket {}",
-        payment_intent_id, ticket_id
-    );
+    payment_intent: &StripePaymentIntent,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    let reason = payment_intent
+        .last_payment_error
+        .as_ref()
+        .and_then(|e| e.message.as_deref().or(e.code.as_deref()))
+        .unwrap_or("unknown");
 
-    // Get total reservation window minutes from environment variable (default: 7 minutes)
-    let total_reservation_window_minutes: i64 = env::var("TOTAL_RESERVATION_WINDOW_MINUTES")
-        .unwrap_or_else(|_| "7".to_string())
-        .parse()
-        .unwrap_or(7);
+    let mut tx = pool.begin().await?;
 
-    // Calculate expiry time: NOW() - INTERVAL '1 minute' * TOTAL_RESERVATION_WINDOW_MINUTES
-    // Tickets with reserved_at older than this are considered expired
-    let expiry_time = Utc::now() - chrono::Duration::minutes(total_reservation_window_minutes);
-
-    let update_result = sqlx::query_as::<_, (Uuid, i32)>(
+    let failed = sqlx::query_as::<_, (Uuid, Uuid, DateTime<Utc>)>(
         r#"
-        UPDATE tickets
-        SET status = 'paid',
+        UPDATE payment_intents
+        SET status = 'failed',
             updated_at = NOW()
         WHERE id = $1
-          AND status = 'reserved'
-          AND reserved_by = $2
-          AND reserved_at > $3
-        RETURNING id, price_at_reservation
+          AND status IN ('created', 'failed')
+        RETURNING ticket_id, buyer_id, created_at
         "#,
     )
-    .bind(&ticket_id)
-    .bind(&buyer_id)
-    .bind(&expiry_time)
-    .fetch_optional(pool)
+    .bind(&payment_intent.id)
+    .fetch_optional(&mut *tx)
     .await?;
 
-    match update_result {
-        Some((_, _price_at_reservation)) => {
-            // Branch A: Happy Path - Reservation is still valid
+    let (ticket_id, buyer_id, created_at) = match failed {
+        Some(failed) => failed,
+        None => {
             info!(
-                "Gatekeeper check passed: Ticket {} reserved by buyer {} within {} minutes",
-                ticket_id, buyer_id, total_reservation_window_minutes
+                "Ignoring payment failure for payment intent {} (unknown or already authorized)",
+                payment_intent.id
             );
+            return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
+        }
+    };
+
+    let released = release_reservation(
+        &mut tx,
+        ticket_id,
+        buyer_id,
+        created_at,
+        &format!("payment failed: {}", reason),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    info!(
+        "Payment intent {} failed ({}), reservation on ticket {} released: {}",
+        payment_intent.id, reason, ticket_id, released
+    );
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent.id
+        })),
+    ))
+}
+
+/// A payment intent was canceled at Stripe
+///
+/// Either our own queued cancel went through, or the authorization was released
+/// at Stripe (e.g. it expired). A queued capture can then never succeed, so it is
+/// dead-lettered for manual follow-up.
+async fn handle_payment_canceled(
+    pool: &PgPool,
+    payment_intent: &StripePaymentIntent,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    let reason = payment_intent.cancellation_reason.as_deref().unwrap_or("unknown");
+
+    let mut tx = pool.begin().await?;
 
-            // Update payment intent status to 'captured'
+    let stored = sqlx::query_as::<_, (Uuid, Uuid, PaymentIntentStatus, DateTime<Utc>)>(
+        "SELECT ticket_id, buyer_id, status, created_at FROM payment_intents WHERE id = $1 FOR UPDATE",
+    )
+    .bind(&payment_intent.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (ticket_id, buyer_id, status, created_at) = match stored {
+        Some(stored) => stored,
+        None => {
+            info!("Ignoring cancellation of unknown payment intent {}", payment_intent.id);
+            return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
+        }
+    };
+
+    match status {
+        PaymentIntentStatus::Created | PaymentIntentStatus::Failed | PaymentIntentStatus::Capturable => {
             sqlx::query(
                 r#"
                 UPDATE payment_intents
-                SET status = 'captured',
+                SET status = 'cancelled',
                     updated_at = NOW()
                 WHERE id = $1
                 "#,
             )
-            .bind(payment_intent_id)
-            .execute(pool)
+            .bind(&payment_intent.id)
+            .execute(&mut *tx)
             .await?;
 
-            info!(
-                "Updated payment intent {} status to 'captured'",
-                payment_intent_id
+            // Never authorized, so the buyer's reservation is still holding the ticket
+            if !matches!(status, PaymentIntentStatus::Capturable) {
+                release_reservation(
+                    &mut tx,
+                    ticket_id,
+                    buyer_id,
+                    created_at,
+                    &format!("payment intent canceled: {}", reason),
+                )
+                .await?;
+            }
+        }
+        PaymentIntentStatus::Cancelled => {}
+        other => {
+            warn!(
+                "Payment intent {} canceled at Stripe but stored as {:?}, leaving as is",
+                payment_intent.id, other
             );
+        }
+    }
 
-            // Call Stripe API to capture funds
-            capture_payment_intent(payment_intent_id).await.map_err(|e| {
-                error!(
-                    "Failed to capture payment intent {} for ticket {}: {}",
-                    payment_intent_id, ticket_id, e
-                );
-                // Ticket status is already 'paid' - this requires manual intervention
-                // Log error but don't fail the webhook (return 200 to Stripe)
-                // TODO: Implement retry mechanism or alert system
-                e
-            })?;
+    // Stripe already did what a queued cancel would have
+    sqlx::query(
+        r#"
+        UPDATE stripe_operations
+        SET status = 'succeeded',
+            last_error = NULL,
+            updated_at = NOW()
+        WHERE payment_intent_id = $1
+          AND operation = 'cancel'
+          AND status IN ('pending', 'dead')
+        "#,
+    )
+    .bind(&payment_intent.id)
+    .execute(&mut *tx)
+    .await?;
 
-            info!(
-                "Successfully captured payment for ticket {} (payment intent {})",
-                ticket_id, payment_intent_id
-            );
+    let dead_capture = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE stripe_operations
+        SET status = 'dead',
+            last_error = $2,
+            updated_at = NOW()
+        WHERE payment_intent_id = $1
+          AND operation = 'capture'
+          AND status = 'pending'
+        RETURNING id
+        "#,
+    )
+    .bind(&payment_intent.id)
+    .bind(format!("Payment intent canceled at Stripe ({})", reason))
+    .fetch_optional(&mut *tx)
+    .await?;
 
-            Ok(())
-        }
+    tx.commit().await?;
+
+    if let Some(operation_id) = dead_capture {
+        error!(
+            "Payment intent {} for paid ticket {} was canceled ({}) before capture (operation {}), needs manual intervention",
+            payment_intent.id, ticket_id, reason, operation_id
+        );
+    } else {
+        info!(
+            "Payment intent {} for ticket {} canceled ({})",
+            payment_intent.id, ticket_id, reason
+        );
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent.id
+        })),
+    ))
+}
+
+/// Funds were captured at Stripe
+///
+/// Usually our own queued capture, already recorded by the outbox worker. If the
+/// worker never saw the confirmation (e.g. the response was lost), the intent and
+/// its capture operation are reconciled here.
+async fn handle_payment_succeeded(
+    pool: &PgPool,
+    payment_intent: &StripePaymentIntent,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    let mut tx = pool.begin().await?;
+
+    let stored = sqlx::query_as::<_, (Uuid, PaymentIntentStatus)>(
+        "SELECT ticket_id, status FROM payment_intents WHERE id = $1 FOR UPDATE",
+    )
+    .bind(&payment_intent.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (ticket_id, status) = match stored {
+        Some(stored) => stored,
         None => {
-            // Branch B: Late Path - Reservation expired
-            warn!(
-                "Gatekeeper check failed: Ticket {} reservation expired or invalid (buyer {}, reservation window: {} minutes)",
-                ticket_id, buyer_id, total_reservation_window_minutes
-            );
+            warn!("Captured payment intent {} is unknown", payment_intent.id);
+            return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
+        }
+    };
 
-            // Update payment intent status to 'cancelled'
+    match status {
+        PaymentIntentStatus::Captured | PaymentIntentStatus::Refunded | PaymentIntentStatus::Disputed => {}
+        other => {
             sqlx::query(
                 r#"
                 UPDATE payment_intents
-                SET status = 'cancelled',
+                SET status = 'captured',
                     updated_at = NOW()
                 WHERE id = $1
                 "#,
             )
-            .bind(payment_intent_id)
-            .execute(pool)
+            .bind(&payment_intent.id)
+            .execute(&mut *tx)
             .await?;
 
-            info!(
-                "Updated payment intent {} status to 'cancelled'",
-                payment_intent_id
-            );
-
-            // Call Stripe API to cancel authorization (release hold)
-            cancel_payment_intent(payment_intent_id).await.map_err(|e| {
+            if matches!(other, PaymentIntentStatus::Capturable) {
+                info!("Reconciled payment intent {} as captured", payment_intent.id);
+            } else {
                 error!(
-                    "Failed to cancel payment intent {} for ticket {}: {}",
-                    payment_intent_id, ticket_id, e
+                    "Payment intent {} for ticket {} was captured at Stripe but stored as {:?}, needs manual intervention",
+                    payment_intent.id, ticket_id, other
                 );
-                // Log error but don't fail the webhook (return 200 to Stripe)
-                // May need manual intervention to release hold
-                e
-            })?;
+            }
+        }
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE stripe_operations
+        SET status = 'succeeded',
+            last_error = NULL,
+            updated_at = NOW()
+        WHERE payment_intent_id = $1
+          AND operation = 'capture'
+          AND status IN ('pending', 'dead')
+        "#,
+    )
+    .bind(&payment_intent.id)
+    .execute(&mut *tx)
+    .await?;
 
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent.id
+        })),
+    ))
+}
+
+/// Release a buyer's reservation (reserved → verified)
+///
+/// Only the reservation the intent was created for is released: a newer
+/// reservation by the same buyer is left alone.
+async fn release_reservation(
+    conn: &mut PgConnection,
+    ticket_id: Uuid,
+    buyer_id: Uuid,
+    intent_created_at: DateTime<Utc>,
+    reason: &str,
+) -> Result<bool> {
+    let transition = Transition::ReleaseReservation {
+        ticket_id,
+        buyer_id,
+        intent_created_at,
+        reason,
+    };
+    let released = apply_transition_on(conn, &WEBHOOK_ACTOR, &transition).await?;
+
+    Ok(!released.is_empty())
+}
+
+/// A charge was refunded in full (by the refund flow or from the Stripe dashboard)
+///
+/// Marks the intent refunded, moves a paid/refunding ticket to 'refunded' and cancels
+/// any payout that hasn't been transferred yet. Partial refunds are only logged.
+async fn handle_charge_refunded(
+    pool: &PgPool,
+    charge: &StripeCharge,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    let payment_intent_id = match &charge.payment_intent {
+        Some(id) => id,
+        None => {
+            info!("Refunded charge {} has no payment intent, ignoring", charge.id);
+            return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
+        }
+    };
+
+    if !charge.refunded {
+        warn!(
+            "Charge {} (payment intent {}) partially refunded: {} of {}",
+            charge.id, payment_intent_id, charge.amount_refunded, charge.amount
+        );
+        return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
+    }
+
+    let ticket_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT ticket_id FROM payment_intents WHERE id = $1",
+    )
+    .bind(payment_intent_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match ticket_id {
+        Some(ticket_id) => {
+            mark_refunded(pool, &WEBHOOK_ACTOR, payment_intent_id, ticket_id).await?;
             info!(
-                "Successfully cancelled payment intent {} for expired reservation on ticket {}",
-                payment_intent_id, ticket_id
+                "Charge {} refunded: payment intent {} and ticket {} marked refunded",
+                charge.id, payment_intent_id, ticket_id
             );
+        }
+        None => warn!("Refunded charge {} has unknown payment intent {}", charge.id, payment_intent_id),
+    }
 
-            Ok(())
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent_id
+        })),
+    ))
+}
+
+/// A buyer disputed a charge
+///
+/// Marks the intent disputed and holds the seller payout if it hasn't been
+/// transferred yet. Payouts already sent need manual follow-up.
+async fn handle_dispute_created(
+    pool: &PgPool,
+    dispute: &StripeDispute,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    let payment_intent_id = match &dispute.payment_intent {
+        Some(id) => id,
+        None => {
+            warn!("Dispute {} on charge {} has no payment intent", dispute.id, dispute.charge);
+            return Ok((StatusCode::OK, Json(serde_json::json!({ "received": true }))));
         }
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE payment_intents
+        SET status = 'disputed',
+            updated_at = NOW()
+        WHERE id = $1
+          AND status = 'captured'
+        "#,
+    )
+    .bind(payment_intent_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let held = sqlx::query(
+        r#"
+        UPDATE payouts
+        SET status = 'held',
+            updated_at = NOW()
+        WHERE payment_intent_id = $1
+          AND status = 'pending'
+        "#,
+    )
+    .bind(payment_intent_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let transferred = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM payouts WHERE payment_intent_id = $1 AND status = 'transferred'",
+    )
+    .bind(payment_intent_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    warn!(
+        "Dispute {} opened on payment intent {} (amount {}, reason {}), held {} payouts",
+        dispute.id, payment_intent_id, dispute.amount, dispute.reason, held.rows_affected()
+    );
+
+    if let Some(payout_id) = transferred {
+        error!(
+            "Disputed payment intent {} was already paid out (payout {}), needs manual intervention",
+            payment_intent_id, payout_id
+        );
     }
+
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "received": true,
+            "payment_intent_id": payment_intent_id
+        })),
+    ))
 }
 
+/// Stage 4: Gatekeeper Check
+/// Determines if the "freeze" becomes a "charge" or a "release", returning the
+/// Stripe operation to queue. Runs inside the webhook's transaction.
+/// 
+/// Happy Path: Ticket is still reserved by this buyer within the reservation window
+///   and the authorized amount matches `price_at_reservation`
+///   - Updates ticket status to 'paid'
+///   - Queues the capture of the buyer's payment
+/// 
+/// Late Path: Reservation expired (or the authorized amount does not match)
+///   - Queues the cancel of the payment intent (releases authorization hold)
+///   - Ticket status remains unchanged (will be reset to 'verified' by cleanup job)
+async fn perform_gatekeeper_check(
+    conn: &mut PgConnection,
+    payment_intent_id: &str,
+    ticket_id: Uuid,
+    buyer_id: Uuid,
+    amount: i64,
+) -> Result<StripeOperationType> {
+    info!(
+        "Performing gatekeeper check for payment intent {} on ticket {}",
+        payment_intent_id, ticket_id
+    );
+
+    // Calculate expiry time: NOW() - INTERVAL '1 minute' * TOTAL_RESERVATION_WINDOW_MINUTES
+    // Tickets with reserved_at older than this are considered expired
+    let window_minutes = total_reservation_window_minutes();
+    let expiry_time = Utc::now() - chrono::Duration::minutes(window_minutes);
+
+    let transition = Transition::Pay {
+        ticket_id,
+        buyer_id,
+        reserved_after: expiry_time,
+        amount,
+    };
+    let paid = apply_transition_on(conn, &WEBHOOK_ACTOR, &transition).await?;
+
+    match paid.first() {
+        Some(_) => {
+            // Branch A: Happy Path - Reservation is still valid
+            info!(
+                "Gatekeeper check passed: Ticket {} reserved by buyer {} within {} minutes",
+                ticket_id, buyer_id, window_minutes
+            );
+
+            // Capture; the intent is marked 'captured' once Stripe confirms
+            Ok(StripeOperationType::Capture)
+        }
+        None => {
+            // Branch B: Late Path - Reservation expired
+            warn!(
+                "Gatekeeper check failed: Ticket {} reservation expired, invalid or amount {} mismatched (buyer {}, reservation window: {} minutes)",
+                ticket_id, amount, buyer_id, window_minutes
+            );
+
+            // Cancel (release hold); the intent is marked 'cancelled' once Stripe confirms
+            Ok(StripeOperationType::Cancel)
+        }
+    }
+}
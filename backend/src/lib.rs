@@ -0,0 +1,24 @@
+use axum::{
+    http::StatusCode,
+    response::Json,
+};
+use serde_json::{json, Value};
+
+pub mod db;
+pub mod error;
+pub mod handlers;
+pub mod models;
+pub mod routes;
+pub mod state;
+pub mod utils;
+
+pub async fn health_check() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::OK,
+        Json(json!({
+            "status": "healthy",
+            "service": "ticket-marketplace-backend",
+            "version": env!("CARGO_PKG_VERSION")
+        })),
+    )
+}
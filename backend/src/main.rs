@@ -1,27 +1,6 @@
-use axum::{
-    http::StatusCode,
-    response::Json,
-};
-use serde_json::{json, Value};
 use std::net::SocketAddr;
 
-mod db;
-mod error;
-mod handlers;
-mod models;
-mod routes;
-mod utils;
-
-pub async fn health_check() -> (StatusCode, Json<Value>) {
-    (
-        StatusCode::OK,
-        Json(json!({
-            "status": "healthy",
-            "service": "ticket-marketplace-backend",
-            "version": env!("CARGO_PKG_VERSION")
-        })),
-    )
-}
+use backend::{db, routes, state::AppState, utils};
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
@@ -32,24 +11,42 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
+    // Set up email delivery before accepting registrations
+    let mailer = utils::mailer::mailer_from_env()?;
+
     // Create database connection pool
     let pool = db::create_pool().await?;
 
     // Run database migrations
     db::run_migrations(&pool).await?;
 
+    // Give the configured bootstrap admin their role
+    utils::auth::promote_bootstrap_admin(&pool).await?;
+
+    // Connect to the payment provider (Stripe unless PAYMENT_GATEWAY says otherwise)
+    let payments = utils::payment_gateway::payment_gateway_from_env()?;
+
     // Start background cleanup tasks
-    utils::cleanup::start_cleanup_tasks(pool.clone());
+    utils::cleanup::start_cleanup_tasks(pool.clone(), payments.clone());
+
+    // Start the Stripe capture/cancel outbox worker
+    utils::stripe_outbox::start_stripe_outbox_worker(pool.clone(), payments.clone());
 
     // Build our application with routes
-    let app = routes::create_router(pool);
+    let app = routes::create_router(AppState {
+        pool,
+        payments,
+        mailer,
+        dev_mode: utils::mailer::DevMode::from_env(),
+    });
 
     // Run it
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     tracing::info!("Server listening on {}", addr);
     
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed to rate limit anonymous requests by IP
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
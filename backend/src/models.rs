@@ -3,3 +3,9 @@ pub mod game;
 pub mod ticket;
 pub mod payment_intent;
 
+pub mod payout;
+pub mod stripe_operation;
+pub mod stripe_discrepancy;
+pub mod admin;
+pub mod ticket_event;
+pub mod purchase;
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::user::UserRole;
+
+/// Admin API key model from database (the key itself is never stored)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to create an admin API key
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+/// Response for a created API key; `key` is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub key: String,
+    pub api_key: AdminApiKey,
+}
+
+/// Response for list API keys endpoint
+#[derive(Debug, Serialize)]
+pub struct ListApiKeysResponse {
+    pub api_keys: Vec<AdminApiKey>,
+}
+
+/// Request to change a user's role
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRoleRequest {
+    pub role: String,
+}
+
+/// Response for update user role endpoint
+#[derive(Debug, Serialize, FromRow)]
+pub struct UserRoleResponse {
+    pub id: Uuid,
+    pub email: String,
+    pub role: UserRole,
+}
+
+/// Admin audit log entry model from database
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminAuditEntry {
+    pub id: Uuid,
+    pub actor_user_id: Option<Uuid>,
+    pub actor_api_key_id: Option<Uuid>,
+    pub actor_label: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query parameters for admin audit log endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListAuditLogQuery {
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+}
+
+/// Response for admin audit log endpoint
+#[derive(Debug, Serialize)]
+pub struct ListAuditLogResponse {
+    pub entries: Vec<AdminAuditEntry>,
+}
+
+/// Bot credential model from database (key hashes are never returned)
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct BotCredential {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub previous_key_expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request to create a bot credential
+#[derive(Debug, Deserialize)]
+pub struct CreateBotCredentialRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+/// Response for a created or rotated bot credential; `key` is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct BotCredentialKeyResponse {
+    pub key: String,
+    pub bot_credential: BotCredential,
+}
+
+/// Response for list bot credentials endpoint
+#[derive(Debug, Serialize)]
+pub struct ListBotCredentialsResponse {
+    pub bot_credentials: Vec<BotCredential>,
+}
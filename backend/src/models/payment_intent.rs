@@ -8,9 +8,12 @@ use uuid::Uuid;
 #[sqlx(type_name = "payment_intent_status", rename_all = "lowercase")]
 pub enum PaymentIntentStatus {
     Created,
+    Failed,
     Capturable,
     Captured,
     Cancelled,
+    Refunded,
+    Disputed,
 }
 
 /// Payment Intent model from database
@@ -26,33 +29,73 @@ pub struct PaymentIntent {
     pub updated_at: DateTime<Utc>,
 }
 
-/// Stripe webhook event payload structure
+/// Stripe webhook event, dispatched on its `type`
+///
+/// Only the fields we use are deserialized; unhandled event types map to `Other`.
 #[derive(Debug, Deserialize)]
-pub struct StripeWebhookEvent {
-    pub id: Option<String>, // Event ID (evt_xxx) - optional for deserialization
-    #[serde(rename = "type")]
-    pub event_type: String,
-    pub data: StripeWebhookData,
+#[serde(tag = "type")]
+pub enum StripeWebhookEvent {
+    #[serde(rename = "payment_intent.amount_capturable_updated")]
+    PaymentIntentAmountCapturableUpdated { data: StripeEventData<StripePaymentIntent> },
+    #[serde(rename = "payment_intent.payment_failed")]
+    PaymentIntentPaymentFailed { data: StripeEventData<StripePaymentIntent> },
+    #[serde(rename = "payment_intent.canceled")]
+    PaymentIntentCanceled { data: StripeEventData<StripePaymentIntent> },
+    #[serde(rename = "payment_intent.succeeded")]
+    PaymentIntentSucceeded { data: StripeEventData<StripePaymentIntent> },
+    #[serde(rename = "charge.refunded")]
+    ChargeRefunded { data: StripeEventData<StripeCharge> },
+    #[serde(rename = "charge.dispute.created")]
+    ChargeDisputeCreated { data: StripeEventData<StripeDispute> },
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct StripeWebhookData {
-    pub object: StripePaymentIntent,
+pub struct StripeEventData<T> {
+    pub object: T,
 }
 
+/// PaymentIntent object from payment_intent.* webhook events
 #[derive(Debug, Deserialize)]
 pub struct StripePaymentIntent {
     pub id: String,
     pub amount: i64,
     pub currency: String,
-    pub status: String,
     pub metadata: StripeMetadata,
+    pub cancellation_reason: Option<String>,
+    pub last_payment_error: Option<StripePaymentError>,
 }
 
+/// Metadata set by the checkout endpoint (absent on intents created elsewhere)
 #[derive(Debug, Deserialize)]
 pub struct StripeMetadata {
-    pub ticket_id: String,
-    pub buyer_id: String,
-    pub reserved_at: String,
+    pub ticket_id: Option<String>,
+    pub buyer_id: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StripePaymentError {
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Charge object from charge.* webhook events
+#[derive(Debug, Deserialize)]
+pub struct StripeCharge {
+    pub id: String,
+    pub payment_intent: Option<String>,
+    pub amount: i64,
+    pub amount_refunded: i64,
+    pub refunded: bool,
+}
+
+/// Dispute object from charge.dispute.* webhook events
+#[derive(Debug, Deserialize)]
+pub struct StripeDispute {
+    pub id: String,
+    pub charge: String,
+    pub payment_intent: Option<String>,
+    pub amount: i64,
+    pub reason: String,
+}
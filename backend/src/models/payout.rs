@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Payout status enum
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "payout_status", rename_all = "lowercase")]
+pub enum PayoutStatus {
+    Pending,
+    Transferred,
+    Failed,
+    Held,
+    Cancelled,
+}
+
+/// Payout model from database
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Payout {
+    pub id: Uuid,
+    pub ticket_id: Uuid,
+    pub seller_id: Uuid,
+    pub amount: i32,
+    pub platform_fee: i32,
+    pub currency: String,
+    pub status: PayoutStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stripe_transfer_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Response for seller payout onboarding endpoint
+#[derive(Debug, Serialize)]
+pub struct PayoutOnboardingResponse {
+    pub stripe_account_id: String,
+    pub onboarding_url: String,
+}
+
+/// Response for list payouts endpoint
+#[derive(Debug, Serialize)]
+pub struct ListPayoutsResponse {
+    pub payouts: Vec<Payout>,
+}
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::payment_intent::PaymentIntentStatus;
+use crate::models::ticket::TicketStatus;
+
+/// A ticket the buyer holds a reservation on or has paid for
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct Purchase {
+    pub ticket_id: Uuid,
+    pub game_id: Uuid,
+    pub event_name: String,
+    pub event_date: DateTime<Utc>,
+    pub level: String,
+    pub seat_section: String,
+    pub seat_row: String,
+    pub seat_number: String,
+    /// Price locked in when the ticket was reserved
+    pub price: i32,
+    pub status: TicketStatus,
+    pub reserved_at: DateTime<Utc>,
+    /// When the reservation lapses unless paid; only while `Reserved`
+    pub reservation_expires_at: Option<DateTime<Utc>>,
+    /// Seconds left on the reservation, counted by the server
+    pub reservation_seconds_left: Option<i64>,
+    /// Latest payment intent of this reservation; none before checkout
+    pub payment_status: Option<PaymentIntentStatus>,
+    /// When the ticket was transferred to the buyer
+    pub sold_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters for purchases endpoint
+#[derive(Debug, Deserialize)]
+pub struct PurchasesQuery {
+    pub status: Option<String>,
+}
+
+/// Response for purchases endpoint
+#[derive(Debug, Serialize)]
+pub struct ListPurchasesResponse {
+    pub purchases: Vec<Purchase>,
+}
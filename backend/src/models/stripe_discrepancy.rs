@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Discrepancy kind enum
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "discrepancy_kind", rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    MissingLocally,
+    MissingAtStripe,
+    AmountMismatch,
+    CurrencyMismatch,
+    StatusMismatch,
+    MetadataMismatch,
+    TicketStatusMismatch,
+}
+
+/// Stripe discrepancy model from database
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct StripeDiscrepancy {
+    pub id: Uuid,
+    pub payment_intent_id: String,
+    pub ticket_id: Option<Uuid>,
+    pub kind: DiscrepancyKind,
+    pub local_value: Option<String>,
+    pub stripe_value: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub expired: bool,
+}
+
+/// Query parameters for list discrepancies endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListDiscrepanciesQuery {
+    pub include_resolved: Option<bool>,
+}
+
+/// Response for list discrepancies endpoint
+#[derive(Debug, Serialize)]
+pub struct ListDiscrepanciesResponse {
+    pub discrepancies: Vec<StripeDiscrepancy>,
+}
+
+/// Query parameters for the run reconciliation endpoint
+#[derive(Debug, Deserialize)]
+pub struct RunReconciliationQuery {
+    pub lookback_hours: Option<i64>,
+}
+
+/// Result of a reconciliation run
+#[derive(Debug, Serialize)]
+pub struct ReconciliationSummary {
+    pub checked: usize,
+    pub discrepancies: usize,
+    pub resolved: u64,
+    pub expired: u64,
+}
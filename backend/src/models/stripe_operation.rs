@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Stripe operation type enum
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "stripe_operation_type", rename_all = "lowercase")]
+pub enum StripeOperationType {
+    Capture,
+    Cancel,
+}
+
+/// Stripe operation status enum
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "stripe_operation_status", rename_all = "lowercase")]
+pub enum StripeOperationStatus {
+    Pending,
+    Succeeded,
+    Dead,
+    Cancelled,
+}
+
+/// Stripe operation (outbox entry) model from database
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct StripeOperation {
+    pub id: Uuid,
+    pub payment_intent_id: String,
+    pub operation: StripeOperationType,
+    pub status: StripeOperationStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Query parameters for list Stripe operations endpoint
+#[derive(Debug, Deserialize)]
+pub struct ListStripeOperationsQuery {
+    pub status: Option<String>,
+}
+
+/// Response for list Stripe operations endpoint
+#[derive(Debug, Serialize)]
+pub struct ListStripeOperationsResponse {
+    pub operations: Vec<StripeOperation>,
+}
@@ -3,8 +3,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use crate::models::game::Game;
+
 /// Database ticket_status enum mapping
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "ticket_status", rename_all = "lowercase")]
 pub enum TicketStatus {
     Unverified,
@@ -13,6 +15,8 @@ pub enum TicketStatus {
     Reserved,
     Paid,
     Sold,
+    Refunding,
+    Refunded,
     Cancelled,
 }
 
@@ -80,12 +84,97 @@ pub struct ListTicketsResponse {
     pub tickets: Vec<Ticket>,
 }
 
+/// Query parameters for the ticket search endpoint
+///
+/// Every filter is optional; `cursor` is the `next_cursor` of the previous page
+/// and is only valid with the same `sort`.
+#[derive(Debug, Deserialize)]
+pub struct ListTicketsQuery {
+    /// Case-insensitive match anywhere in the event name
+    pub q: Option<String>,
+    pub game_id: Option<Uuid>,
+    pub sport_type: Option<String>,
+    pub min_price: Option<i32>,
+    pub max_price: Option<i32>,
+    pub level: Option<String>,
+    pub section: Option<String>,
+    /// Earliest event date (inclusive)
+    pub date_from: Option<DateTime<Utc>>,
+    /// Latest event date (inclusive)
+    pub date_to: Option<DateTime<Utc>>,
+    /// `date` (default), `price`, `price_desc` or `newest`
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+/// Response for the ticket search endpoint
+#[derive(Debug, Serialize)]
+pub struct TicketSearchResponse {
+    pub tickets: Vec<Ticket>,
+    /// Matching tickets across all pages
+    pub total: i64,
+    /// Pass as `cursor` to fetch the next page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// Whether a buyer can get a ticket, as shown on its detail page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TicketAvailability {
+    /// On sale and can be reserved
+    Available,
+    /// Held by a buyer; goes back on sale if the reservation lapses unpaid
+    Reserved,
+    /// Paid for by a buyer
+    Sold,
+    /// Withdrawn from sale or refunded
+    Unavailable,
+}
+
+/// Response for ticket detail endpoint
+///
+/// The raw status and reservation fields are only filled in for the seller and
+/// the current reserver; everyone else sees `availability`.
+#[derive(Debug, Serialize)]
+pub struct TicketDetailResponse {
+    pub id: Uuid,
+    pub seller_id: Uuid,
+    pub game: Game,
+    pub level: String,
+    pub seat_section: String,
+    pub seat_row: String,
+    pub seat_number: String,
+    pub price: i32,
+    pub availability: TicketAvailability,
+    pub created_at: DateTime<Utc>,
+    pub is_seller: bool,
+    pub is_reserver: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<TicketStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reserved_by: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reserved_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_at_reservation: Option<i32>,
+    /// When the current reservation lapses unless paid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reservation_expires_at: Option<DateTime<Utc>>,
+}
+
 /// Query parameters for my-listings endpoint
 #[derive(Debug, Deserialize)]
 pub struct MyListingsQuery {
     pub status: Option<String>,
 }
 
+/// Request payload for seller ticket update endpoint
+#[derive(Debug, Deserialize)]
+pub struct UpdateTicketRequest {
+    pub status: Option<String>,
+    pub price: Option<i32>,
+}
+
 /// Response for reserve ticket endpoint
 #[derive(Debug, Serialize)]
 pub struct ReserveTicketResponse {
@@ -95,6 +184,13 @@ pub struct ReserveTicketResponse {
     pub reserved_at: DateTime<Utc>,
 }
 
+/// Response for checkout endpoint
+#[derive(Debug, Serialize)]
+pub struct CheckoutResponse {
+    pub ticket_id: Uuid,
+    pub client_secret: String,
+}
+
 /// Generic status response for bot verify/rollback
 #[derive(Debug, Serialize, FromRow)]
 pub struct TicketStatusResponse {
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::ticket::TicketStatus;
+
+/// Ticket status change from the append-only `ticket_events` table
+///
+/// `from_status` is null for the event that created the ticket and `to_status`
+/// is null once the ticket was deleted.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct TicketEvent {
+    pub id: i64,
+    pub ticket_id: Uuid,
+    pub from_status: Option<TicketStatus>,
+    pub to_status: Option<TicketStatus>,
+    pub actor_type: String,
+    pub actor_id: Option<Uuid>,
+    pub actor_label: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Response for ticket timeline endpoint
+#[derive(Debug, Serialize)]
+pub struct TicketTimelineResponse {
+    pub ticket_id: Uuid,
+    pub events: Vec<TicketEvent>,
+}
@@ -3,6 +3,15 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// Database user_role enum mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+pub enum UserRole {
+    User,
+    Moderator,
+    Admin,
+}
+
 #[derive(Debug, Clone, FromRow, Serialize)]
 pub struct User {
     pub id: Uuid,
@@ -10,8 +19,12 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub email_verified: bool,
+    pub role: UserRole,
     #[serde(skip_serializing)]
-    pub verification_code: Option<String>,
+    pub verification_code_hash: Option<String>,
+    pub verification_code_sent_at: Option<DateTime<Utc>>,
+    pub verification_attempts: i32,
+    pub verification_locked_until: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -35,6 +48,41 @@ pub struct RegisterResponse {
     pub verification_code: Option<String>, // Only in development/testing
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResendVerificationResponse {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_code: Option<String>, // Only in development/testing
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_token: Option<String>, // Only in development/testing
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct VerifyEmailResponse {
     pub message: String,
@@ -52,11 +100,30 @@ pub struct UserInfo {
     pub id: Uuid,
     pub email: String,
     pub email_verified: bool,
+    pub role: UserRole,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user: UserInfo,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    pub message: String,
+    pub sessions_revoked: u64,
+}
+
@@ -2,34 +2,74 @@ use axum::{
     routing::{delete, get, patch, post},
     Router,
 };
-use sqlx::PgPool;
 use tower_http::cors::CorsLayer;
 
-use crate::handlers::{auth, games, tickets, webhooks};
+use crate::handlers::{
+    admin, auth, games, payouts, purchases, reconciliation, stripe_operations, tickets, webhooks,
+};
+use crate::state::AppState;
 use crate::utils::rate_limit::RateLimitLayer;
 
-pub fn create_router(pool: PgPool) -> Router {
-    // Create rate-limited reservation route
+pub fn create_router(state: AppState) -> Router {
+    // Create rate-limited reservation and checkout routes
     let reservation_routes = Router::new()
-        .route("/api/tickets/:id/reserve", post(tickets::reserve_ticket))
-        .layer(RateLimitLayer::new());
+        .route(
+            "/api/tickets/:id/reserve",
+            post(tickets::reserve_ticket).delete(tickets::release_reservation),
+        )
+        .route("/api/tickets/:id/checkout", post(tickets::checkout_ticket))
+        .layer(RateLimitLayer::new("reservation"));
 
-    Router::new()
-        .route("/health", get(crate::health_check))
+    // Create rate-limited auth routes (keyed by client IP, as callers are anonymous)
+    let auth_routes = Router::new()
         .route("/api/auth/register", post(auth::register))
         .route("/api/auth/verify-email", post(auth::verify_email))
+        .route("/api/auth/resend-verification", post(auth::resend_verification))
         .route("/api/auth/login", post(auth::login))
+        .route("/api/auth/forgot-password", post(auth::forgot_password))
+        .route("/api/auth/reset-password", post(auth::reset_password))
+        .route("/api/auth/refresh", post(auth::refresh))
+        .route("/api/auth/logout", post(auth::logout))
+        .route("/api/auth/logout-all", post(auth::logout_all))
+        .layer(RateLimitLayer::new("auth"));
+
+    Router::new()
+        .route("/health", get(crate::health_check))
         .route("/api/games", get(games::list_games).post(games::create_game))
         .route("/api/games/:id", delete(games::delete_game))
         .route("/api/tickets", get(tickets::list_tickets).post(tickets::create_ticket))
+        .route("/api/tickets/:id", get(tickets::get_ticket).patch(tickets::update_ticket))
         .route("/api/tickets/claim", post(tickets::claim_ticket))
         .route("/api/tickets/:id/verify", patch(tickets::verify_ticket))
         .route("/api/tickets/:id/unclaim", delete(tickets::unclaim_ticket))
         .route("/api/tickets/:id/sold", patch(tickets::mark_sold))
+        .route("/api/tickets/:id/refund", post(tickets::refund_ticket))
+        .route("/api/tickets/:id/events", get(tickets::get_ticket_events))
         .route("/api/tickets/my-listings", get(tickets::my_listings))
+        .route("/api/me/purchases", get(purchases::list_purchases))
+        .route("/api/payouts", get(payouts::list_payouts))
+        .route("/api/payouts/onboarding", post(payouts::start_onboarding))
+        .route("/api/admin/stripe-operations", get(stripe_operations::list_stripe_operations))
+        .route(
+            "/api/admin/stripe-operations/:id/retry",
+            post(stripe_operations::retry_stripe_operation),
+        )
+        .route("/api/admin/reconciliation", post(reconciliation::run_reconciliation))
+        .route("/api/admin/discrepancies", get(reconciliation::list_discrepancies))
+        .route("/api/admin/api-keys", get(admin::list_api_keys).post(admin::create_api_key))
+        .route("/api/admin/api-keys/:id", delete(admin::revoke_api_key))
+        .route("/api/admin/users/:id/role", patch(admin::update_user_role))
+        .route(
+            "/api/admin/bot-credentials",
+            get(admin::list_bot_credentials).post(admin::create_bot_credential),
+        )
+        .route("/api/admin/bot-credentials/:id", delete(admin::revoke_bot_credential))
+        .route("/api/admin/bot-credentials/:id/rotate", post(admin::rotate_bot_credential))
+        .route("/api/admin/audit-log", get(admin::list_audit_log))
         .route("/api/webhooks/stripe", post(webhooks::handle_stripe_webhook))
         .merge(reservation_routes)
+        .merge(auth_routes)
         .layer(CorsLayer::permissive())
-        .with_state(pool)
+        .with_state(state)
 }
 
@@ -0,0 +1,42 @@
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::utils::mailer::{DevMode, Mailer};
+use crate::utils::payment_gateway::PaymentGateway;
+
+/// Shared application state
+///
+/// Handlers extract the parts they need, e.g. `State<PgPool>` or
+/// `State<Arc<dyn PaymentGateway>>`.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: PgPool,
+    pub payments: Arc<dyn PaymentGateway>,
+    pub mailer: Arc<dyn Mailer>,
+    pub dev_mode: DevMode,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn PaymentGateway> {
+    fn from_ref(state: &AppState) -> Self {
+        state.payments.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Mailer> {
+    fn from_ref(state: &AppState) -> Self {
+        state.mailer.clone()
+    }
+}
+
+impl FromRef<AppState> for DevMode {
+    fn from_ref(state: &AppState) -> Self {
+        state.dev_mode
+    }
+}
@@ -1,8 +1,19 @@
+pub mod audit;
 pub mod auth;
 pub mod cleanup;
+pub mod config;
 pub mod email;
 pub mod jwt;
+pub mod mailer;
 pub mod password;
+pub mod payment_gateway;
+pub mod payouts;
+pub mod permissions;
 pub mod rate_limit;
+pub mod reconciliation;
+pub mod refunds;
+pub mod sessions;
 pub mod stripe;
-
+pub mod stripe_outbox;
+pub mod ticket_events;
+pub mod ticket_state;
@@ -0,0 +1,54 @@
+use serde_json::Value;
+use sqlx::Postgres;
+use tracing::info;
+
+use crate::error::Result;
+use crate::utils::auth::Actor;
+
+/// Record an admin action in `admin_audit_log`
+///
+/// `action` is a short verb such as "game.create"; `target_type`/`target_id`
+/// identify what it was done to, and `details` holds anything else worth keeping.
+pub async fn record_admin_action<'e, E>(
+    executor: E,
+    actor: &Actor,
+    action: &str,
+    target_type: &str,
+    target_id: Option<String>,
+    details: Value,
+) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    let (actor_user_id, actor_api_key_id) = match actor {
+        Actor::User { user_id, .. } => (Some(*user_id), None),
+        Actor::ApiKey { key_id, .. } => (None, Some(*key_id)),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_audit_log
+            (actor_user_id, actor_api_key_id, actor_label, action, target_type, target_id, details)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(actor_user_id)
+    .bind(actor_api_key_id)
+    .bind(actor.label())
+    .bind(action)
+    .bind(target_type)
+    .bind(&target_id)
+    .bind(&details)
+    .execute(executor)
+    .await?;
+
+    info!(
+        "Admin action {} on {} {} by {}",
+        action,
+        target_type,
+        target_id.as_deref().unwrap_or("-"),
+        actor.label()
+    );
+
+    Ok(())
+}
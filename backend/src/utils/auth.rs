@@ -1,52 +1,130 @@
-use axum::http::HeaderMap;
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use sqlx::{FromRow, PgPool};
 use std::env;
+use std::marker::PhantomData;
 use std::sync::{Arc, OnceLock};
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+use uuid::Uuid;
 use crate::error::{AppError, Result};
+use crate::models::user::UserRole;
+use crate::utils::config::{env_in_or, env_or};
+use crate::utils::jwt::extract_session;
+use crate::utils::password::{hash_secure_token, secure_token_matches};
+use crate::utils::permissions::{role_has_permission, CanRefundTickets, Permission, RequiredPermission};
 
-/// Validate admin API key from Authorization header
-pub fn validate_admin_key(headers: &HeaderMap) -> Result<()> {
+/// Prefix that tells admin API keys apart from JWTs
+pub const ADMIN_API_KEY_PREFIX: &str = "tmk_";
+
+/// Prefix of bot credential keys
+pub const BOT_KEY_PREFIX: &str = "tmb_";
+
+/// Characters at the start of an API key or bot key stored as its `key_prefix`
+pub const KEY_PREFIX_LENGTH: usize = 12;
+
+/// Extract the credential from an `Authorization: Bearer <token>` header
+pub fn bearer_token(headers: &HeaderMap) -> Result<&str> {
     let auth_header = headers
-        .get("authorization")
+        .get(AUTHORIZATION)
         .and_then(|h| h.to_str().ok())
         .ok_or(AppError::Unauthorized)?;
 
-    let expected_key = env::var("ADMIN_API_KEY")
-        .map_err(|_| AppError::Internal(anyhow::anyhow!("ADMIN_API_KEY environment variable must be set")))?;
-
-    if auth_header != expected_key {
+    let (scheme, token) = auth_header.split_once(' ').ok_or(AppError::Unauthorized)?;
+    let token = token.trim();
+    if !scheme.eq_ignore_ascii_case("bearer") || token.is_empty() {
         return Err(AppError::Unauthorized);
     }
 
-    Ok(())
+    Ok(token)
 }
 
-/// Validate bot API key from Authorization header
-pub fn validate_bot_key(headers: &HeaderMap) -> Result<()> {
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or(AppError::Unauthorized)?;
+/// An authenticated verification bot
+#[derive(Debug, Clone)]
+pub struct Bot {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+}
 
-    let expected_key = env::var("BOT_API_KEY")
-        .map_err(|_| AppError::Internal(anyhow::anyhow!("BOT_API_KEY environment variable must be set")))?;
+impl Bot {
+    /// Fail with 403 unless the credential is scoped for a permission
+    pub fn require(&self, permission: Permission) -> Result<()> {
+        if !self.scopes.iter().any(|s| s == permission.as_str()) {
+            warn!("bot:{} denied {}", self.name, permission.as_str());
+            return Err(AppError::PermissionDenied);
+        }
+        Ok(())
+    }
+}
+
+#[derive(FromRow)]
+struct BotCredentialCandidate {
+    id: Uuid,
+    name: String,
+    scopes: Vec<String>,
+    key_hash: String,
+    previous_key_hash: Option<String>,
+}
+
+/// How long a bot's previous key keeps working after a rotation
+pub fn bot_key_rotation_grace_minutes() -> i64 {
+    env_in_or("BOT_KEY_ROTATION_GRACE_MINUTES", 0.., 60)
+}
 
-    if auth_header != expected_key {
+/// Authenticate a bot credential from the Authorization header
+///
+/// The key's prefix finds the credential and the full key is checked against
+/// the stored hash in constant time. After a rotation the previous key is
+/// accepted until its grace period ends.
+pub async fn authenticate_bot(pool: &PgPool, headers: &HeaderMap) -> Result<Bot> {
+    let token = bearer_token(headers)?;
+    if !token.starts_with(BOT_KEY_PREFIX) || token.len() <= KEY_PREFIX_LENGTH {
         return Err(AppError::Unauthorized);
     }
 
-    Ok(())
+    let candidates = sqlx::query_as::<_, BotCredentialCandidate>(
+        r#"
+        SELECT id, name, scopes, key_hash,
+               CASE WHEN previous_key_expires_at > NOW() THEN previous_key_hash END AS previous_key_hash
+        FROM bot_credentials
+        WHERE (key_prefix = $1 OR (previous_key_prefix = $1 AND previous_key_expires_at > NOW()))
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(&token[..KEY_PREFIX_LENGTH])
+    .fetch_all(pool)
+    .await?;
+
+    let Some(credential) = candidates.into_iter().find(|c| {
+        secure_token_matches(token, &c.key_hash)
+            || c.previous_key_hash.as_deref().is_some_and(|h| secure_token_matches(token, h))
+    }) else {
+        warn!("Unknown, expired or revoked bot key used");
+        return Err(AppError::Unauthorized);
+    };
+
+    sqlx::query("UPDATE bot_credentials SET last_used_at = NOW() WHERE id = $1")
+        .bind(credential.id)
+        .execute(pool)
+        .await?;
+
+    Ok(Bot {
+        id: credential.id,
+        name: credential.name,
+        scopes: credential.scopes,
+    })
 }
 
 fn bot_semaphore() -> &'static Arc<Semaphore> {
     static BOT_SEMAPHORE: OnceLock<Arc<Semaphore>> = OnceLock::new();
 
     BOT_SEMAPHORE.get_or_init(|| {
-        let limit = env::var("BOT_CONCURRENCY_LIMIT")
-            .ok()
-            .and_then(|v| v.parse::<usize>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(5);
+        let limit = env_or("BOT_CONCURRENCY_LIMIT", 5);
         Arc::new(Semaphore::new(limit))
     })
 }
@@ -60,3 +138,235 @@ pub async fn acquire_bot_permit() -> Result<OwnedSemaphorePermit> {
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Bot semaphore closed: {}", e)))
 }
 
+/// A logged-in user, authenticated by a JWT access token with a live session
+///
+/// Taking this extractor makes the handler reject unauthenticated requests with 401.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub email: String,
+    pub role: UserRole,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let pool = PgPool::from_ref(state);
+        extract_session(&pool, &parts.headers).await
+    }
+}
+
+/// Who is calling an admin endpoint
+#[derive(Debug, Clone)]
+pub enum Actor {
+    /// A logged-in user with an elevated role
+    User { user_id: Uuid, email: String, role: UserRole },
+    /// A scoped admin API key
+    ApiKey { key_id: Uuid, name: String, scopes: Vec<String> },
+}
+
+impl Actor {
+    /// Authenticate an admin caller from an API key or a user access token
+    pub async fn authenticate(pool: &PgPool, headers: &HeaderMap) -> Result<Actor> {
+        let token = bearer_token(headers)?;
+
+        if !token.starts_with(ADMIN_API_KEY_PREFIX) {
+            let user = extract_session(pool, headers).await?;
+            return Ok(Actor::User {
+                user_id: user.user_id,
+                email: user.email,
+                role: user.role,
+            });
+        }
+
+        let key = sqlx::query_as::<_, (Uuid, String, Vec<String>)>(
+            r#"
+            UPDATE admin_api_keys
+            SET last_used_at = NOW()
+            WHERE key_hash = $1
+              AND revoked_at IS NULL
+            RETURNING id, name, scopes
+            "#,
+        )
+        .bind(hash_secure_token(token))
+        .fetch_optional(pool)
+        .await?;
+
+        match key {
+            Some((key_id, name, scopes)) => Ok(Actor::ApiKey { key_id, name, scopes }),
+            None => {
+                warn!("Unknown or revoked admin API key used");
+                Err(AppError::Unauthorized)
+            }
+        }
+    }
+
+    /// Whether the caller's role or key scopes grant a permission
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        match self {
+            Actor::User { role, .. } => role_has_permission(*role, permission),
+            Actor::ApiKey { scopes, .. } => scopes.iter().any(|s| s == permission.as_str()),
+        }
+    }
+
+    /// Fail with 403 unless the caller has a permission
+    pub fn require(&self, permission: Permission) -> Result<()> {
+        if !self.has_permission(permission) {
+            warn!("{} denied {}", self.label(), permission.as_str());
+            return Err(AppError::PermissionDenied);
+        }
+        Ok(())
+    }
+
+    /// User id or API key id
+    pub fn id(&self) -> Uuid {
+        match self {
+            Actor::User { user_id, .. } => *user_id,
+            Actor::ApiKey { key_id, .. } => *key_id,
+        }
+    }
+
+    /// Human-readable identity for logs and the audit trail
+    pub fn label(&self) -> String {
+        match self {
+            Actor::User { email, .. } => format!("user:{}", email),
+            Actor::ApiKey { name, .. } => format!("api_key:{}", name),
+        }
+    }
+}
+
+/// An admin request allowed to do `P` (a `utils::permissions` marker)
+///
+/// Accepts a logged-in user whose role grants the permission, or an admin API
+/// key scoped for it. Anything else is rejected with 401/403 before the handler
+/// runs.
+#[derive(Debug)]
+pub struct AdminCaller<P> {
+    pub actor: Actor,
+    _permission: PhantomData<P>,
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for AdminCaller<P>
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+    P: RequiredPermission,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let pool = PgPool::from_ref(state);
+        let actor = Actor::authenticate(&pool, &parts.headers).await?;
+        actor.require(P::PERMISSION)?;
+
+        Ok(Self {
+            actor,
+            _permission: PhantomData,
+        })
+    }
+}
+
+/// A bot request allowed to do `P` (a `utils::permissions` marker)
+///
+/// Accepts only a bot credential scoped for the permission, and holds a bot
+/// concurrency permit until the handler finishes.
+#[derive(Debug)]
+pub struct BotCaller<P> {
+    pub bot: Bot,
+    _permit: OwnedSemaphorePermit,
+    _permission: PhantomData<P>,
+}
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for BotCaller<P>
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+    P: RequiredPermission,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let pool = PgPool::from_ref(state);
+        let bot = authenticate_bot(&pool, &parts.headers).await?;
+        bot.require(P::PERMISSION)?;
+        let permit = acquire_bot_permit().await?;
+
+        Ok(Self {
+            bot,
+            _permit: permit,
+            _permission: PhantomData,
+        })
+    }
+}
+
+/// A request from the verification bot or an admin allowed to refund tickets
+///
+/// Bots hold a concurrency permit until the handler finishes, as with `BotCaller`.
+#[derive(Debug)]
+pub enum AdminOrBotCaller {
+    Admin(Actor),
+    Bot { bot: Bot, _permit: OwnedSemaphorePermit },
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminOrBotCaller
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let pool = PgPool::from_ref(state);
+
+        if bearer_token(&parts.headers)?.starts_with(BOT_KEY_PREFIX) {
+            let bot = authenticate_bot(&pool, &parts.headers).await?;
+            bot.require(CanRefundTickets::PERMISSION)?;
+            let permit = acquire_bot_permit().await?;
+            return Ok(Self::Bot { bot, _permit: permit });
+        }
+
+        let actor = Actor::authenticate(&pool, &parts.headers).await?;
+        actor.require(CanRefundTickets::PERMISSION)?;
+
+        Ok(Self::Admin(actor))
+    }
+}
+
+/// Email that is made an admin on registration and at startup (`BOOTSTRAP_ADMIN_EMAIL`)
+///
+/// Gives a fresh deployment its first admin, who can then promote others and
+/// create API keys.
+pub fn bootstrap_admin_email() -> Option<String> {
+    env::var("BOOTSTRAP_ADMIN_EMAIL")
+        .ok()
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+}
+
+/// Promote the bootstrap admin if their account already exists
+pub async fn promote_bootstrap_admin(pool: &PgPool) -> Result<()> {
+    let Some(email) = bootstrap_admin_email() else {
+        return Ok(());
+    };
+
+    let promoted = sqlx::query("UPDATE users SET role = 'admin' WHERE LOWER(email) = $1 AND role <> 'admin'")
+        .bind(&email)
+        .execute(pool)
+        .await?;
+
+    if promoted.rows_affected() > 0 {
+        info!("Promoted bootstrap admin {}", email);
+    }
+
+    Ok(())
+}
@@ -1,73 +1,100 @@
 use sqlx::PgPool;
-use std::env;
+use std::sync::Arc;
 use tokio::time::{interval, Duration};
 use tracing::{error, info};
 
-async fn cleanup_expired_unverified(pool: &PgPool) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query(
-        r#"
-        DELETE FROM tickets
-        WHERE id IN (
-            SELECT id
-            FROM tickets
-            WHERE status = 'unverified'
-              AND transfer_deadline <= NOW()
-            FOR UPDATE SKIP LOCKED
-        )
-        "#,
+use crate::error::Result;
+use crate::handlers::tickets::total_reservation_window_minutes;
+use crate::utils::config::env_or;
+use crate::utils::payment_gateway::PaymentGateway;
+use crate::utils::payouts::{process_due_payouts, queue_payouts};
+use crate::utils::reconciliation::{reconcile_payment_intents, reconciliation_lookback_hours};
+use crate::utils::refunds::process_pending_refunds;
+use crate::utils::ticket_events::TicketActor;
+use crate::utils::ticket_state::{apply_transition, Transition};
+
+pub async fn cleanup_expired_unverified(pool: &PgPool) -> Result<u64> {
+    let deleted = apply_transition(
+        pool,
+        &TicketActor::System("transfer_deadline_cleanup"),
+        &Transition::ExpireListing,
     )
-    .execute(pool)
     .await?;
 
-    Ok(result.rows_affected())
+    Ok(deleted.len() as u64)
 }
 
-async fn cleanup_stuck_verifying(pool: &PgPool, verifying_timeout_minutes: i64) -> Result<u64, sqlx::Error> {
+pub async fn cleanup_password_reset_tokens(pool: &PgPool) -> Result<u64> {
     let result = sqlx::query(
         r#"
-        UPDATE tickets
-        SET status = 'unverified',
-            updated_at = NOW()
-        WHERE status = 'verifying'
-          AND updated_at < NOW() - INTERVAL '1 minute' * $1
+        DELETE FROM password_reset_tokens
+        WHERE expires_at < NOW() - INTERVAL '1 day'
         "#,
     )
-    .bind(&verifying_timeout_minutes)
     .execute(pool)
     .await?;
 
     Ok(result.rows_affected())
 }
 
-async fn cleanup_expired_reservations(pool: &PgPool, total_reservation_window_minutes: i64) -> Result<u64, sqlx::Error> {
+pub async fn cleanup_sessions(pool: &PgPool) -> Result<u64> {
     let result = sqlx::query(
         r#"
-        UPDATE tickets
-        SET status = 'verified',
-            reserved_at = NULL,
-            reserved_by = NULL,
-            price_at_reservation = NULL,
-            updated_at = NOW()
-        WHERE status = 'reserved'
-          AND reserved_at < NOW() - INTERVAL '1 minute' * $1
+        DELETE FROM sessions
+        WHERE expires_at < NOW() - INTERVAL '1 day'
+           OR revoked_at < NOW() - INTERVAL '1 day'
         "#,
     )
-    .bind(&total_reservation_window_minutes)
     .execute(pool)
     .await?;
 
     Ok(result.rows_affected())
 }
 
-pub fn start_cleanup_tasks(pool: PgPool) {
+pub async fn cleanup_stuck_verifying(pool: &PgPool, verifying_timeout_minutes: i64) -> Result<u64> {
+    let reset = apply_transition(
+        pool,
+        &TicketActor::System("verifying_cleanup"),
+        &Transition::VerificationTimeout {
+            timeout_minutes: verifying_timeout_minutes,
+        },
+    )
+    .await?;
+
+    Ok(reset.len() as u64)
+}
+
+pub async fn cleanup_expired_reservations(pool: &PgPool, total_reservation_window_minutes: i64) -> Result<u64> {
+    let released = apply_transition(
+        pool,
+        &TicketActor::System("reservation_cleanup"),
+        &Transition::ExpireReservation {
+            window_minutes: total_reservation_window_minutes,
+        },
+    )
+    .await?;
+
+    Ok(released.len() as u64)
+}
+
+pub async fn cleanup_undelivered_paid(pool: &PgPool, paid_transfer_deadline_hours: i64) -> Result<u64> {
+    let refunding = apply_transition(
+        pool,
+        &TicketActor::System("refund_job"),
+        &Transition::RefundOverdue {
+            deadline_hours: paid_transfer_deadline_hours,
+        },
+    )
+    .await?;
+
+    Ok(refunding.len() as u64)
+}
+
+pub fn start_cleanup_tasks(pool: PgPool, payments: Arc<dyn PaymentGateway>) {
     // Cleanup expired unverified tickets
     {
         let pool = pool.clone();
-        let interval_hours = env::var("TRANSFER_DEADLINE_CLEANUP_INTERVAL_HOURS")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(1);
+        let interval_hours = env_or("TRANSFER_DEADLINE_CLEANUP_INTERVAL_HOURS", 1);
         let mut ticker = interval(Duration::from_secs(interval_hours * 3600));
 
         tokio::spawn(async move {
@@ -85,19 +112,39 @@ pub fn start_cleanup_tasks(pool: PgPool) {
         });
     }
 
+    // Cleanup expired password reset tokens and dead sessions
+    {
+        let pool = pool.clone();
+        let mut ticker = interval(Duration::from_secs(3600));
+
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                match cleanup_password_reset_tokens(&pool).await {
+                    Ok(affected) => {
+                        if affected > 0 {
+                            info!("Password reset token cleanup removed {} tokens", affected);
+                        }
+                    }
+                    Err(e) => error!("Password reset token cleanup failed: {}", e),
+                }
+                match cleanup_sessions(&pool).await {
+                    Ok(affected) => {
+                        if affected > 0 {
+                            info!("Session cleanup removed {} sessions", affected);
+                        }
+                    }
+                    Err(e) => error!("Session cleanup failed: {}", e),
+                }
+            }
+        });
+    }
+
     // Cleanup stuck verifying tickets
     {
         let pool = pool.clone();
-        let interval_seconds = env::var("VERIFYING_CLEANUP_INTERVAL_SECONDS")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(60);
-        let verifying_timeout_minutes = env::var("VERIFYING_TIMEOUT_MINUTES")
-            .ok()
-            .and_then(|v| v.parse::<i64>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(10);
+        let interval_seconds = env_or("VERIFYING_CLEANUP_INTERVAL_SECONDS", 60);
+        let verifying_timeout_minutes = env_or("VERIFYING_TIMEOUT_MINUTES", 10);
         let mut ticker = interval(Duration::from_secs(interval_seconds));
 
         tokio::spawn(async move {
@@ -118,16 +165,8 @@ pub fn start_cleanup_tasks(pool: PgPool) {
     // Cleanup expired reservations
     {
         let pool = pool.clone();
-        let interval_seconds = env::var("RESERVATION_CLEANUP_INTERVAL_SECONDS")
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(60);
-        let total_reservation_window_minutes = env::var("TOTAL_RESERVATION_WINDOW_MINUTES")
-            .ok()
-            .and_then(|v| v.parse::<i64>().ok())
-            .filter(|v| *v > 0)
-            .unwrap_or(7);
+        let interval_seconds = env_or("RESERVATION_CLEANUP_INTERVAL_SECONDS", 60);
+        let total_reservation_window_minutes = total_reservation_window_minutes();
         let mut ticker = interval(Duration::from_secs(interval_seconds));
 
         tokio::spawn(async move {
@@ -144,4 +183,82 @@ pub fn start_cleanup_tasks(pool: PgPool) {
             }
         });
     }
+
+    // Retry pending seller payouts
+    {
+        let pool = pool.clone();
+        let payments = payments.clone();
+        let interval_seconds = env_or("PAYOUT_RETRY_INTERVAL_SECONDS", 60);
+        let mut ticker = interval(Duration::from_secs(interval_seconds));
+
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                match queue_payouts(&pool, None).await {
+                    Ok(queued) => {
+                        if !queued.is_empty() {
+                            info!("Payout job queued {} missing payouts", queued.len());
+                        }
+                    }
+                    Err(e) => error!("Payout queueing failed: {}", e),
+                }
+                match process_due_payouts(&pool, payments.as_ref()).await {
+                    Ok(processed) => {
+                        if processed > 0 {
+                            info!("Payout job processed {} payouts", processed);
+                        }
+                    }
+                    Err(e) => error!("Payout processing failed: {}", e),
+                }
+            }
+        });
+    }
+
+    // Refund paid tickets that were never transferred to the buyer
+    {
+        let pool = pool.clone();
+        let payments = payments.clone();
+        let interval_seconds = env_or("REFUND_CLEANUP_INTERVAL_SECONDS", 300);
+        let paid_transfer_deadline_hours = env_or("PAID_TRANSFER_DEADLINE_HOURS", 48);
+        let mut ticker = interval(Duration::from_secs(interval_seconds));
+
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                match cleanup_undelivered_paid(&pool, paid_transfer_deadline_hours).await {
+                    Ok(affected) => {
+                        if affected > 0 {
+                            info!("Undelivered paid cleanup moved {} tickets to refunding", affected);
+                        }
+                    }
+                    Err(e) => error!("Undelivered paid cleanup failed: {}", e),
+                }
+                match process_pending_refunds(&pool, payments.as_ref()).await {
+                    Ok(processed) => {
+                        if processed > 0 {
+                            info!("Refund job processed {} tickets", processed);
+                        }
+                    }
+                    Err(e) => error!("Refund processing failed: {}", e),
+                }
+            }
+        });
+    }
+
+    // Compare recent payment intents with Stripe and record discrepancies
+    {
+        let pool = pool.clone();
+        let interval_minutes = env_or("RECONCILIATION_INTERVAL_MINUTES", 60);
+        let lookback_hours = reconciliation_lookback_hours();
+        let mut ticker = interval(Duration::from_secs(interval_minutes * 60));
+
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                if let Err(e) = reconcile_payment_intents(&pool, payments.as_ref(), lookback_hours).await {
+                    error!("Stripe reconciliation failed: {}", e);
+                }
+            }
+        });
+    }
 }
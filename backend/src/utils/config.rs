@@ -0,0 +1,55 @@
+use std::{
+    env,
+    ops::{Bound, RangeBounds},
+    str::FromStr,
+};
+
+/// Read a numeric setting that must be positive, falling back to `default`
+/// when it's unset, unparsable or out of range
+pub fn env_or<T: FromStr + PartialOrd + Default>(name: &str, default: T) -> T {
+    env_positive(name).unwrap_or(default)
+}
+
+/// Read a numeric setting that must fall within `range`, falling back to `default`
+pub fn env_in_or<T: FromStr + PartialOrd>(name: &str, range: impl RangeBounds<T>, default: T) -> T {
+    env_in(name, range).unwrap_or(default)
+}
+
+/// Read a numeric setting that must be positive, if it's set
+pub fn env_positive<T: FromStr + PartialOrd + Default>(name: &str) -> Option<T> {
+    env_in(name, (Bound::Excluded(T::default()), Bound::Unbounded))
+}
+
+fn env_in<T: FromStr + PartialOrd>(name: &str, range: impl RangeBounds<T>) -> Option<T> {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<T>().ok())
+        .filter(|v| range.contains(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_or_falls_back_unless_positive() {
+        assert_eq!(env_or("CONFIG_TEST_UNSET", 7i64), 7);
+
+        env::set_var("CONFIG_TEST_POSITIVE", " 12 ");
+        assert_eq!(env_or("CONFIG_TEST_POSITIVE", 7i64), 12);
+
+        for value in ["0", "-3", "soon", ""] {
+            env::set_var("CONFIG_TEST_REJECTED", value);
+            assert_eq!(env_or("CONFIG_TEST_REJECTED", 7u64), 7, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn env_in_or_respects_the_range() {
+        env::set_var("CONFIG_TEST_ZERO", "0");
+        assert_eq!(env_in_or("CONFIG_TEST_ZERO", 0.., 60i64), 0);
+
+        env::set_var("CONFIG_TEST_PERCENT", "101");
+        assert_eq!(env_in_or("CONFIG_TEST_PERCENT", 0..=100, 0i64), 0);
+    }
+}
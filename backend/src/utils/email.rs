@@ -1,6 +1,9 @@
-use crate::error::{AppError, Result};
+use bcrypt::{hash, verify, DEFAULT_COST};
 use rand::Rng;
 
+use crate::error::{AppError, Result};
+use crate::utils::config::env_or;
+
 /// Validates if an email is msu.edu
 pub fn validate_school_email(email: &str) -> Result<()> {
     // Basic email format validation
@@ -24,3 +27,34 @@ pub fn generate_verification_code() -> String {
     format!("{:06}", rng.gen_range(100000..=999999))
 }
 
+/// Hashes a verification code for storage
+pub fn hash_verification_code(code: &str) -> Result<String> {
+    hash(code, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Verification code hashing failed: {}", e)))
+}
+
+/// Verifies a verification code against its stored hash
+pub fn verify_verification_code(code: &str, hash: &str) -> Result<bool> {
+    verify(code, hash)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Verification code check failed: {}", e)))
+}
+
+/// How long a verification code stays valid
+pub fn verification_code_ttl_minutes() -> i64 {
+    env_or("VERIFICATION_CODE_TTL_MINUTES", 15)
+}
+
+/// Minimum time between verification emails to the same address
+pub fn verification_resend_cooldown_seconds() -> i64 {
+    env_or("VERIFICATION_RESEND_COOLDOWN_SECONDS", 60)
+}
+
+/// Wrong guesses allowed before the code is invalidated and the account locked out
+pub fn verification_max_attempts() -> i32 {
+    env_or("VERIFICATION_MAX_ATTEMPTS", 5)
+}
+
+/// How long verification stays locked after too many wrong guesses
+pub fn verification_lockout_minutes() -> i64 {
+    env_or("VERIFICATION_LOCKOUT_MINUTES", 15)
+}
@@ -3,25 +3,37 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use chrono::{Duration, Utc};
 use axum::http::HeaderMap;
+use sqlx::PgPool;
 use uuid::Uuid;
 use crate::error::{AppError, Result};
+use crate::models::user::UserRole;
+use crate::utils::auth::{bearer_token, AuthUser};
+use crate::utils::config::env_or;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub id: String,
     pub email: String,
+    pub role: UserRole, // informational; permissions are checked against the current role
+    pub sid: Uuid, // session ID
+    pub jti: Uuid, // token ID
+    pub iat: usize, // issued at
     pub exp: usize, // expiration time
 }
 
-const DEFAULT_EXPIRY_HOURS: i64 = 24;
+/// How long an access token is valid; clients use their refresh token afterwards
+pub fn access_token_ttl_minutes() -> i64 {
+    env_or("ACCESS_TOKEN_TTL_MINUTES", 15)
+}
 
-/// Generate a JWT token for a user
-pub fn generate_token(user_id: &str, email: &str) -> Result<String> {
+/// Generate a JWT access token for a user's session
+pub fn generate_token(user_id: &str, email: &str, role: UserRole, session_id: Uuid) -> Result<String> {
     let secret = env::var("JWT_SECRET")
         .map_err(|_| AppError::Internal(anyhow::anyhow!("JWT_SECRET environment variable must be set")))?;
 
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(DEFAULT_EXPIRY_HOURS))
+    let now = Utc::now();
+    let expiration = now
+        .checked_add_signed(Duration::minutes(access_token_ttl_minutes()))
         .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Invalid expiration timestamp: date calculation overflow")))?
         .timestamp()
         .try_into()
@@ -32,6 +44,10 @@ pub fn generate_token(user_id: &str, email: &str) -> Result<String> {
     let claims = Claims {
         id: user_id.to_string(),
         email: email.to_string(),
+        role,
+        sid: session_id,
+        jti: Uuid::new_v4(),
+        iat: now.timestamp() as usize,
         exp: expiration,
     };
 
@@ -43,7 +59,7 @@ pub fn generate_token(user_id: &str, email: &str) -> Result<String> {
     .map_err(|e| AppError::Internal(anyhow::anyhow!("Token generation failed: {}", e)))
 }
 
-/// Validate a JWT token and return the claims
+/// Validate a JWT token and return the claims (invalid or expired tokens are `Unauthorized`)
 pub fn validate_token(token: &str) -> Result<Claims> {
     let secret = env::var("JWT_SECRET")
         .map_err(|_| AppError::Internal(anyhow::anyhow!("JWT_SECRET environment variable must be set")))?;
@@ -53,22 +69,43 @@ pub fn validate_token(token: &str) -> Result<Claims> {
         &DecodingKey::from_secret(secret.as_ref()),
         &Validation::default(),
     )
-    .map_err(|_| AppError::Internal(anyhow::anyhow!("Invalid token")))?;
+    .map_err(|_| AppError::Unauthorized)?;
 
     Ok(token_data.claims)
 }
 
-/// Extract and validate JWT token from Authorization header, returning the user ID
-pub fn extract_user_id(headers: &HeaderMap) -> Result<Uuid> {
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or(AppError::Unauthorized)?;
+/// Extract and validate JWT token from Authorization header, returning the
+/// session's user
+///
+/// Tokens whose session has been revoked (logout, password reset) or has expired
+/// are rejected. The user's role is read from the database, not the token, so
+/// role changes apply immediately.
+pub async fn extract_session(pool: &PgPool, headers: &HeaderMap) -> Result<AuthUser> {
+    let claims = validate_token(bearer_token(headers)?)?;
+    let user_id = Uuid::parse_str(&claims.id).map_err(|_| AppError::Unauthorized)?;
+
+    let user = sqlx::query_as::<_, (String, UserRole)>(
+        r#"
+        SELECT u.email, u.role
+        FROM sessions s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.id = $1
+          AND s.user_id = $2
+          AND s.revoked_at IS NULL
+          AND s.expires_at > NOW()
+        "#,
+    )
+    .bind(claims.sid)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
 
-    let claims = validate_token(auth_header)?;
-    let user_id = Uuid::parse_str(&claims.id)
-        .map_err(|_| AppError::Internal(anyhow::anyhow!("Invalid user ID in token")))?;
+    let (email, role) = user.ok_or(AppError::Unauthorized)?;
 
-    Ok(user_id)
+    Ok(AuthUser {
+        user_id,
+        session_id: claims.sid,
+        email,
+        role,
+    })
 }
-
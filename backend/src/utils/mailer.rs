@@ -0,0 +1,283 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use lettre::{
+    message::{header::ContentType, Mailbox, MultiPart, SinglePart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::{
+    env,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+
+/// Attempts made by `send_in_background` before giving up on a message
+const BACKGROUND_SEND_ATTEMPTS: u32 = 3;
+
+/// An outgoing email
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// Email transport
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<()>;
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Whether development mode is enabled
+///
+/// Development mode exposes secrets such as verification codes in API responses
+/// so flows can be exercised without a mailbox. Never enable it in production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DevMode(pub bool);
+
+impl DevMode {
+    /// Read `DEV_MODE` (enabled when `true`)
+    pub fn from_env() -> Self {
+        DevMode(env_var("DEV_MODE").is_some_and(|v| v.eq_ignore_ascii_case("true")))
+    }
+}
+
+/// Delivers mail through an SMTP relay
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Create an SMTP mailer from environment
+    ///
+    /// Environment variables:
+    /// - SMTP_HOST: Relay host (required)
+    /// - SMTP_PORT: Relay port (default: 587, or 465 with SMTP_TLS=tls)
+    /// - SMTP_USERNAME / SMTP_PASSWORD: Credentials (optional)
+    /// - SMTP_TLS: `starttls`, `tls` or `none` (default: starttls)
+    /// - MAIL_FROM: Sender address (required)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let host = env_var("SMTP_HOST").ok_or_else(|| anyhow::anyhow!("SMTP_HOST must be set"))?;
+        let from: Mailbox = env_var("MAIL_FROM")
+            .ok_or_else(|| anyhow::anyhow!("MAIL_FROM must be set"))?
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid MAIL_FROM: {}", e))?;
+        let tls = env_var("SMTP_TLS").unwrap_or_else(|| "starttls".to_string()).to_lowercase();
+
+        let mut builder = match tls.as_str() {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => anyhow::bail!("Invalid SMTP_TLS: {} (expected starttls, tls or none)", other),
+        };
+
+        if let Some(port) = env_var("SMTP_PORT") {
+            builder = builder.port(port.parse().map_err(|e| anyhow::anyhow!("Invalid SMTP_PORT: {}", e))?);
+        }
+
+        if let (Some(username), Some(password)) = (env_var("SMTP_USERNAME"), env_var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let to: Mailbox = message
+            .to
+            .parse()
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid recipient {}: {}", message.to, e)))?;
+
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&message.subject)
+            .multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_PLAIN)
+                            .body(message.text_body.clone()),
+                    )
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_HTML)
+                            .body(message.html_body.clone()),
+                    ),
+            )
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to build email: {}", e)))?;
+
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Writes each message to a file, for local development
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    /// Create a file mailer writing to `MAIL_FILE_DIR` (default: ./mail)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let dir = PathBuf::from(env_var("MAIL_FILE_DIR").unwrap_or_else(|| "./mail".to_string()));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create MAIL_FILE_DIR {}: {}", dir.display(), e))?;
+
+        Ok(Self { dir })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        let path = self.dir.join(format!(
+            "{}-{}.txt",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        ));
+        let contents = format!(
+            "To: {}\nSubject: {}\n\n{}",
+            message.to, message.subject, message.text_body
+        );
+
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to write email to {}: {}", path.display(), e)))?;
+
+        info!("Email to {} written to {}", message.to, path.display());
+        Ok(())
+    }
+}
+
+/// Keeps messages in memory, for tests
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<EmailMessage>>,
+}
+
+impl MemoryMailer {
+    /// Messages sent so far, oldest first
+    pub fn sent(&self) -> Vec<EmailMessage> {
+        self.sent.lock().expect("mailer lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        self.sent.lock().expect("mailer lock poisoned").push(message.clone());
+        Ok(())
+    }
+}
+
+/// Build the mailer from environment
+///
+/// `MAIL_TRANSPORT` selects the transport: `smtp` (default), `file` or `memory`.
+/// Called once at startup so a misconfigured transport fails fast.
+pub fn mailer_from_env() -> anyhow::Result<Arc<dyn Mailer>> {
+    let transport = env_var("MAIL_TRANSPORT").unwrap_or_else(|| "smtp".to_string()).to_lowercase();
+
+    let mailer: Arc<dyn Mailer> = match transport.as_str() {
+        "smtp" => Arc::new(SmtpMailer::from_env()?),
+        "file" => Arc::new(FileMailer::from_env()?),
+        "memory" => Arc::new(MemoryMailer::default()),
+        other => anyhow::bail!("Invalid MAIL_TRANSPORT: {} (expected smtp, file or memory)", other),
+    };
+
+    info!("Mail transport: {}", transport);
+    Ok(mailer)
+}
+
+/// Send an email without blocking the caller
+///
+/// Failed sends are retried a few times with a short backoff, then logged.
+pub fn send_in_background(mailer: Arc<dyn Mailer>, message: EmailMessage) {
+    tokio::spawn(async move {
+        for attempt in 1..=BACKGROUND_SEND_ATTEMPTS {
+            match mailer.send(&message).await {
+                Ok(()) => {
+                    info!("Sent \"{}\" email to {}", message.subject, message.to);
+                    return;
+                }
+                Err(e) if attempt < BACKGROUND_SEND_ATTEMPTS => {
+                    warn!(
+                        "Sending email to {} failed (attempt {}/{}), retrying: {}",
+                        message.to, attempt, BACKGROUND_SEND_ATTEMPTS, e
+                    );
+                    tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                }
+                Err(e) => {
+                    error!(
+                        "Sending email to {} failed after {} attempts: {}",
+                        message.to, BACKGROUND_SEND_ATTEMPTS, e
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Verification code email sent after registration
+pub fn verification_code_email(to: &str, code: &str) -> EmailMessage {
+    EmailMessage {
+        to: to.to_string(),
+        subject: "Your verification code".to_string(),
+        text_body: format!(
+            "Welcome!\n\n\
+             Your verification code is: {code}\n\n\
+             Enter this code to activate your account. If you didn't sign up, you can ignore this email.\n"
+        ),
+        html_body: format!(
+            "<p>Welcome!</p>\
+             <p>Your verification code is: <strong style=\"font-size: 1.5em; letter-spacing: 0.2em;\">{code}</strong></p>\
+             <p>Enter this code to activate your account. If you didn't sign up, you can ignore this email.</p>"
+        ),
+    }
+}
+
+/// Password reset email sent by forgot-password
+///
+/// The link points at `PASSWORD_RESET_URL` (default: http://localhost:3001/reset-password)
+/// with the token as a query parameter.
+pub fn password_reset_email(to: &str, token: &str) -> EmailMessage {
+    let base_url = env_var("PASSWORD_RESET_URL")
+        .unwrap_or_else(|| "http://localhost:3001/reset-password".to_string());
+    let link = format!("{}?token={}", base_url, token);
+
+    EmailMessage {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        text_body: format!(
+            "We received a request to reset your password.\n\n\
+             Open this link to choose a new one: {link}\n\n\
+             The link can be used once and expires soon. If you didn't ask for a reset, you can ignore this email.\n"
+        ),
+        html_body: format!(
+            "<p>We received a request to reset your password.</p>\
+             <p><a href=\"{link}\">Choose a new password</a></p>\
+             <p>The link can be used once and expires soon. If you didn't ask for a reset, you can ignore this email.</p>"
+        ),
+    }
+}
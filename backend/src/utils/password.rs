@@ -1,7 +1,13 @@
 use bcrypt::{hash, verify, DEFAULT_COST};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
 use crate::error::{AppError, Result};
+use crate::utils::config::env_or;
 
 const MIN_PASSWORD_LENGTH: usize = 8;
+const SECURE_TOKEN_LENGTH: usize = 48;
 
 /// Validates password meets minimum requirements
 pub fn validate_password(password: &str) -> Result<()> {
@@ -23,3 +29,34 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
         .map_err(|e| AppError::Internal(anyhow::anyhow!("Password verification failed: {}", e)))
 }
 
+/// Generates a random opaque token (password reset and refresh tokens)
+pub fn generate_secure_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECURE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Hashes an opaque token for storage and lookup
+///
+/// Tokens are long and random, so a fast unsalted hash is enough and lets the
+/// token be looked up by its hash.
+pub fn hash_secure_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Whether a token matches a stored `hash_secure_token` hash, in constant time
+pub fn secure_token_matches(token: &str, hash: &str) -> bool {
+    hash_secure_token(token).as_bytes().ct_eq(hash.as_bytes()).into()
+}
+
+/// How long a password reset token stays valid
+pub fn password_reset_token_ttl_minutes() -> i64 {
+    env_or("PASSWORD_RESET_TOKEN_TTL_MINUTES", 30)
+}
+
+/// Minimum time between password reset emails to the same account
+pub fn password_reset_cooldown_seconds() -> i64 {
+    env_or("PASSWORD_RESET_COOLDOWN_SECONDS", 60)
+}
@@ -0,0 +1,297 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::utils::stripe::StripeGateway;
+
+/// Payment intent details needed by the checkout flow
+pub struct CheckoutPaymentIntent {
+    pub id: String,
+    pub client_secret: String,
+    pub currency: String,
+}
+
+/// Payment intent as the payment provider currently holds it
+#[derive(Debug, Clone)]
+pub struct PaymentIntentSnapshot {
+    pub id: String,
+    pub amount: i64,
+    pub currency: String,
+    pub status: String,
+    pub client_secret: Option<String>,
+    pub ticket_id: Option<String>,
+    pub buyer_id: Option<String>,
+    pub created: i64,
+    /// The charge made by capturing the intent
+    pub latest_charge: Option<String>,
+}
+
+/// Payment provider operations
+///
+/// Capture, cancel and refund must be idempotent per payment intent, and
+/// `create_transfer` per idempotency key, so the outbox and payout workers
+/// can safely retry after a lost response.
+#[async_trait]
+pub trait PaymentGateway: Send + Sync {
+    /// Create a manual-capture payment intent for a reserved ticket
+    ///
+    /// Idempotent per reservation (`ticket_id` and `reserved_at`), so a retried
+    /// checkout gets the same intent back instead of a second one.
+    async fn create_payment_intent(
+        &self,
+        amount: i32,
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        reserved_at: DateTime<Utc>,
+    ) -> Result<CheckoutPaymentIntent>;
+
+    /// Look up a payment intent, e.g. to hand its client secret out again
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> Result<PaymentIntentSnapshot>;
+
+    /// Capture a payment intent (charge the buyer)
+    async fn capture_payment_intent(&self, payment_intent_id: &str) -> Result<()>;
+
+    /// Cancel a payment intent (release the authorization hold)
+    async fn cancel_payment_intent(&self, payment_intent_id: &str) -> Result<()>;
+
+    /// Refund a captured payment intent in full
+    async fn refund_payment_intent(&self, payment_intent_id: &str) -> Result<()>;
+
+    /// Every payment intent created since the given time, for reconciliation
+    async fn list_payment_intents_since(&self, since: DateTime<Utc>) -> Result<Vec<PaymentIntentSnapshot>>;
+
+    /// Create a connected account for a seller, returning its ID
+    async fn create_connected_account(&self, email: &str) -> Result<String>;
+
+    /// Create an onboarding link for a seller's connected account
+    async fn create_account_onboarding_link(&self, account_id: &str) -> Result<String>;
+
+    /// Transfer funds from the platform account to a seller's connected account
+    ///
+    /// The transfer is funded by `source_transaction` (the buyer's charge), so it can
+    /// be made before that charge's funds have settled into the platform balance.
+    async fn create_transfer(
+        &self,
+        idempotency_key: &str,
+        destination: &str,
+        amount: i32,
+        currency: &str,
+        transfer_group: &str,
+        source_transaction: &str,
+    ) -> Result<String>;
+}
+
+#[derive(Default)]
+struct MemoryState {
+    payment_intents: HashMap<String, PaymentIntentSnapshot>,
+    checkouts: HashMap<(Uuid, i64), String>,
+    refunded: Vec<String>,
+    transfers: HashMap<String, String>,
+}
+
+/// Keeps payments in memory, for tests and local development
+///
+/// Intents start out authorized ('requires_capture') so checkout, capture and
+/// cancel can be exercised without a card. Nothing leaves the process.
+#[derive(Default)]
+pub struct MemoryPaymentGateway {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryPaymentGateway {
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().expect("payment gateway lock poisoned")
+    }
+
+    /// Move a payment intent to `to` if its current status allows it
+    fn transition(&self, payment_intent_id: &str, allowed_from: &[&str], to: &str) -> Result<()> {
+        let mut state = self.state();
+        let payment_intent = state
+            .payment_intents
+            .get_mut(payment_intent_id)
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("No such payment intent: {}", payment_intent_id)))?;
+
+        // Repeating a call that already succeeded is a no-op, as with an idempotency key
+        if payment_intent.status != to && !allowed_from.contains(&payment_intent.status.as_str()) {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Payment intent {} is {}, cannot move to {}",
+                payment_intent_id,
+                payment_intent.status,
+                to
+            )));
+        }
+
+        payment_intent.status = to.to_string();
+        Ok(())
+    }
+
+    /// Payment intent IDs refunded so far, oldest first
+    pub fn refunded(&self) -> Vec<String> {
+        self.state().refunded.clone()
+    }
+}
+
+#[async_trait]
+impl PaymentGateway for MemoryPaymentGateway {
+    async fn create_payment_intent(
+        &self,
+        amount: i32,
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        reserved_at: DateTime<Utc>,
+    ) -> Result<CheckoutPaymentIntent> {
+        let mut state = self.state();
+
+        // One intent per reservation, as the Stripe gateway's idempotency key gives
+        let reservation = (ticket_id, reserved_at.timestamp_micros());
+        if let Some(existing) = state
+            .checkouts
+            .get(&reservation)
+            .and_then(|id| state.payment_intents.get(id))
+        {
+            return Ok(CheckoutPaymentIntent {
+                id: existing.id.clone(),
+                client_secret: existing.client_secret.clone().unwrap_or_default(),
+                currency: existing.currency.clone(),
+            });
+        }
+
+        let id = format!("pi_mem_{}", Uuid::new_v4().simple());
+        let client_secret = format!("{}_secret_{}", id, Uuid::new_v4().simple());
+
+        state.checkouts.insert(reservation, id.clone());
+        state.payment_intents.insert(
+            id.clone(),
+            PaymentIntentSnapshot {
+                id: id.clone(),
+                amount: amount.into(),
+                currency: "usd".to_string(),
+                status: "requires_capture".to_string(),
+                client_secret: Some(client_secret.clone()),
+                ticket_id: Some(ticket_id.to_string()),
+                buyer_id: Some(buyer_id.to_string()),
+                created: Utc::now().timestamp(),
+                latest_charge: None,
+            },
+        );
+
+        info!("Created in-memory payment intent {} for ticket {}", id, ticket_id);
+        Ok(CheckoutPaymentIntent {
+            id,
+            client_secret,
+            currency: "usd".to_string(),
+        })
+    }
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> Result<PaymentIntentSnapshot> {
+        self.state()
+            .payment_intents
+            .get(payment_intent_id)
+            .cloned()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("No such payment intent: {}", payment_intent_id)))
+    }
+
+    async fn capture_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        self.transition(payment_intent_id, &["requires_capture"], "succeeded")?;
+
+        if let Some(payment_intent) = self.state().payment_intents.get_mut(payment_intent_id) {
+            payment_intent
+                .latest_charge
+                .get_or_insert_with(|| format!("ch_mem_{}", Uuid::new_v4().simple()));
+        }
+        Ok(())
+    }
+
+    async fn cancel_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        self.transition(
+            payment_intent_id,
+            &["requires_payment_method", "requires_confirmation", "requires_action", "requires_capture"],
+            "canceled",
+        )
+    }
+
+    async fn refund_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        let mut state = self.state();
+        let captured = state
+            .payment_intents
+            .get(payment_intent_id)
+            .is_some_and(|payment_intent| payment_intent.status == "succeeded");
+        if !captured {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Payment intent {} has not been captured",
+                payment_intent_id
+            )));
+        }
+
+        if !state.refunded.iter().any(|id| id == payment_intent_id) {
+            state.refunded.push(payment_intent_id.to_string());
+        }
+        Ok(())
+    }
+
+    async fn list_payment_intents_since(&self, since: DateTime<Utc>) -> Result<Vec<PaymentIntentSnapshot>> {
+        Ok(self
+            .state()
+            .payment_intents
+            .values()
+            .filter(|payment_intent| payment_intent.created >= since.timestamp())
+            .cloned()
+            .collect())
+    }
+
+    async fn create_connected_account(&self, _email: &str) -> Result<String> {
+        Ok(format!("acct_mem_{}", Uuid::new_v4().simple()))
+    }
+
+    async fn create_account_onboarding_link(&self, account_id: &str) -> Result<String> {
+        Ok(format!("http://localhost/connect/onboarding/{}", account_id))
+    }
+
+    async fn create_transfer(
+        &self,
+        idempotency_key: &str,
+        destination: &str,
+        amount: i32,
+        currency: &str,
+        _transfer_group: &str,
+        _source_transaction: &str,
+    ) -> Result<String> {
+        let transfer_id = self
+            .state()
+            .transfers
+            .entry(idempotency_key.to_string())
+            .or_insert_with(|| format!("tr_mem_{}", Uuid::new_v4().simple()))
+            .clone();
+
+        info!("In-memory transfer {} of {} {} to {}", transfer_id, amount, currency, destination);
+        Ok(transfer_id)
+    }
+}
+
+/// Build the payment gateway from environment
+///
+/// `PAYMENT_GATEWAY` selects the implementation: `stripe` (default) or `memory`.
+/// Called once at startup so a misconfigured gateway fails fast.
+pub fn payment_gateway_from_env() -> anyhow::Result<Arc<dyn PaymentGateway>> {
+    let gateway = env::var("PAYMENT_GATEWAY")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "stripe".to_string())
+        .to_lowercase();
+
+    let payments: Arc<dyn PaymentGateway> = match gateway.as_str() {
+        "stripe" => Arc::new(StripeGateway::from_env()?),
+        "memory" => Arc::new(MemoryPaymentGateway::default()),
+        other => anyhow::bail!("Invalid PAYMENT_GATEWAY: {} (expected stripe or memory)", other),
+    };
+
+    info!("Payment gateway: {}", gateway);
+    Ok(payments)
+}
@@ -0,0 +1,227 @@
+use sqlx::PgPool;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::payout::PayoutStatus;
+use crate::utils::config::{env_in_or, env_or};
+use crate::utils::payment_gateway::PaymentGateway;
+
+/// Platform fee taken from each sale, as a percentage of the captured amount
+fn platform_fee_percent() -> i64 {
+    env_in_or("PLATFORM_FEE_PERCENT", 0..=100, 0)
+}
+
+fn payout_max_attempts() -> i32 {
+    env_or("PAYOUT_MAX_ATTEMPTS", 5)
+}
+
+fn payout_retry_base_seconds() -> i64 {
+    env_or("PAYOUT_RETRY_BASE_SECONDS", 60)
+}
+
+/// Create pending payouts for sold tickets that don't have one yet
+///
+/// Pass a ticket ID to queue a single ticket (e.g. right after it is marked sold),
+/// or `None` to sweep every sold ticket. Returns the IDs of the payouts created.
+pub async fn queue_payouts(pool: &PgPool, ticket_id: Option<Uuid>) -> Result<Vec<Uuid>> {
+    let payout_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO payouts (ticket_id, seller_id, payment_intent_id, amount, platform_fee, currency)
+        SELECT t.id, t.seller_id, pi.id, pi.amount, (pi.amount * $2) / 100, pi.currency
+        FROM tickets t
+        JOIN payment_intents pi ON pi.ticket_id = t.id AND pi.status = 'captured'
+        WHERE t.status = 'sold'
+          AND ($1::uuid IS NULL OR t.id = $1)
+        ON CONFLICT (ticket_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(platform_fee_percent())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(payout_ids)
+}
+
+/// The charge that paid for a payout's sale, which funds its transfer
+async fn sale_charge(payments: &dyn PaymentGateway, payment_intent_id: &str) -> Result<String> {
+    payments
+        .retrieve_payment_intent(payment_intent_id)
+        .await?
+        .latest_charge
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Payment intent {} has no charge", payment_intent_id)))
+}
+
+/// Attempt the Stripe transfer for a single pending payout
+///
+/// The transfer draws on the buyer's charge (`source_transaction`) rather than the
+/// platform's available balance, so it doesn't have to wait for the charge to settle.
+/// The payout is leased by bumping `attempts` and pushing `next_attempt_at` out with
+/// exponential backoff, so a concurrent worker skips it. The payout ID is used as the
+/// Stripe idempotency key, which makes retries after a lost response safe.
+/// Payouts for sellers who haven't onboarded yet are left pending untouched.
+pub async fn process_payout(pool: &PgPool, payments: &dyn PaymentGateway, payout_id: Uuid) -> Result<()> {
+    let leased = sqlx::query_as::<_, (Uuid, Uuid, String, i32, i32, String, i32, String)>(
+        r#"
+        UPDATE payouts p
+        SET attempts = p.attempts + 1,
+            next_attempt_at = NOW() + INTERVAL '1 second' * $2 * POWER(2, LEAST(p.attempts, 10)),
+            updated_at = NOW()
+        FROM users u
+        WHERE p.id = $1
+          AND p.seller_id = u.id
+          AND p.status = 'pending'
+          AND p.next_attempt_at <= NOW()
+          AND u.stripe_account_id IS NOT NULL
+        RETURNING p.id, p.ticket_id, p.payment_intent_id, p.amount, p.platform_fee, p.currency, p.attempts, u.stripe_account_id
+        "#,
+    )
+    .bind(payout_id)
+    .bind(payout_retry_base_seconds())
+    .fetch_optional(pool)
+    .await?;
+
+    let (payout_id, ticket_id, payment_intent_id, amount, platform_fee, currency, attempts, destination) = match leased {
+        Some(leased) => leased,
+        None => {
+            info!("Payout {} not due or seller not onboarded, skipping", payout_id);
+            return Ok(());
+        }
+    };
+
+    let transfer_result = async {
+        let charge_id = sale_charge(payments, &payment_intent_id).await?;
+
+        payments
+            .create_transfer(
+                &format!("payout-{}", payout_id),
+                &destination,
+                amount - platform_fee,
+                &currency,
+                &ticket_id.to_string(),
+                &charge_id,
+            )
+            .await
+    }
+    .await;
+
+    match transfer_result {
+        Ok(transfer_id) => {
+            let recorded = sqlx::query(
+                r#"
+                UPDATE payouts
+                SET status = $2,
+                    stripe_transfer_id = $3,
+                    last_error = NULL,
+                    updated_at = NOW()
+                WHERE id = $1
+                  AND status = 'pending'
+                "#,
+            )
+            .bind(payout_id)
+            .bind(PayoutStatus::Transferred)
+            .bind(&transfer_id)
+            .execute(pool)
+            .await?;
+
+            if recorded.rows_affected() == 0 {
+                // Held by a dispute or cancelled by a refund while the transfer was in
+                // flight: keep that status, but record the transfer so it can be reversed
+                sqlx::query("UPDATE payouts SET stripe_transfer_id = $2, updated_at = NOW() WHERE id = $1")
+                    .bind(payout_id)
+                    .bind(&transfer_id)
+                    .execute(pool)
+                    .await?;
+
+                error!(
+                    "Payout {} for ticket {} stopped being pending during its transfer {}, needs manual intervention",
+                    payout_id, ticket_id, transfer_id
+                );
+                return Ok(());
+            }
+
+            info!(
+                "Payout {} for ticket {} transferred ({} to seller, {} platform fee, transfer {})",
+                payout_id, ticket_id, amount - platform_fee, platform_fee, transfer_id
+            );
+        }
+        Err(e) => {
+            let last_error = match &e {
+                AppError::Internal(inner) => inner.to_string(),
+                other => other.to_string(),
+            };
+            let max_attempts = payout_max_attempts();
+            let status = if attempts >= max_attempts {
+                PayoutStatus::Failed
+            } else {
+                PayoutStatus::Pending
+            };
+
+            let recorded = sqlx::query(
+                r#"
+                UPDATE payouts
+                SET status = $2,
+                    last_error = $3,
+                    updated_at = NOW()
+                WHERE id = $1
+                  AND status = 'pending'
+                "#,
+            )
+            .bind(payout_id)
+            .bind(status)
+            .bind(&last_error)
+            .execute(pool)
+            .await?;
+
+            if recorded.rows_affected() == 0 {
+                info!(
+                    "Payout {} for ticket {} stopped being pending during a failed transfer: {}",
+                    payout_id, ticket_id, e
+                );
+                return Ok(());
+            }
+
+            if attempts >= max_attempts {
+                error!(
+                    "Payout {} for ticket {} failed after {} attempts, needs manual intervention: {}",
+                    payout_id, ticket_id, attempts, e
+                );
+            } else {
+                warn!(
+                    "Payout {} for ticket {} failed (attempt {}/{}), will retry: {}",
+                    payout_id, ticket_id, attempts, max_attempts, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Attempt every pending payout whose retry time has come
+pub async fn process_due_payouts(pool: &PgPool, payments: &dyn PaymentGateway) -> Result<usize> {
+    let payout_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT p.id
+        FROM payouts p
+        JOIN users u ON u.id = p.seller_id
+        WHERE p.status = 'pending'
+          AND p.next_attempt_at <= NOW()
+          AND u.stripe_account_id IS NOT NULL
+        ORDER BY p.next_attempt_at ASC
+        LIMIT 50
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for payout_id in &payout_ids {
+        if let Err(e) = process_payout(pool, payments, *payout_id).await {
+            error!("Processing payout {} failed: {}", payout_id, e);
+        }
+    }
+
+    Ok(payout_ids.len())
+}
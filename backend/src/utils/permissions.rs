@@ -0,0 +1,125 @@
+use crate::error::{AppError, Result};
+use crate::models::user::UserRole;
+
+/// Something an admin or bot caller can be allowed to do
+///
+/// Roles grant a fixed set of permissions; admin API keys and bot credentials
+/// carry an explicit subset (their scopes), stored as the `as_str` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ManageGames,
+    RefundTickets,
+    ManagePayments,
+    ManageUsers,
+    ManageApiKeys,
+    ViewAuditLog,
+    ManageBots,
+    VerifyTickets,
+    MarkTicketsSold,
+    ViewTicketHistory,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 10] = [
+        Permission::ManageGames,
+        Permission::RefundTickets,
+        Permission::ManagePayments,
+        Permission::ManageUsers,
+        Permission::ManageApiKeys,
+        Permission::ViewAuditLog,
+        Permission::ManageBots,
+        Permission::VerifyTickets,
+        Permission::MarkTicketsSold,
+        Permission::ViewTicketHistory,
+    ];
+
+    /// Scopes a bot credential can be given
+    pub const BOT_SCOPES: [Permission; 3] = [
+        Permission::VerifyTickets,
+        Permission::MarkTicketsSold,
+        Permission::RefundTickets,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::ManageGames => "games:manage",
+            Permission::RefundTickets => "tickets:refund",
+            Permission::ManagePayments => "payments:manage",
+            Permission::ManageUsers => "users:manage",
+            Permission::ManageApiKeys => "api_keys:manage",
+            Permission::ViewAuditLog => "audit_log:read",
+            Permission::ManageBots => "bots:manage",
+            Permission::VerifyTickets => "tickets:verify",
+            Permission::MarkTicketsSold => "tickets:mark_sold",
+            Permission::ViewTicketHistory => "tickets:history",
+        }
+    }
+
+    /// Parse an API key scope
+    pub fn parse(s: &str) -> Result<Permission> {
+        Self::parse_one_of(s, &Permission::ALL)
+    }
+
+    /// Parse a bot credential scope
+    pub fn parse_bot_scope(s: &str) -> Result<Permission> {
+        Self::parse_one_of(s, &Permission::BOT_SCOPES)
+    }
+
+    fn parse_one_of(s: &str, allowed: &[Permission]) -> Result<Permission> {
+        allowed.iter().copied().find(|p| p.as_str() == s).ok_or_else(|| {
+            let valid: Vec<&str> = allowed.iter().map(|p| p.as_str()).collect();
+            AppError::BadRequest(format!("Invalid scope '{}'. Must be one of: {}", s, valid.join(", ")))
+        })
+    }
+}
+
+/// Whether a role grants a permission
+pub fn role_has_permission(role: UserRole, permission: Permission) -> bool {
+    match role {
+        UserRole::Admin => true,
+        UserRole::Moderator => matches!(
+            permission,
+            Permission::ManageGames | Permission::RefundTickets | Permission::ViewTicketHistory
+        ),
+        UserRole::User => false,
+    }
+}
+
+/// Compile-time permission required by an `AdminCaller<P>` or `BotCaller<P>` extractor
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! permission_markers {
+    ($($(#[$doc:meta])* $marker:ident => $permission:ident,)*) => {
+        $(
+            $(#[$doc])*
+            pub struct $marker;
+
+            impl RequiredPermission for $marker {
+                const PERMISSION: Permission = Permission::$permission;
+            }
+        )*
+    };
+}
+
+permission_markers! {
+    /// Create and delete games
+    CanManageGames => ManageGames,
+    /// Refund paid tickets
+    CanRefundTickets => RefundTickets,
+    /// Inspect and repair Stripe state (outbox, reconciliation)
+    CanManagePayments => ManagePayments,
+    /// Change user roles
+    CanManageUsers => ManageUsers,
+    /// Create, list and revoke admin API keys
+    CanManageApiKeys => ManageApiKeys,
+    /// Read the admin audit log
+    CanViewAuditLog => ViewAuditLog,
+    /// Create, rotate and revoke bot credentials
+    CanManageBots => ManageBots,
+    /// Claim, verify and unclaim tickets (bots)
+    CanVerifyTickets => VerifyTickets,
+    /// Mark paid tickets as sold once transferred (bots)
+    CanMarkTicketsSold => MarkTicketsSold,
+}
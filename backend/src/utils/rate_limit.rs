@@ -1,64 +1,139 @@
 use axum::{
     body::Body,
-    http::{Request, Response, StatusCode},
+    extract::ConnectInfo,
+    http::{HeaderMap, HeaderValue, Request, Response},
     response::IntoResponse,
 };
 use governor::{
-    clock::DefaultClock,
-    state::{InMemoryState, NotKeyed},
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
     Quota, RateLimiter,
 };
 use std::{
     env,
+    net::SocketAddr,
     num::NonZeroU32,
     sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
 use tower::{Layer, Service};
-use tracing::warn;
+use tracing::{debug, warn};
 
+use crate::error::AppError;
+use crate::utils::auth::bearer_token;
+use crate::utils::config::{env_or, env_positive};
 use crate::utils::jwt::validate_token;
 
-/// Rate limiter state shared across requests
-type SharedRateLimiter = Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>;
+/// Keyed rate limiter state shared across requests (key: "user:<id>" or "ip:<addr>")
+type SharedRateLimiter = Arc<
+    RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock, StateInformationMiddleware>,
+>;
 
-/// Layer that applies rate limiting to requests
+/// Read a rate limit setting for a route group, falling back to the global setting
+fn group_setting<T: std::str::FromStr + PartialOrd + Default>(group: &str, name: &str, default: T) -> T {
+    env_positive(&format!("RATE_LIMIT_{}_{}", group.to_uppercase(), name))
+        .unwrap_or_else(|| env_or(&format!("RATE_LIMIT_{}", name), default))
+}
+
+/// Layer that applies per-user (or per-IP) rate limiting to requests
 #[derive(Clone)]
 pub struct RateLimitLayer {
     limiter: SharedRateLimiter,
+    clock: DefaultClock,
+    group: &'static str,
+    burst: u32,
+    replenish_interval: Duration,
+    trust_proxy: bool,
 }
 
 impl RateLimitLayer {
-    /// Create a new rate limit layer with configurable limits from environment
-    /// 
-    /// Environment variables:
-    /// - RATE_LIMIT_REQUESTS: Max requests per window (default: 10)
-    /// - RATE_LIMIT_WINDOW_SECONDS: Window duration in seconds (default: 60)
-    pub fn new() -> Self {
-        let requests_per_window: u32 = env::var("RATE_LIMIT_REQUESTS")
-            .unwrap_or_else(|_| "10".to_string())
-            .parse()
-            .unwrap_or(10);
-
-        let window_seconds: u64 = env::var("RATE_LIMIT_WINDOW_SECONDS")
-            .unwrap_or_else(|_| "60".to_string())
-            .parse()
-            .unwrap_or(60);
-
-        let quota = Quota::with_period(Duration::from_secs(window_seconds))
+    /// Create a new rate limit layer for a route group with limits from environment
+    ///
+    /// Environment variables (the group-specific one wins, e.g. RATE_LIMIT_RESERVATION_REQUESTS):
+    /// - RATE_LIMIT_<GROUP>_REQUESTS / RATE_LIMIT_REQUESTS: Max requests per window per key (default: 10)
+    /// - RATE_LIMIT_<GROUP>_WINDOW_SECONDS / RATE_LIMIT_WINDOW_SECONDS: Window duration in seconds (default: 60)
+    /// - RATE_LIMIT_EVICTION_INTERVAL_SECONDS: How often idle keys are dropped (default: 60)
+    /// - RATE_LIMIT_TRUST_PROXY: Key anonymous requests by the last X-Forwarded-For
+    ///   entry instead of the peer address (default: false)
+    ///
+    /// Must be created inside the Tokio runtime (it spawns the eviction task).
+    pub fn new(group: &'static str) -> Self {
+        let requests_per_window: u32 = group_setting(group, "REQUESTS", 10);
+        let window_seconds: u64 = group_setting(group, "WINDOW_SECONDS", 60);
+
+        let eviction_interval_seconds: u64 = env_or("RATE_LIMIT_EVICTION_INTERVAL_SECONDS", 60);
+
+        let trust_proxy = env::var("RATE_LIMIT_TRUST_PROXY")
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
+        let burst = NonZeroU32::new(requests_per_window).expect("Rate limit must be > 0");
+        let quota = Quota::with_period(Duration::from_secs(window_seconds) / requests_per_window)
             .expect("Invalid rate limit period")
-            .allow_burst(NonZeroU32::new(requests_per_window).expect("Rate limit must be > 0"));
+            .allow_burst(burst);
+
+        let clock = DefaultClock::default();
+        let limiter = Arc::new(
+            RateLimiter::dashmap_with_clock(quota, &clock).with_middleware::<StateInformationMiddleware>(),
+        );
+
+        // Drop keys whose state has fully replenished so memory stays bounded;
+        // the task ends once the router (and with it the limiter) is dropped
+        let weak_limiter = Arc::downgrade(&limiter);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(eviction_interval_seconds));
+            loop {
+                ticker.tick().await;
+                let Some(limiter) = weak_limiter.upgrade() else {
+                    break;
+                };
+                limiter.retain_recent();
+                limiter.shrink_to_fit();
+                debug!("Rate limiter '{}' tracking {} keys", group, limiter.len());
+            }
+        });
+
+        Self {
+            limiter,
+            clock,
+            group,
+            burst: burst.get(),
+            replenish_interval: quota.replenish_interval(),
+            trust_proxy,
+        }
+    }
 
-        let limiter = Arc::new(RateLimiter::direct(quota));
+    /// Rate limit key: the JWT user id, or the client IP for anonymous requests
+    fn key_for(&self, req: &Request<Body>) -> String {
+        let user_id = bearer_token(req.headers())
+            .ok()
+            .and_then(|token| validate_token(token).ok())
+            .map(|claims| claims.id);
 
-        Self { limiter }
-    }
-}
+        if let Some(user_id) = user_id {
+            return format!("user:{}", user_id);
+        }
 
-impl Default for RateLimitLayer {
-    fn default() -> Self {
-        Self::new()
+        let forwarded_ip = if self.trust_proxy {
+            req.headers()
+                .get("x-forwarded-for")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .map(|ip| ip.trim().to_string())
+                .filter(|ip| !ip.is_empty())
+        } else {
+            None
+        };
+
+        let ip = forwarded_ip.or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+
+        format!("ip:{}", ip.unwrap_or_else(|| "unknown".to_string()))
     }
 }
 
@@ -68,7 +143,7 @@ impl<S> Layer<S> for RateLimitLayer {
     fn layer(&self, inner: S) -> Self::Service {
         RateLimitService {
             inner,
-            limiter: self.limiter.clone(),
+            config: self.clone(),
         }
     }
 }
@@ -77,7 +152,16 @@ impl<S> Layer<S> for RateLimitLayer {
 #[derive(Clone)]
 pub struct RateLimitService<S> {
     inner: S,
-    limiter: SharedRateLimiter,
+    config: RateLimitLayer,
+}
+
+fn set_header(headers: &mut HeaderMap, name: &'static str, value: u64) {
+    headers.insert(name, HeaderValue::from(value));
+}
+
+/// Whole seconds, rounded up so clients never retry too early
+fn ceil_seconds(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
 }
 
 impl<S> Service<Request<Body>> for RateLimitService<S>
@@ -96,37 +180,43 @@ where
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let limiter = self.limiter.clone();
+        let config = self.config.clone();
         let mut inner = self.inner.clone();
 
         Box::pin(async move {
-            // Extract user identifier from JWT for logging (optional)
-            let user_key = req
-                .headers()
-                .get("authorization")
-                .and_then(|h| h.to_str().ok())
-                .and_then(|token| validate_token(token).ok())
-                .map(|claims| claims.id)
-                .unwrap_or_else(|| "anonymous".to_string());
+            let key = config.key_for(&req);
 
-            // Check rate limit
-            match limiter.check() {
-                Ok(_) => {
+            // Check rate limit; X-RateLimit-Reset is when the full limit is available again
+            match config.limiter.check_key(&key) {
+                Ok(snapshot) => {
                     // Request allowed, proceed
-                    inner.call(req).await
+                    let remaining = snapshot.remaining_burst_capacity();
+                    let reset = config.replenish_interval * (config.burst - remaining);
+
+                    let mut response = inner.call(req).await?;
+                    let headers = response.headers_mut();
+                    set_header(headers, "x-ratelimit-limit", config.burst.into());
+                    set_header(headers, "x-ratelimit-remaining", remaining.into());
+                    set_header(headers, "x-ratelimit-reset", ceil_seconds(reset));
+                    Ok(response)
                 }
-                Err(_) => {
+                Err(not_until) => {
                     // Rate limit exceeded
-                    warn!("Rate limit exceeded for user: {}", user_key);
-                    
-                    let response = (
-                        StatusCode::TOO_MANY_REQUESTS,
-                        serde_json::json!({
-                            "error": "Too many requests, please slow down"
-                        })
-                        .to_string(),
-                    )
-                        .into_response();
+                    let wait = not_until.wait_time_from(config.clock.now());
+                    let retry_after = ceil_seconds(wait);
+                    let reset = wait + config.replenish_interval * (config.burst - 1);
+
+                    warn!(
+                        "Rate limit '{}' exceeded for {}, retry after {}s",
+                        config.group, key, retry_after
+                    );
+
+                    let mut response = AppError::TooManyRequests.into_response();
+                    let headers = response.headers_mut();
+                    set_header(headers, "retry-after", retry_after);
+                    set_header(headers, "x-ratelimit-limit", config.burst.into());
+                    set_header(headers, "x-ratelimit-remaining", 0);
+                    set_header(headers, "x-ratelimit-reset", ceil_seconds(reset));
 
                     Ok(response)
                 }
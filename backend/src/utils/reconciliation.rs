@@ -0,0 +1,283 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::models::payment_intent::PaymentIntentStatus;
+use crate::models::stripe_discrepancy::{DiscrepancyKind, ReconciliationSummary};
+use crate::models::ticket::TicketStatus;
+use crate::utils::config::env_or;
+use crate::utils::payment_gateway::{PaymentGateway, PaymentIntentSnapshot};
+
+/// Local rows are inserted after Stripe creates the intent, so only rows older than
+/// this past the lookback start are expected to show up in Stripe's list
+const MISSING_AT_STRIPE_MARGIN_MINUTES: i64 = 5;
+
+/// How far back reconciliation looks (Stripe authorizations expire after 7 days)
+pub fn reconciliation_lookback_hours() -> i64 {
+    env_or("RECONCILIATION_LOOKBACK_HOURS", 48)
+}
+
+#[derive(FromRow)]
+struct LocalPaymentIntent {
+    id: String,
+    ticket_id: Uuid,
+    buyer_id: Uuid,
+    amount: i32,
+    currency: String,
+    status: PaymentIntentStatus,
+    ticket_status: TicketStatus,
+    other_intent_captured: bool,
+}
+
+struct Discrepancy {
+    payment_intent_id: String,
+    ticket_id: Option<Uuid>,
+    kind: DiscrepancyKind,
+    local_value: Option<String>,
+    stripe_value: Option<String>,
+}
+
+/// Whether a local payment intent status agrees with Stripe's
+fn status_matches(stripe_status: &str, local: PaymentIntentStatus) -> bool {
+    match stripe_status {
+        "requires_payment_method" | "requires_confirmation" | "requires_action" | "processing" => {
+            matches!(local, PaymentIntentStatus::Created | PaymentIntentStatus::Failed)
+        }
+        "requires_capture" => matches!(local, PaymentIntentStatus::Capturable),
+        "succeeded" => matches!(
+            local,
+            PaymentIntentStatus::Captured | PaymentIntentStatus::Refunded | PaymentIntentStatus::Disputed
+        ),
+        "canceled" => matches!(local, PaymentIntentStatus::Cancelled),
+        _ => false,
+    }
+}
+
+/// Compare one Stripe payment intent with our row and its ticket
+fn compare(stripe: &PaymentIntentSnapshot, local: &LocalPaymentIntent) -> Vec<Discrepancy> {
+    let mut found = Vec::new();
+    let mut push = |kind, local_value: String, stripe_value: String| {
+        found.push(Discrepancy {
+            payment_intent_id: stripe.id.clone(),
+            ticket_id: Some(local.ticket_id),
+            kind,
+            local_value: Some(local_value),
+            stripe_value: Some(stripe_value),
+        });
+    };
+
+    if i64::from(local.amount) != stripe.amount {
+        push(DiscrepancyKind::AmountMismatch, local.amount.to_string(), stripe.amount.to_string());
+    }
+
+    if !local.currency.eq_ignore_ascii_case(&stripe.currency) {
+        push(DiscrepancyKind::CurrencyMismatch, local.currency.clone(), stripe.currency.clone());
+    }
+
+    if !status_matches(&stripe.status, local.status) {
+        push(DiscrepancyKind::StatusMismatch, format!("{:?}", local.status), stripe.status.clone());
+    }
+
+    let ticket_id = local.ticket_id.to_string();
+    let buyer_id = local.buyer_id.to_string();
+    if stripe.ticket_id.as_deref().is_some_and(|id| id != ticket_id)
+        || stripe.buyer_id.as_deref().is_some_and(|id| id != buyer_id)
+    {
+        push(
+            DiscrepancyKind::MetadataMismatch,
+            format!("ticket_id={}, buyer_id={}", ticket_id, buyer_id),
+            format!(
+                "ticket_id={}, buyer_id={}",
+                stripe.ticket_id.as_deref().unwrap_or("-"),
+                stripe.buyer_id.as_deref().unwrap_or("-")
+            ),
+        );
+    }
+
+    // Money taken without a sale, or a sale without the money
+    let ticket_mismatch = match stripe.status.as_str() {
+        "succeeded" => !matches!(
+            local.ticket_status,
+            TicketStatus::Paid | TicketStatus::Sold | TicketStatus::Refunding | TicketStatus::Refunded
+        ),
+        "canceled" => {
+            matches!(local.ticket_status, TicketStatus::Paid | TicketStatus::Sold) && !local.other_intent_captured
+        }
+        _ => false,
+    };
+    if ticket_mismatch {
+        push(
+            DiscrepancyKind::TicketStatusMismatch,
+            format!("{:?}", local.ticket_status),
+            stripe.status.clone(),
+        );
+    }
+
+    found
+}
+
+/// Compare payment intents created at Stripe in the last `lookback_hours` with our
+/// `payment_intents` rows and their tickets
+///
+/// Every mismatch is recorded in `stripe_discrepancies` (one open row per intent and
+/// kind, refreshed on each run). Open discrepancies that a run no longer finds are
+/// marked resolved, and ones first seen before the lookback window (which no run
+/// checks any more) are marked resolved as expired. Nothing is changed in
+/// `payment_intents` or `tickets`.
+pub async fn reconcile_payment_intents(
+    pool: &PgPool,
+    payments: &dyn PaymentGateway,
+    lookback_hours: i64,
+) -> Result<ReconciliationSummary> {
+    let since = Utc::now() - Duration::hours(lookback_hours);
+    let run_started = sqlx::query_scalar::<_, DateTime<Utc>>("SELECT NOW()")
+        .fetch_one(pool)
+        .await?;
+
+    let stripe_intents = payments.list_payment_intents_since(since).await?;
+    let stripe_ids: Vec<String> = stripe_intents.iter().map(|pi| pi.id.clone()).collect();
+
+    let local_intents = sqlx::query_as::<_, LocalPaymentIntent>(
+        r#"
+        SELECT pi.id, pi.ticket_id, pi.buyer_id, pi.amount, pi.currency, pi.status,
+               t.status AS ticket_status,
+               EXISTS (
+                   SELECT 1
+                   FROM payment_intents other
+                   WHERE other.ticket_id = pi.ticket_id
+                     AND other.id <> pi.id
+                     AND other.status IN ('captured', 'refunded', 'disputed')
+               ) AS other_intent_captured
+        FROM payment_intents pi
+        JOIN tickets t ON t.id = pi.ticket_id
+        WHERE pi.id = ANY($1)
+        "#,
+    )
+    .bind(&stripe_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let local_by_id: HashMap<&str, &LocalPaymentIntent> =
+        local_intents.iter().map(|pi| (pi.id.as_str(), pi)).collect();
+
+    let mut discrepancies = Vec::new();
+
+    for stripe in &stripe_intents {
+        match local_by_id.get(stripe.id.as_str()) {
+            Some(local) => discrepancies.extend(compare(stripe, local)),
+            // Intents without our metadata weren't created for a ticket. The metadata
+            // ticket may not exist locally either, so it's only kept in the report text
+            None => {
+                if let Some(ticket_id) = &stripe.ticket_id {
+                    discrepancies.push(Discrepancy {
+                        payment_intent_id: stripe.id.clone(),
+                        ticket_id: None,
+                        kind: DiscrepancyKind::MissingLocally,
+                        local_value: None,
+                        stripe_value: Some(format!("{} (ticket_id={})", stripe.status, ticket_id)),
+                    });
+                }
+            }
+        }
+    }
+
+    let missing_at_stripe = sqlx::query_as::<_, (String, Uuid, PaymentIntentStatus)>(
+        r#"
+        SELECT id, ticket_id, status
+        FROM payment_intents
+        WHERE created_at >= $1 + INTERVAL '1 minute' * $2
+          AND NOT (id = ANY($3))
+        "#,
+    )
+    .bind(since)
+    .bind(MISSING_AT_STRIPE_MARGIN_MINUTES)
+    .bind(&stripe_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut checked_ids = stripe_ids;
+    for (payment_intent_id, ticket_id, status) in missing_at_stripe {
+        checked_ids.push(payment_intent_id.clone());
+        discrepancies.push(Discrepancy {
+            payment_intent_id,
+            ticket_id: Some(ticket_id),
+            kind: DiscrepancyKind::MissingAtStripe,
+            local_value: Some(format!("{:?}", status)),
+            stripe_value: None,
+        });
+    }
+
+    for discrepancy in &discrepancies {
+        sqlx::query(
+            r#"
+            INSERT INTO stripe_discrepancies (payment_intent_id, ticket_id, kind, local_value, stripe_value)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (payment_intent_id, kind) WHERE resolved_at IS NULL DO UPDATE
+            SET local_value = EXCLUDED.local_value,
+                stripe_value = EXCLUDED.stripe_value,
+                last_seen_at = NOW()
+            "#,
+        )
+        .bind(&discrepancy.payment_intent_id)
+        .bind(discrepancy.ticket_id)
+        .bind(discrepancy.kind)
+        .bind(&discrepancy.local_value)
+        .bind(&discrepancy.stripe_value)
+        .execute(pool)
+        .await?;
+
+        warn!(
+            "Stripe discrepancy on payment intent {}: {:?} (local {:?}, Stripe {:?})",
+            discrepancy.payment_intent_id, discrepancy.kind, discrepancy.local_value, discrepancy.stripe_value
+        );
+    }
+
+    // Anything checked this run but not seen again has been fixed
+    let resolved = sqlx::query(
+        r#"
+        UPDATE stripe_discrepancies
+        SET resolved_at = NOW()
+        WHERE resolved_at IS NULL
+          AND payment_intent_id = ANY($1)
+          AND last_seen_at < $2
+        "#,
+    )
+    .bind(&checked_ids)
+    .bind(run_started)
+    .execute(pool)
+    .await?;
+
+    // An intent is older than its first discrepancy, so one first seen before the
+    // window is out of every later run too. A short manual run mustn't expire what
+    // the scheduled runs still check, hence the longer of the two windows.
+    let expire_before = Utc::now() - Duration::hours(lookback_hours.max(reconciliation_lookback_hours()));
+    let expired = sqlx::query(
+        r#"
+        UPDATE stripe_discrepancies
+        SET resolved_at = NOW(),
+            expired = TRUE
+        WHERE resolved_at IS NULL
+          AND first_seen_at < $1
+        "#,
+    )
+    .bind(expire_before)
+    .execute(pool)
+    .await?;
+
+    let summary = ReconciliationSummary {
+        checked: checked_ids.len(),
+        discrepancies: discrepancies.len(),
+        resolved: resolved.rows_affected(),
+        expired: expired.rows_affected(),
+    };
+
+    info!(
+        "Reconciliation checked {} payment intents: {} discrepancies, {} resolved, {} expired",
+        summary.checked, summary.discrepancies, summary.resolved, summary.expired
+    );
+
+    Ok(summary)
+}
@@ -0,0 +1,320 @@
+use sqlx::{PgPool, Postgres};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::payment_intent::PaymentIntentStatus;
+use crate::models::stripe_operation::StripeOperationType;
+use crate::utils::config::env_or;
+use crate::utils::payment_gateway::PaymentGateway;
+use crate::utils::stripe_outbox::{enqueue_stripe_operation, process_stripe_operation};
+use crate::utils::ticket_events::TicketActor;
+use crate::utils::ticket_state::{apply_transition_on, Transition};
+
+fn refund_max_attempts() -> i32 {
+    env_or("REFUND_MAX_ATTEMPTS", 5)
+}
+
+fn refund_retry_base_seconds() -> i64 {
+    env_or("REFUND_RETRY_BASE_SECONDS", 60)
+}
+
+/// A disputed charge is settled through the dispute, not refunded
+fn disputed(payment_intent_id: &str) -> AppError {
+    AppError::Conflict(format!(
+        "Payment intent {} is disputed, resolve the dispute in Stripe instead of refunding",
+        payment_intent_id
+    ))
+}
+
+/// The intent that paid for the ticket's current sale, not one from an earlier reservation
+async fn sale_payment_intent<'e, E>(executor: E, ticket_id: Uuid) -> Result<Option<(String, PaymentIntentStatus)>>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    let payment_intent = sqlx::query_as::<_, (String, PaymentIntentStatus)>(
+        r#"
+        SELECT pi.id, pi.status
+        FROM payment_intents pi
+        JOIN tickets t ON t.id = pi.ticket_id
+        WHERE pi.ticket_id = $1
+          AND pi.buyer_id = t.reserved_by
+          AND pi.created_at >= t.reserved_at
+          AND pi.status IN ('capturable', 'captured', 'disputed', 'refunded', 'cancelled')
+        ORDER BY pi.updated_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(ticket_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(payment_intent)
+}
+
+/// Move a paid ticket to 'refunding' (paid → refunding)
+///
+/// Once a ticket is refunding it can no longer be marked sold, so the refund
+/// can't race the bot completing the transfer. Returns false if the ticket is
+/// not in a refundable state, and a conflict if its charge is disputed.
+/// Requesting again for a 'refunding' ticket starts the refund job's retries over,
+/// including for one it gave up on.
+///
+/// The ticket is leased for the caller's inline attempt the way `retry_refund` leases
+/// it, in the same transaction as the status change and counting as the first
+/// attempt, so the refund job only picks it up once that attempt has failed and the
+/// base backoff has passed.
+pub async fn request_refund(pool: &PgPool, actor: &TicketActor<'_>, ticket_id: Uuid) -> Result<bool> {
+    if let Some((payment_intent_id, PaymentIntentStatus::Disputed)) = sale_payment_intent(pool, ticket_id).await? {
+        return Err(disputed(&payment_intent_id));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let requested = apply_transition_on(&mut tx, actor, &Transition::RequestRefund { ticket_id }).await?;
+    if requested.is_empty() {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE tickets
+        SET refund_attempts = 1,
+            refund_last_error = NULL,
+            refund_next_attempt_at = NOW() + INTERVAL '1 second' * $2,
+            refund_failed_at = NULL
+        WHERE id = $1
+          AND status = 'refunding'
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(refund_retry_base_seconds())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Refund the buyer of a 'refunding' ticket (refunding → refunded)
+///
+/// Refunds the captured payment intent at Stripe, then marks the intent
+/// 'refunded', the ticket 'refunded' and cancels any payout not yet sent.
+/// If the capture never went through (still queued or dead-lettered), the
+/// authorization is cancelled instead and the intent ends up 'cancelled'.
+/// Safe to call repeatedly; the Stripe call is skipped if the intent was
+/// already refunded (e.g. via the charge.refunded webhook). A disputed charge
+/// is a conflict and is left for manual handling.
+pub async fn process_refund(
+    pool: &PgPool,
+    payments: &dyn PaymentGateway,
+    actor: &TicketActor<'_>,
+    ticket_id: Uuid,
+) -> Result<()> {
+    let (payment_intent_id, status) = sale_payment_intent(pool, ticket_id).await?.ok_or_else(|| {
+        error!("No paid payment intent found for refunding ticket {}", ticket_id);
+        AppError::Internal(anyhow::anyhow!("No paid payment intent for ticket"))
+    })?;
+
+    match status {
+        PaymentIntentStatus::Capturable => cancel_authorization(pool, payments, &payment_intent_id).await?,
+        PaymentIntentStatus::Captured => payments.refund_payment_intent(&payment_intent_id).await?,
+        PaymentIntentStatus::Disputed => return Err(disputed(&payment_intent_id)),
+        _ => {}
+    }
+
+    mark_refunded(pool, actor, &payment_intent_id, ticket_id).await?;
+
+    info!(
+        "Ticket {} refunded (payment intent {})",
+        ticket_id, payment_intent_id
+    );
+
+    Ok(())
+}
+
+/// Release the authorization of an intent that was never captured
+///
+/// Drops its queued capture and cancels it through the Stripe outbox, so a failed
+/// cancel is retried by the outbox worker. Errors until Stripe has confirmed the
+/// cancel, which leaves the ticket 'refunding' for the refund job to finish.
+async fn cancel_authorization(pool: &PgPool, payments: &dyn PaymentGateway, payment_intent_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE stripe_operations
+        SET status = 'cancelled',
+            updated_at = NOW()
+        WHERE payment_intent_id = $1
+          AND operation = 'capture'
+          AND status IN ('pending', 'dead')
+        "#,
+    )
+    .bind(payment_intent_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let operation_id = enqueue_stripe_operation(&mut *tx, payment_intent_id, StripeOperationType::Cancel).await?;
+
+    tx.commit().await?;
+
+    if let Some(operation_id) = operation_id {
+        process_stripe_operation(pool, payments, operation_id).await?;
+    }
+
+    let status = sqlx::query_scalar::<_, PaymentIntentStatus>("SELECT status FROM payment_intents WHERE id = $1")
+        .bind(payment_intent_id)
+        .fetch_one(pool)
+        .await?;
+
+    if !matches!(status, PaymentIntentStatus::Cancelled) {
+        return Err(AppError::Internal(anyhow::anyhow!(
+            "Cancel of payment intent {} not confirmed yet",
+            payment_intent_id
+        )));
+    }
+
+    Ok(())
+}
+
+/// Record a completed refund locally (in one transaction)
+pub async fn mark_refunded(
+    pool: &PgPool,
+    actor: &TicketActor<'_>,
+    payment_intent_id: &str,
+    ticket_id: Uuid,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE payment_intents
+        SET status = 'refunded',
+            updated_at = NOW()
+        WHERE id = $1
+          -- A released authorization never charged the buyer
+          AND status <> 'cancelled'
+        "#,
+    )
+    .bind(payment_intent_id)
+    .execute(&mut *tx)
+    .await?;
+
+    apply_transition_on(&mut tx, actor, &Transition::Refund { ticket_id }).await?;
+
+    // Nothing to pay out for a refunded sale
+    sqlx::query(
+        r#"
+        UPDATE payouts
+        SET status = 'cancelled',
+            updated_at = NOW()
+        WHERE ticket_id = $1
+          AND status IN ('pending', 'held')
+        "#,
+    )
+    .bind(ticket_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Attempt the refund of a single 'refunding' ticket from the refund job
+///
+/// The ticket is leased by bumping `refund_attempts` and pushing
+/// `refund_next_attempt_at` out with exponential backoff, so a concurrent job skips
+/// it. After `REFUND_MAX_ATTEMPTS` failures, or straight away for a disputed charge,
+/// `refund_failed_at` is set and the job leaves the ticket for manual intervention.
+async fn retry_refund(pool: &PgPool, payments: &dyn PaymentGateway, ticket_id: Uuid) -> Result<()> {
+    let attempts = sqlx::query_scalar::<_, i32>(
+        r#"
+        UPDATE tickets
+        SET refund_attempts = refund_attempts + 1,
+            refund_next_attempt_at = NOW() + INTERVAL '1 second' * $2 * POWER(2, LEAST(refund_attempts, 10))
+        WHERE id = $1
+          AND status = 'refunding'
+          AND refund_failed_at IS NULL
+          AND refund_next_attempt_at <= NOW()
+        RETURNING refund_attempts
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(refund_retry_base_seconds())
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(attempts) = attempts else {
+        info!("Refund of ticket {} not due, skipping", ticket_id);
+        return Ok(());
+    };
+
+    let e = match process_refund(pool, payments, &TicketActor::System("refund_job"), ticket_id).await {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+
+    let last_error = match &e {
+        AppError::Internal(inner) => inner.to_string(),
+        other => other.to_string(),
+    };
+    let max_attempts = refund_max_attempts();
+    // Retrying won't settle a dispute
+    let give_up = attempts >= max_attempts || matches!(e, AppError::Conflict(_));
+
+    sqlx::query(
+        r#"
+        UPDATE tickets
+        SET refund_last_error = $2,
+            refund_failed_at = CASE WHEN $3 THEN NOW() END
+        WHERE id = $1
+        "#,
+    )
+    .bind(ticket_id)
+    .bind(&last_error)
+    .bind(give_up)
+    .execute(pool)
+    .await?;
+
+    if give_up {
+        error!(
+            "Refund of ticket {} failed after {} attempts, needs manual intervention: {}",
+            ticket_id, attempts, e
+        );
+    } else {
+        warn!(
+            "Refund of ticket {} failed (attempt {}/{}), will retry: {}",
+            ticket_id, attempts, max_attempts, e
+        );
+    }
+
+    Ok(())
+}
+
+/// Retry every 'refunding' ticket whose next attempt is due
+pub async fn process_pending_refunds(pool: &PgPool, payments: &dyn PaymentGateway) -> Result<usize> {
+    let ticket_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id
+        FROM tickets
+        WHERE status = 'refunding'
+          AND refund_failed_at IS NULL
+          AND refund_next_attempt_at <= NOW()
+        ORDER BY refund_next_attempt_at ASC
+        LIMIT 50
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for ticket_id in &ticket_ids {
+        if let Err(e) = retry_refund(pool, payments, *ticket_id).await {
+            error!("Processing refund for ticket {} failed: {}", ticket_id, e);
+        }
+    }
+
+    Ok(ticket_ids.len())
+}
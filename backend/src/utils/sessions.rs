@@ -0,0 +1,149 @@
+use sqlx::{PgConnection, PgPool, Postgres};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::user::UserRole;
+use crate::utils::config::env_or;
+use crate::utils::password::{generate_secure_token, hash_secure_token};
+
+/// How long a session stays alive without being refreshed
+pub fn refresh_token_ttl_days() -> i64 {
+    env_or("REFRESH_TOKEN_TTL_DAYS", 30)
+}
+
+/// A session whose refresh token was just rotated
+pub struct RotatedSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub role: UserRole,
+    pub refresh_token: String,
+}
+
+/// Start a session for a user, returning the session ID and its refresh token
+pub async fn create_session<'e, E>(executor: E, user_id: Uuid) -> Result<(Uuid, String)>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    let refresh_token = generate_secure_token();
+
+    let session_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO sessions (user_id, refresh_token_hash, expires_at)
+        VALUES ($1, $2, NOW() + INTERVAL '1 day' * $3)
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_secure_token(&refresh_token))
+    .bind(refresh_token_ttl_days())
+    .fetch_one(executor)
+    .await?;
+
+    Ok((session_id, refresh_token))
+}
+
+/// Exchange a refresh token for a new one
+///
+/// Each refresh token works once. Presenting the token a session already
+/// rotated away from means it was copied, so the whole session is revoked.
+pub async fn rotate_session(pool: &PgPool, refresh_token: &str) -> Result<RotatedSession> {
+    let token_hash = hash_secure_token(refresh_token);
+    let mut tx = pool.begin().await?;
+
+    let session = sqlx::query_as::<_, (Uuid, Uuid, String, UserRole, bool)>(
+        r#"
+        SELECT s.id, s.user_id, u.email, u.role, s.refresh_token_hash = $1 AS current
+        FROM sessions s
+        JOIN users u ON u.id = s.user_id
+        WHERE (s.refresh_token_hash = $1 OR s.previous_refresh_token_hash = $1)
+          AND s.revoked_at IS NULL
+          AND s.expires_at > NOW()
+        FOR UPDATE OF s
+        "#,
+    )
+    .bind(&token_hash)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (session_id, user_id, email, role, current) = session.ok_or(AppError::Unauthorized)?;
+
+    if !current {
+        revoke_session(&mut tx, session_id).await?;
+        tx.commit().await?;
+        warn!(
+            "Reused refresh token for session {} (user {}), session revoked",
+            session_id, user_id
+        );
+        return Err(AppError::Unauthorized);
+    }
+
+    let new_refresh_token = generate_secure_token();
+
+    sqlx::query(
+        r#"
+        UPDATE sessions
+        SET previous_refresh_token_hash = refresh_token_hash,
+            refresh_token_hash = $2,
+            expires_at = NOW() + INTERVAL '1 day' * $3
+        WHERE id = $1
+        "#,
+    )
+    .bind(session_id)
+    .bind(hash_secure_token(&new_refresh_token))
+    .bind(refresh_token_ttl_days())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(RotatedSession {
+        session_id,
+        user_id,
+        email,
+        role,
+        refresh_token: new_refresh_token,
+    })
+}
+
+/// Revoke a single session
+pub async fn revoke_session(conn: &mut PgConnection, session_id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE id = $1
+          AND revoked_at IS NULL
+        "#,
+    )
+    .bind(session_id)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke every live session of a user, returning how many were revoked
+pub async fn revoke_all_sessions<'e, E>(executor: E, user_id: Uuid) -> Result<u64>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions
+        SET revoked_at = NOW()
+        WHERE user_id = $1
+          AND revoked_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        info!("Revoked {} sessions for user {}", result.rows_affected(), user_id);
+    }
+
+    Ok(result.rows_affected())
+}
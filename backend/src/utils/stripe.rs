@@ -1,17 +1,18 @@
-use stripe::{Client, PaymentIntent, PaymentIntentId, CapturePaymentIntent, CancelPaymentIntent};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use stripe::{
+    Account, AccountId, AccountLink, AccountLinkType, AccountType, CancelPaymentIntent,
+    CapturePaymentIntent, ChargeId, Client, CreateAccount, CreateAccountLink, CreatePaymentIntent,
+    CreateRefund, CreateTransfer, Currency, ListPaymentIntents, Metadata, PaymentIntent,
+    PaymentIntentCaptureMethod, PaymentIntentId, RangeQuery, Refund, RequestStrategy, Transfer,
+};
 use std::str::FromStr;
 use std::env;
 use tracing::{error, info};
+use uuid::Uuid;
 
 use crate::error::{AppError, Result};
-
-/// Get Stripe client instance
-fn get_stripe_client() -> Result<Client> {
-    let secret_key = env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| AppError::Internal(anyhow::anyhow!("STRIPE_SECRET_KEY environment variable not set")))?;
-    
-    Ok(Client::new(secret_key))
-}
+use crate::utils::payment_gateway::{CheckoutPaymentIntent, PaymentGateway, PaymentIntentSnapshot};
 
 /// Verify Stripe webhook signature
 /// 
@@ -29,41 +30,262 @@ pub fn verify_stripe_webhook_signature(
     Ok(())
 }
 
-/// Capture a payment intent (charge the buyer)
-pub async fn capture_payment_intent(payment_intent_id: &str) -> Result<()> {
-    let client = get_stripe_client()?;
-    let payment_intent_id = PaymentIntentId::from_str(payment_intent_id)
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid payment intent ID: {}", e)))?;
+/// Payment gateway backed by the Stripe API
+///
+/// Holds one client for the lifetime of the process, so connections are reused
+/// across requests and background jobs.
+pub struct StripeGateway {
+    client: Client,
+}
 
-    info!("Capturing payment intent: {}", payment_intent_id);
+impl StripeGateway {
+    /// Create a Stripe gateway from environment
+    ///
+    /// Environment variables:
+    /// - STRIPE_SECRET_KEY: API secret key (required)
+    /// - STRIPE_API_BASE_URL: API base URL, e.g. a mock Stripe server for testing (optional)
+    pub fn from_env() -> anyhow::Result<Self> {
+        let secret_key = env::var("STRIPE_SECRET_KEY")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("STRIPE_SECRET_KEY environment variable not set"))?;
 
-    PaymentIntent::capture(&client, &payment_intent_id, CapturePaymentIntent::default())
-        .await
-        .map_err(|e| {
-            error!("Failed to capture payment intent {}: {:?}", payment_intent_id, e);
-            AppError::Internal(anyhow::anyhow!("Failed to capture payment intent: {}", e))
-        })?;
+        let client = match env::var("STRIPE_API_BASE_URL").ok().filter(|v| !v.trim().is_empty()) {
+            Some(base_url) => Client::from_url(base_url.as_str(), secret_key),
+            None => Client::new(secret_key),
+        };
 
-    info!("Successfully captured payment intent: {}", payment_intent_id);
-    Ok(())
+        Ok(Self { client })
+    }
+
+    /// The shared client, sending `idempotency_key` with each request
+    fn idempotent(&self, idempotency_key: String) -> Client {
+        self.client
+            .clone()
+            .with_strategy(RequestStrategy::Idempotent(idempotency_key))
+    }
+}
+
+fn parse_payment_intent_id(payment_intent_id: &str) -> Result<PaymentIntentId> {
+    PaymentIntentId::from_str(payment_intent_id)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid payment intent ID: {}", e)))
+}
+
+fn snapshot(payment_intent: PaymentIntent) -> PaymentIntentSnapshot {
+    PaymentIntentSnapshot {
+        id: payment_intent.id.to_string(),
+        amount: payment_intent.amount,
+        currency: payment_intent.currency.to_string(),
+        status: payment_intent.status.as_str().to_string(),
+        client_secret: payment_intent.client_secret,
+        ticket_id: payment_intent.metadata.get("ticket_id").cloned(),
+        buyer_id: payment_intent.metadata.get("buyer_id").cloned(),
+        created: payment_intent.created,
+        latest_charge: payment_intent.latest_charge.map(|charge| charge.id().to_string()),
+    }
 }
 
-/// Cancel a payment intent (release the authorization hold)
-pub async fn cancel_payment_intent(payment_intent_id: &str) -> Result<()> {
-    let client = get_stripe_client()?;
-    let payment_intent_id = PaymentIntentId::from_str(payment_intent_id)
-        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid payment intent ID: {}", e)))?;
+#[async_trait]
+impl PaymentGateway for StripeGateway {
+    /// The amount and metadata are set server-side so the buyer cannot tamper with them.
+    /// Uses a per-reservation idempotency key so concurrent or retried checkouts share one intent.
+    async fn create_payment_intent(
+        &self,
+        amount: i32,
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        reserved_at: DateTime<Utc>,
+    ) -> Result<CheckoutPaymentIntent> {
+        let client = self.idempotent(format!("checkout-{}-{}", ticket_id, reserved_at.timestamp_micros()));
+
+        let mut metadata = Metadata::new();
+        metadata.insert("ticket_id".to_string(), ticket_id.to_string());
+        metadata.insert("buyer_id".to_string(), buyer_id.to_string());
+        metadata.insert("reserved_at".to_string(), reserved_at.to_rfc3339());
 
-    info!("Cancelling payment intent: {}", payment_intent_id);
+        let mut params = CreatePaymentIntent::new(amount.into(), Currency::USD);
+        params.capture_method = Some(PaymentIntentCaptureMethod::Manual);
+        params.metadata = Some(metadata);
 
-    PaymentIntent::cancel(&client, &payment_intent_id, CancelPaymentIntent { cancellation_reason: None })
-        .await
-        .map_err(|e| {
-            error!("Failed to cancel payment intent {}: {:?}", payment_intent_id, e);
-            AppError::Internal(anyhow::anyhow!("Failed to cancel payment intent: {}", e))
+        info!("Creating payment intent for ticket {} (amount {})", ticket_id, amount);
+
+        let payment_intent = PaymentIntent::create(&client, params)
+            .await
+            .map_err(|e| {
+                error!("Failed to create payment intent for ticket {}: {:?}", ticket_id, e);
+                AppError::Internal(anyhow::anyhow!("Failed to create payment intent: {}", e))
+            })?;
+
+        let client_secret = payment_intent.client_secret.ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("Payment intent {} has no client secret", payment_intent.id))
         })?;
 
-    info!("Successfully cancelled payment intent: {}", payment_intent_id);
-    Ok(())
-}
+        info!("Created payment intent {} for ticket {}", payment_intent.id, ticket_id);
+        Ok(CheckoutPaymentIntent {
+            id: payment_intent.id.to_string(),
+            client_secret,
+            currency: payment_intent.currency.to_string(),
+        })
+    }
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> Result<PaymentIntentSnapshot> {
+        let payment_intent_id = parse_payment_intent_id(payment_intent_id)?;
+
+        let payment_intent = PaymentIntent::retrieve(&self.client, &payment_intent_id, &[])
+            .await
+            .map_err(|e| {
+                error!("Failed to retrieve payment intent {}: {:?}", payment_intent_id, e);
+                AppError::Internal(anyhow::anyhow!("Failed to retrieve payment intent: {}", e))
+            })?;
+
+        Ok(snapshot(payment_intent))
+    }
+
+    /// Uses a per-intent idempotency key so a retry after a lost response is safe.
+    async fn capture_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        let client = self.idempotent(format!("capture-{}", payment_intent_id));
+        let payment_intent_id = parse_payment_intent_id(payment_intent_id)?;
+
+        info!("Capturing payment intent: {}", payment_intent_id);
+
+        PaymentIntent::capture(&client, &payment_intent_id, CapturePaymentIntent::default())
+            .await
+            .map_err(|e| {
+                error!("Failed to capture payment intent {}: {:?}", payment_intent_id, e);
+                AppError::Internal(anyhow::anyhow!("Failed to capture payment intent: {}", e))
+            })?;
+
+        info!("Successfully captured payment intent: {}", payment_intent_id);
+        Ok(())
+    }
+
+    /// Uses a per-intent idempotency key so a retry after a lost response is safe.
+    async fn cancel_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        let client = self.idempotent(format!("cancel-{}", payment_intent_id));
+        let payment_intent_id = parse_payment_intent_id(payment_intent_id)?;
+
+        info!("Cancelling payment intent: {}", payment_intent_id);
+
+        PaymentIntent::cancel(&client, &payment_intent_id, CancelPaymentIntent { cancellation_reason: None })
+            .await
+            .map_err(|e| {
+                error!("Failed to cancel payment intent {}: {:?}", payment_intent_id, e);
+                AppError::Internal(anyhow::anyhow!("Failed to cancel payment intent: {}", e))
+            })?;
+
+        info!("Successfully cancelled payment intent: {}", payment_intent_id);
+        Ok(())
+    }
+
+    /// Uses a per-intent idempotency key so retried refunds never refund twice.
+    async fn refund_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        let client = self.idempotent(format!("refund-{}", payment_intent_id));
+        let payment_intent_id = parse_payment_intent_id(payment_intent_id)?;
 
+        info!("Refunding payment intent: {}", payment_intent_id);
+
+        let mut params = CreateRefund::new();
+        params.payment_intent = Some(payment_intent_id.clone());
+
+        Refund::create(&client, params).await.map_err(|e| {
+            error!("Failed to refund payment intent {}: {:?}", payment_intent_id, e);
+            AppError::Internal(anyhow::anyhow!("Failed to refund payment intent: {}", e))
+        })?;
+
+        info!("Successfully refunded payment intent: {}", payment_intent_id);
+        Ok(())
+    }
+
+    async fn list_payment_intents_since(&self, since: DateTime<Utc>) -> Result<Vec<PaymentIntentSnapshot>> {
+        let mut snapshots = Vec::new();
+        let mut starting_after: Option<PaymentIntentId> = None;
+
+        loop {
+            let mut params = ListPaymentIntents::new();
+            params.created = Some(RangeQuery::gte(since.timestamp()));
+            params.limit = Some(100);
+            params.starting_after = starting_after.take();
+
+            let page = PaymentIntent::list(&self.client, &params).await.map_err(|e| {
+                error!("Failed to list payment intents: {:?}", e);
+                AppError::Internal(anyhow::anyhow!("Failed to list payment intents: {}", e))
+            })?;
+
+            starting_after = page.data.last().map(|payment_intent| payment_intent.id.clone());
+            snapshots.extend(page.data.into_iter().map(snapshot));
+
+            if !page.has_more || starting_after.is_none() {
+                break;
+            }
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Creates an Express account.
+    async fn create_connected_account(&self, email: &str) -> Result<String> {
+        let mut params = CreateAccount::new();
+        params.type_ = Some(AccountType::Express);
+        params.email = Some(email);
+
+        let account = Account::create(&self.client, params).await.map_err(|e| {
+            error!("Failed to create connected account for {}: {:?}", email, e);
+            AppError::Internal(anyhow::anyhow!("Failed to create connected account: {}", e))
+        })?;
+
+        info!("Created connected account {} for {}", account.id, email);
+        Ok(account.id.to_string())
+    }
+
+    async fn create_account_onboarding_link(&self, account_id: &str) -> Result<String> {
+        let account_id = AccountId::from_str(account_id)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid account ID: {}", e)))?;
+
+        let refresh_url = env::var("STRIPE_CONNECT_REFRESH_URL")
+            .map_err(|_| AppError::Internal(anyhow::anyhow!("STRIPE_CONNECT_REFRESH_URL environment variable not set")))?;
+        let return_url = env::var("STRIPE_CONNECT_RETURN_URL")
+            .map_err(|_| AppError::Internal(anyhow::anyhow!("STRIPE_CONNECT_RETURN_URL environment variable not set")))?;
+
+        let mut params = CreateAccountLink::new(account_id.clone(), AccountLinkType::AccountOnboarding);
+        params.refresh_url = Some(&refresh_url);
+        params.return_url = Some(&return_url);
+
+        let link = AccountLink::create(&self.client, params).await.map_err(|e| {
+            error!("Failed to create onboarding link for {}: {:?}", account_id, e);
+            AppError::Internal(anyhow::anyhow!("Failed to create onboarding link: {}", e))
+        })?;
+
+        Ok(link.url)
+    }
+
+    async fn create_transfer(
+        &self,
+        idempotency_key: &str,
+        destination: &str,
+        amount: i32,
+        currency: &str,
+        transfer_group: &str,
+        source_transaction: &str,
+    ) -> Result<String> {
+        let client = self.idempotent(idempotency_key.to_string());
+        let currency = Currency::from_str(currency)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid currency: {}", e)))?;
+        let source_transaction = ChargeId::from_str(source_transaction)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid charge ID: {}", e)))?;
+
+        let mut params = CreateTransfer::new(currency, destination.to_string());
+        params.amount = Some(amount.into());
+        params.transfer_group = Some(transfer_group);
+        params.source_transaction = Some(source_transaction);
+
+        info!("Transferring {} {} to {}", amount, currency, destination);
+
+        let transfer = Transfer::create(&client, params).await.map_err(|e| {
+            error!("Failed to transfer to {}: {:?}", destination, e);
+            AppError::Internal(anyhow::anyhow!("Failed to create transfer: {}", e))
+        })?;
+
+        info!("Successfully created transfer {} to {}", transfer.id, destination);
+        Ok(transfer.id.to_string())
+    }
+}
@@ -0,0 +1,228 @@
+use sqlx::{PgPool, Postgres};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::payment_intent::PaymentIntentStatus;
+use crate::models::stripe_operation::{StripeOperationStatus, StripeOperationType};
+use crate::utils::config::env_or;
+use crate::utils::payment_gateway::PaymentGateway;
+
+fn stripe_operation_max_attempts() -> i32 {
+    env_or("STRIPE_OPERATION_MAX_ATTEMPTS", 8)
+}
+
+fn stripe_operation_retry_base_seconds() -> i64 {
+    env_or("STRIPE_OPERATION_RETRY_BASE_SECONDS", 30)
+}
+
+/// Record a Stripe capture/cancel that must eventually happen
+///
+/// At most one operation of each type exists per payment intent, so enqueueing
+/// twice (e.g. on a webhook redelivery) is a no-op. Returns the operation ID if
+/// one was created.
+pub async fn enqueue_stripe_operation<'e, E>(
+    executor: E,
+    payment_intent_id: &str,
+    operation: StripeOperationType,
+) -> Result<Option<Uuid>>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    let operation_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO stripe_operations (payment_intent_id, operation)
+        VALUES ($1, $2)
+        ON CONFLICT (payment_intent_id, operation) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(payment_intent_id)
+    .bind(operation)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(operation_id)
+}
+
+/// Attempt a single pending Stripe operation
+///
+/// The operation is leased by bumping `attempts` and pushing `next_attempt_at` out
+/// with exponential backoff, so a concurrent worker skips it. The payment intent
+/// only moves to 'captured'/'cancelled' once Stripe confirms the call. Operations
+/// that keep failing end up 'dead' for an admin to inspect and retry.
+pub async fn process_stripe_operation(
+    pool: &PgPool,
+    payments: &dyn PaymentGateway,
+    operation_id: Uuid,
+) -> Result<()> {
+    let leased = sqlx::query_as::<_, (Uuid, String, StripeOperationType, i32)>(
+        r#"
+        UPDATE stripe_operations
+        SET attempts = attempts + 1,
+            next_attempt_at = NOW() + INTERVAL '1 second' * $2 * POWER(2, LEAST(attempts, 10)),
+            updated_at = NOW()
+        WHERE id = $1
+          AND status = 'pending'
+          AND next_attempt_at <= NOW()
+        RETURNING id, payment_intent_id, operation, attempts
+        "#,
+    )
+    .bind(operation_id)
+    .bind(stripe_operation_retry_base_seconds())
+    .fetch_optional(pool)
+    .await?;
+
+    let (operation_id, payment_intent_id, operation, attempts) = match leased {
+        Some(leased) => leased,
+        None => {
+            info!("Stripe operation {} not due, skipping", operation_id);
+            return Ok(());
+        }
+    };
+
+    let (stripe_result, confirmed_status) = match operation {
+        StripeOperationType::Capture => (
+            payments.capture_payment_intent(&payment_intent_id).await,
+            PaymentIntentStatus::Captured,
+        ),
+        StripeOperationType::Cancel => (
+            payments.cancel_payment_intent(&payment_intent_id).await,
+            PaymentIntentStatus::Cancelled,
+        ),
+    };
+
+    match stripe_result {
+        Ok(()) => {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(
+                r#"
+                UPDATE stripe_operations
+                SET status = $2,
+                    last_error = NULL,
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(operation_id)
+            .bind(StripeOperationStatus::Succeeded)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                UPDATE payment_intents
+                SET status = $2,
+                    updated_at = NOW()
+                WHERE id = $1
+                  AND (
+                      status = 'capturable'
+                      -- A cancel can also drop an intent that was never authorized,
+                      -- e.g. when the buyer releases their reservation after checkout
+                      OR ($3 AND status IN ('created', 'failed'))
+                  )
+                "#,
+            )
+            .bind(&payment_intent_id)
+            .bind(confirmed_status)
+            .bind(matches!(operation, StripeOperationType::Cancel))
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
+
+            info!(
+                "Stripe {:?} of payment intent {} confirmed, marked {:?}",
+                operation, payment_intent_id, confirmed_status
+            );
+        }
+        Err(e) => {
+            let last_error = match &e {
+                AppError::Internal(inner) => inner.to_string(),
+                other => other.to_string(),
+            };
+            let max_attempts = stripe_operation_max_attempts();
+            let status = if attempts >= max_attempts {
+                StripeOperationStatus::Dead
+            } else {
+                StripeOperationStatus::Pending
+            };
+
+            sqlx::query(
+                r#"
+                UPDATE stripe_operations
+                SET status = $2,
+                    last_error = $3,
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(operation_id)
+            .bind(status)
+            .bind(&last_error)
+            .execute(pool)
+            .await?;
+
+            if attempts >= max_attempts {
+                error!(
+                    "Stripe {:?} of payment intent {} dead-lettered after {} attempts, needs manual intervention: {}",
+                    operation, payment_intent_id, attempts, e
+                );
+            } else {
+                warn!(
+                    "Stripe {:?} of payment intent {} failed (attempt {}/{}), will retry: {}",
+                    operation, payment_intent_id, attempts, max_attempts, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Attempt every pending Stripe operation whose retry time has come
+pub async fn process_due_stripe_operations(pool: &PgPool, payments: &dyn PaymentGateway) -> Result<usize> {
+    let operation_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT id
+        FROM stripe_operations
+        WHERE status = 'pending'
+          AND next_attempt_at <= NOW()
+        ORDER BY next_attempt_at ASC
+        LIMIT 50
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for operation_id in &operation_ids {
+        if let Err(e) = process_stripe_operation(pool, payments, *operation_id).await {
+            error!("Processing Stripe operation {} failed: {}", operation_id, e);
+        }
+    }
+
+    Ok(operation_ids.len())
+}
+
+/// Start the background worker that drains the Stripe operation outbox
+pub fn start_stripe_outbox_worker(pool: PgPool, payments: Arc<dyn PaymentGateway>) {
+    let interval_seconds = env_or("STRIPE_OUTBOX_INTERVAL_SECONDS", 15);
+    let mut ticker = interval(Duration::from_secs(interval_seconds));
+
+    tokio::spawn(async move {
+        loop {
+            ticker.tick().await;
+            match process_due_stripe_operations(&pool, payments.as_ref()).await {
+                Ok(processed) => {
+                    if processed > 0 {
+                        info!("Stripe outbox worker processed {} operations", processed);
+                    }
+                }
+                Err(e) => error!("Stripe outbox processing failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,69 @@
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::utils::auth::{Actor, Bot};
+
+/// Who changed a ticket's status, as recorded in `ticket_events`
+pub enum TicketActor<'a> {
+    /// The seller acting on their listing
+    Seller(Uuid),
+    /// A buyer acting on a ticket they're purchasing
+    Buyer(Uuid),
+    Bot(&'a Bot),
+    Admin(&'a Actor),
+    /// A background job or the Stripe webhook, by name
+    System(&'static str),
+}
+
+impl TicketActor<'_> {
+    fn parts(&self) -> (&'static str, Option<Uuid>, Option<String>) {
+        match self {
+            TicketActor::Seller(user_id) | TicketActor::Buyer(user_id) => ("user", Some(*user_id), None),
+            TicketActor::Bot(bot) => ("bot", Some(bot.id), Some(bot.name.clone())),
+            TicketActor::Admin(actor) => ("admin", Some(actor.id()), Some(actor.label())),
+            TicketActor::System(job) => ("system", None, Some(job.to_string())),
+        }
+    }
+}
+
+/// Attribute the ticket status changes made in the current transaction
+///
+/// The `ticket_events` trigger reads these transaction-local settings, so this
+/// must run on the same transaction as the `UPDATE tickets`. Changes made
+/// without it are recorded as an anonymous 'system' actor.
+pub async fn set_ticket_event_context(
+    conn: &mut PgConnection,
+    actor: &TicketActor<'_>,
+    reason: &str,
+) -> Result<()> {
+    let (actor_type, actor_id, actor_label) = actor.parts();
+
+    sqlx::query(
+        r#"
+        SELECT set_config('ticket_events.actor_type', $1, true),
+               set_config('ticket_events.actor_id', $2, true),
+               set_config('ticket_events.actor_label', $3, true),
+               set_config('ticket_events.reason', $4, true)
+        "#,
+    )
+    .bind(actor_type)
+    .bind(actor_id.map(|id| id.to_string()).unwrap_or_default())
+    .bind(actor_label.unwrap_or_default())
+    .bind(reason)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Begin a transaction whose ticket status changes are attributed to `actor`
+pub async fn begin_ticket_transition(
+    pool: &PgPool,
+    actor: &TicketActor<'_>,
+    reason: &str,
+) -> Result<Transaction<'static, Postgres>> {
+    let mut tx = pool.begin().await?;
+    set_ticket_event_context(&mut tx, actor, reason).await?;
+    Ok(tx)
+}
@@ -0,0 +1,637 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::error::{AppError, Result};
+use crate::models::ticket::TicketStatus;
+use crate::utils::ticket_events::{set_ticket_event_context, TicketActor};
+
+/// Kind of caller behind a ticket status change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActorKind {
+    Seller,
+    Buyer,
+    Bot,
+    Admin,
+    System,
+}
+
+impl ActorKind {
+    pub fn of(actor: &TicketActor<'_>) -> Self {
+        match actor {
+            TicketActor::Seller(_) => ActorKind::Seller,
+            TicketActor::Buyer(_) => ActorKind::Buyer,
+            TicketActor::Bot(_) => ActorKind::Bot,
+            TicketActor::Admin(_) => ActorKind::Admin,
+            TicketActor::System(_) => ActorKind::System,
+        }
+    }
+}
+
+/// Every legal ticket status change (see TICKET_STATE_TRANSITIONS.md)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    Claim,
+    Verify,
+    Unclaim,
+    VerificationTimeout,
+    ExpireListing,
+    Cancel,
+    Reserve,
+    Pay,
+    ReleaseReservation,
+    CancelReservation,
+    ExpireReservation,
+    MarkSold,
+    RequestRefund,
+    RefundOverdue,
+    Refund,
+}
+
+impl TransitionKind {
+    /// Statuses the ticket may be in before the change
+    pub fn from(self) -> &'static [TicketStatus] {
+        use TicketStatus::*;
+
+        match self {
+            TransitionKind::Claim | TransitionKind::ExpireListing => &[Unverified],
+            TransitionKind::Verify | TransitionKind::Unclaim | TransitionKind::VerificationTimeout => &[Verifying],
+            TransitionKind::Cancel => &[Unverified, Verified],
+            // A reservation past its window can be taken over by another buyer
+            TransitionKind::Reserve => &[Verified, Reserved],
+            TransitionKind::Pay
+            | TransitionKind::ReleaseReservation
+            | TransitionKind::CancelReservation
+            | TransitionKind::ExpireReservation => &[Reserved],
+            TransitionKind::MarkSold | TransitionKind::RefundOverdue => &[Paid],
+            // Refunds are retried until Stripe confirms them
+            TransitionKind::RequestRefund | TransitionKind::Refund => &[Paid, Refunding],
+        }
+    }
+
+    /// Status after the change (None: the ticket is deleted)
+    pub fn to(self) -> Option<TicketStatus> {
+        use TicketStatus::*;
+
+        match self {
+            TransitionKind::Claim => Some(Verifying),
+            TransitionKind::Verify => Some(Verified),
+            TransitionKind::Unclaim | TransitionKind::VerificationTimeout => Some(Unverified),
+            TransitionKind::ExpireListing => None,
+            TransitionKind::Cancel => Some(Cancelled),
+            TransitionKind::Reserve => Some(Reserved),
+            TransitionKind::Pay => Some(Paid),
+            TransitionKind::ReleaseReservation
+            | TransitionKind::CancelReservation
+            | TransitionKind::ExpireReservation => Some(Verified),
+            TransitionKind::MarkSold => Some(Sold),
+            TransitionKind::RequestRefund | TransitionKind::RefundOverdue => Some(Refunding),
+            TransitionKind::Refund => Some(Refunded),
+        }
+    }
+
+    /// Callers allowed to trigger the change
+    pub fn actors(self) -> &'static [ActorKind] {
+        use ActorKind::*;
+
+        match self {
+            TransitionKind::Claim | TransitionKind::Verify | TransitionKind::Unclaim | TransitionKind::MarkSold => {
+                &[Bot]
+            }
+            TransitionKind::Cancel => &[Seller],
+            TransitionKind::Reserve | TransitionKind::CancelReservation => &[Buyer],
+            TransitionKind::VerificationTimeout
+            | TransitionKind::ExpireListing
+            | TransitionKind::Pay
+            | TransitionKind::ReleaseReservation
+            | TransitionKind::ExpireReservation
+            | TransitionKind::RefundOverdue => &[System],
+            TransitionKind::RequestRefund | TransitionKind::Refund => &[Admin, Bot, System],
+        }
+    }
+
+    pub fn allows(self, actor: ActorKind) -> bool {
+        self.actors().contains(&actor)
+    }
+}
+
+/// A ticket status change and the parameters of its guard
+///
+/// Each variant updates only tickets in one of its `from` statuses that also pass
+/// its guard, in a single conditional statement, so concurrent callers can't both
+/// succeed.
+#[derive(Debug, Clone)]
+pub enum Transition<'a> {
+    /// unverified → verifying: the oldest live listing for a seat
+    Claim {
+        event_name: &'a str,
+        seat_section: &'a str,
+        seat_row: &'a str,
+        seat_number: &'a str,
+    },
+    /// verifying → verified
+    Verify { ticket_id: Uuid },
+    /// verifying → unverified, released by the bot
+    Unclaim { ticket_id: Uuid },
+    /// verifying → unverified, for every ticket claimed longer ago than the timeout
+    VerificationTimeout { timeout_minutes: i64 },
+    /// unverified → deleted, for every listing past its transfer deadline
+    ExpireListing,
+    /// unverified/verified → cancelled by the seller, optionally repricing
+    Cancel { ticket_id: Uuid, price: Option<i32> },
+    /// verified → reserved by anyone but the seller, or takes over a reservation made before `expired_before`
+    Reserve { ticket_id: Uuid, expired_before: DateTime<Utc> },
+    /// reserved → paid, if still reserved by the buyer within the window at the authorized amount
+    Pay {
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        reserved_after: DateTime<Utc>,
+        amount: i64,
+    },
+    /// reserved → verified, after the payment intent created at `intent_created_at` failed
+    ReleaseReservation {
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        intent_created_at: DateTime<Utc>,
+        reason: &'a str,
+    },
+    /// reserved → verified, given up by the buyer holding the reservation
+    CancelReservation { ticket_id: Uuid },
+    /// reserved → verified, for every reservation older than the window
+    ExpireReservation { window_minutes: i64 },
+    /// paid → sold
+    MarkSold { ticket_id: Uuid },
+    /// paid → refunding
+    RequestRefund { ticket_id: Uuid },
+    /// paid → refunding, for every ticket paid longer ago than the transfer deadline
+    RefundOverdue { deadline_hours: i64 },
+    /// paid/refunding → refunded
+    Refund { ticket_id: Uuid },
+}
+
+impl Transition<'_> {
+    pub fn kind(&self) -> TransitionKind {
+        match self {
+            Transition::Claim { .. } => TransitionKind::Claim,
+            Transition::Verify { .. } => TransitionKind::Verify,
+            Transition::Unclaim { .. } => TransitionKind::Unclaim,
+            Transition::VerificationTimeout { .. } => TransitionKind::VerificationTimeout,
+            Transition::ExpireListing => TransitionKind::ExpireListing,
+            Transition::Cancel { .. } => TransitionKind::Cancel,
+            Transition::Reserve { .. } => TransitionKind::Reserve,
+            Transition::Pay { .. } => TransitionKind::Pay,
+            Transition::ReleaseReservation { .. } => TransitionKind::ReleaseReservation,
+            Transition::CancelReservation { .. } => TransitionKind::CancelReservation,
+            Transition::ExpireReservation { .. } => TransitionKind::ExpireReservation,
+            Transition::MarkSold { .. } => TransitionKind::MarkSold,
+            Transition::RequestRefund { .. } => TransitionKind::RequestRefund,
+            Transition::RefundOverdue { .. } => TransitionKind::RefundOverdue,
+            Transition::Refund { .. } => TransitionKind::Refund,
+        }
+    }
+
+    /// Reason recorded in `ticket_events`
+    fn reason(&self) -> &str {
+        match self {
+            Transition::Claim { .. } => "claimed for verification",
+            Transition::Verify { .. } => "transfer verified",
+            Transition::Unclaim { .. } => "claim released",
+            Transition::VerificationTimeout { .. } => "verification timed out",
+            Transition::ExpireListing => "transfer deadline passed",
+            Transition::Cancel { .. } => "cancelled by seller",
+            Transition::Reserve { .. } => "reserved by buyer",
+            Transition::Pay { .. } => "payment authorized",
+            Transition::ReleaseReservation { reason, .. } => reason,
+            Transition::CancelReservation { .. } => "reservation released by buyer",
+            Transition::ExpireReservation { .. } => "reservation expired",
+            Transition::MarkSold { .. } => "transferred to buyer",
+            Transition::RequestRefund { .. } => "refund requested",
+            Transition::RefundOverdue { .. } => "ticket not transferred to buyer in time",
+            Transition::Refund { .. } => "buyer refunded",
+        }
+    }
+
+    /// The ticket a single-ticket transition applies to (None: selected by its guard)
+    fn ticket_id(&self) -> Option<Uuid> {
+        match self {
+            Transition::Verify { ticket_id }
+            | Transition::Unclaim { ticket_id }
+            | Transition::Cancel { ticket_id, .. }
+            | Transition::Reserve { ticket_id, .. }
+            | Transition::Pay { ticket_id, .. }
+            | Transition::ReleaseReservation { ticket_id, .. }
+            | Transition::CancelReservation { ticket_id }
+            | Transition::MarkSold { ticket_id }
+            | Transition::RequestRefund { ticket_id }
+            | Transition::Refund { ticket_id } => Some(*ticket_id),
+            Transition::Claim { .. }
+            | Transition::VerificationTimeout { .. }
+            | Transition::ExpireListing
+            | Transition::ExpireReservation { .. }
+            | Transition::RefundOverdue { .. } => None,
+        }
+    }
+
+    /// Build the conditional `UPDATE`/`DELETE ... RETURNING id` for this transition
+    ///
+    /// Single-ticket transitions wait for a concurrent change to the row and then
+    /// re-check the guard; the others lock their candidates with `SKIP LOCKED` so
+    /// overlapping jobs split the work instead of blocking each other.
+    fn query(&self, actor: &TicketActor<'_>) -> QueryBuilder<'_, Postgres> {
+        let kind = self.kind();
+        let mut query = QueryBuilder::new("");
+
+        match kind.to() {
+            Some(to) => {
+                query.push("UPDATE tickets SET status = ").push_bind(to);
+                query.push(", updated_at = NOW()");
+                self.push_assignments(&mut query, actor);
+            }
+            None => {
+                query.push("DELETE FROM tickets");
+            }
+        }
+
+        match self.ticket_id() {
+            Some(ticket_id) => {
+                query.push(" WHERE id = ").push_bind(ticket_id);
+                push_guards(self, &mut query, actor);
+            }
+            None => {
+                query.push(" WHERE id IN (SELECT id FROM tickets WHERE TRUE");
+                push_guards(self, &mut query, actor);
+                if matches!(self, Transition::Claim { .. }) {
+                    query.push(" ORDER BY created_at ASC LIMIT 1");
+                }
+                query.push(" FOR UPDATE SKIP LOCKED)");
+            }
+        }
+
+        query.push(" RETURNING id");
+        query
+    }
+
+    /// Columns set alongside the status
+    fn push_assignments<'q>(&'q self, query: &mut QueryBuilder<'q, Postgres>, actor: &TicketActor<'_>) {
+        let bot_id = match actor {
+            TicketActor::Bot(bot) => Some(bot.id),
+            _ => None,
+        };
+
+        match self {
+            Transition::Claim { .. } => {
+                query.push(", claimed_by_bot_id = ").push_bind(bot_id);
+            }
+            Transition::Verify { .. } => {
+                query.push(", verified_by_bot_id = ").push_bind(bot_id);
+            }
+            Transition::Unclaim { .. } => {
+                query.push(", unclaimed_by_bot_id = ").push_bind(bot_id);
+            }
+            Transition::MarkSold { .. } => {
+                query.push(", sold_by_bot_id = ").push_bind(bot_id);
+            }
+            Transition::Cancel { price, .. } => {
+                query.push(", price = COALESCE(").push_bind(*price).push(", price)");
+            }
+            Transition::Reserve { .. } => {
+                let buyer_id = match actor {
+                    TicketActor::Buyer(buyer_id) => Some(*buyer_id),
+                    _ => None,
+                };
+                query.push(", reserved_at = NOW(), reserved_by = ").push_bind(buyer_id);
+                query.push(", price_at_reservation = price");
+            }
+            Transition::ReleaseReservation { .. }
+            | Transition::CancelReservation { .. }
+            | Transition::ExpireReservation { .. } => {
+                query.push(", reserved_at = NULL, reserved_by = NULL, price_at_reservation = NULL");
+            }
+            Transition::VerificationTimeout { .. }
+            | Transition::ExpireListing
+            | Transition::Pay { .. }
+            | Transition::RequestRefund { .. }
+            | Transition::RefundOverdue { .. }
+            | Transition::Refund { .. } => {}
+        }
+    }
+}
+
+/// `AND status IN (...)` plus the transition's own guard
+fn push_guards<'q>(transition: &'q Transition<'_>, query: &mut QueryBuilder<'q, Postgres>, actor: &TicketActor<'_>) {
+    query.push(" AND status IN (");
+    let mut statuses = query.separated(", ");
+    for status in transition.kind().from() {
+        statuses.push_bind(*status);
+    }
+    query.push(")");
+
+    match transition {
+        Transition::Claim {
+            event_name,
+            seat_section,
+            seat_row,
+            seat_number,
+        } => {
+            query.push(" AND event_name = ").push_bind(*event_name);
+            query.push(" AND seat_section = ").push_bind(*seat_section);
+            query.push(" AND seat_row = ").push_bind(*seat_row);
+            query.push(" AND seat_number = ").push_bind(*seat_number);
+            query.push(" AND transfer_deadline > NOW()");
+        }
+        Transition::VerificationTimeout { timeout_minutes } => {
+            query
+                .push(" AND updated_at < NOW() - INTERVAL '1 minute' * ")
+                .push_bind(*timeout_minutes);
+        }
+        Transition::ExpireListing => {
+            query.push(" AND transfer_deadline <= NOW()");
+        }
+        Transition::Cancel { .. } => {
+            let seller_id = match actor {
+                TicketActor::Seller(seller_id) => Some(*seller_id),
+                _ => None,
+            };
+            query.push(" AND seller_id = ").push_bind(seller_id);
+        }
+        Transition::Reserve { expired_before, .. } => {
+            let buyer_id = match actor {
+                TicketActor::Buyer(buyer_id) => Some(*buyer_id),
+                _ => None,
+            };
+            query.push(" AND seller_id <> ").push_bind(buyer_id);
+            query
+                .push(" AND (status <> ")
+                .push_bind(TicketStatus::Reserved)
+                .push(" OR reserved_at < ")
+                .push_bind(*expired_before)
+                .push(")");
+        }
+        Transition::Pay {
+            buyer_id,
+            reserved_after,
+            amount,
+            ..
+        } => {
+            query.push(" AND reserved_by = ").push_bind(*buyer_id);
+            query.push(" AND reserved_at > ").push_bind(*reserved_after);
+            query.push(" AND price_at_reservation = ").push_bind(*amount);
+        }
+        Transition::ReleaseReservation {
+            buyer_id,
+            intent_created_at,
+            ..
+        } => {
+            // A newer reservation by the same buyer is left alone
+            query.push(" AND reserved_by = ").push_bind(*buyer_id);
+            query.push(" AND reserved_at <= ").push_bind(*intent_created_at);
+        }
+        Transition::CancelReservation { .. } => {
+            let buyer_id = match actor {
+                TicketActor::Buyer(buyer_id) => Some(*buyer_id),
+                _ => None,
+            };
+            query.push(" AND reserved_by = ").push_bind(buyer_id);
+        }
+        Transition::ExpireReservation { window_minutes } => {
+            query
+                .push(" AND reserved_at < NOW() - INTERVAL '1 minute' * ")
+                .push_bind(*window_minutes);
+        }
+        Transition::RefundOverdue { deadline_hours } => {
+            query
+                .push(" AND updated_at < NOW() - INTERVAL '1 hour' * ")
+                .push_bind(*deadline_hours);
+        }
+        Transition::Verify { .. }
+        | Transition::Unclaim { .. }
+        | Transition::MarkSold { .. }
+        | Transition::RequestRefund { .. }
+        | Transition::Refund { .. } => {}
+    }
+}
+
+/// Apply a transition on the caller's transaction, returning the ids of the tickets it changed
+///
+/// Fails with PermissionDenied if `actor` may not trigger the transition. An empty result
+/// means no ticket was in a `from` status and passed the guard.
+pub async fn apply_transition_on(
+    conn: &mut PgConnection,
+    actor: &TicketActor<'_>,
+    transition: &Transition<'_>,
+) -> Result<Vec<Uuid>> {
+    let kind = transition.kind();
+    let actor_kind = ActorKind::of(actor);
+
+    if !kind.allows(actor_kind) {
+        warn!("{:?} actor attempted ticket transition {:?}", actor_kind, kind);
+        return Err(AppError::PermissionDenied);
+    }
+
+    set_ticket_event_context(conn, actor, transition.reason()).await?;
+
+    let ticket_ids = transition
+        .query(actor)
+        .build_query_scalar::<Uuid>()
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(ticket_ids)
+}
+
+/// Apply a transition in its own transaction
+pub async fn apply_transition(
+    pool: &PgPool,
+    actor: &TicketActor<'_>,
+    transition: &Transition<'_>,
+) -> Result<Vec<Uuid>> {
+    let mut tx = pool.begin().await?;
+    let ticket_ids = apply_transition_on(&mut tx, actor, transition).await?;
+    tx.commit().await?;
+
+    Ok(ticket_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TicketStatus::*;
+
+    const ACTORS: [ActorKind; 5] = [
+        ActorKind::Seller,
+        ActorKind::Buyer,
+        ActorKind::Bot,
+        ActorKind::Admin,
+        ActorKind::System,
+    ];
+
+    const TRANSITIONS: [TransitionKind; 15] = [
+        TransitionKind::Claim,
+        TransitionKind::Verify,
+        TransitionKind::Unclaim,
+        TransitionKind::VerificationTimeout,
+        TransitionKind::ExpireListing,
+        TransitionKind::Cancel,
+        TransitionKind::Reserve,
+        TransitionKind::Pay,
+        TransitionKind::ReleaseReservation,
+        TransitionKind::CancelReservation,
+        TransitionKind::ExpireReservation,
+        TransitionKind::MarkSold,
+        TransitionKind::RequestRefund,
+        TransitionKind::RefundOverdue,
+        TransitionKind::Refund,
+    ];
+
+    const STATUSES: [TicketStatus; 9] = [
+        Unverified, Verifying, Verified, Reserved, Paid, Sold, Refunding, Refunded, Cancelled,
+    ];
+
+    /// The "State Transition Summary" table in TICKET_STATE_TRANSITIONS.md
+    const TABLE: [(TransitionKind, TicketStatus, Option<TicketStatus>, &[ActorKind]); 18] = [
+        (TransitionKind::Claim, Unverified, Some(Verifying), &[ActorKind::Bot]),
+        (TransitionKind::Verify, Verifying, Some(Verified), &[ActorKind::Bot]),
+        (TransitionKind::Unclaim, Verifying, Some(Unverified), &[ActorKind::Bot]),
+        (TransitionKind::VerificationTimeout, Verifying, Some(Unverified), &[ActorKind::System]),
+        (TransitionKind::ExpireListing, Unverified, None, &[ActorKind::System]),
+        (TransitionKind::Cancel, Unverified, Some(Cancelled), &[ActorKind::Seller]),
+        (TransitionKind::Cancel, Verified, Some(Cancelled), &[ActorKind::Seller]),
+        (TransitionKind::Reserve, Verified, Some(Reserved), &[ActorKind::Buyer]),
+        (TransitionKind::Reserve, Reserved, Some(Reserved), &[ActorKind::Buyer]),
+        (TransitionKind::Pay, Reserved, Some(Paid), &[ActorKind::System]),
+        (TransitionKind::ReleaseReservation, Reserved, Some(Verified), &[ActorKind::System]),
+        (TransitionKind::CancelReservation, Reserved, Some(Verified), &[ActorKind::Buyer]),
+        (TransitionKind::ExpireReservation, Reserved, Some(Verified), &[ActorKind::System]),
+        (TransitionKind::MarkSold, Paid, Some(Sold), &[ActorKind::Bot]),
+        (
+            TransitionKind::RequestRefund,
+            Paid,
+            Some(Refunding),
+            &[ActorKind::Admin, ActorKind::Bot, ActorKind::System],
+        ),
+        (TransitionKind::RefundOverdue, Paid, Some(Refunding), &[ActorKind::System]),
+        (
+            TransitionKind::Refund,
+            Paid,
+            Some(Refunded),
+            &[ActorKind::Admin, ActorKind::Bot, ActorKind::System],
+        ),
+        (
+            TransitionKind::Refund,
+            Refunding,
+            Some(Refunded),
+            &[ActorKind::Admin, ActorKind::Bot, ActorKind::System],
+        ),
+    ];
+
+    /// Retries that leave the status unchanged
+    const RETRIES: [(TransitionKind, TicketStatus); 1] = [(TransitionKind::RequestRefund, Refunding)];
+
+    /// Whether any transition moves a ticket from `from` to `to` (None: deleted)
+    fn is_legal(from: TicketStatus, to: Option<TicketStatus>) -> bool {
+        TRANSITIONS
+            .iter()
+            .any(|kind| kind.from().contains(&from) && kind.to() == to)
+    }
+
+    fn in_table(kind: TransitionKind, from: TicketStatus, to: Option<TicketStatus>) -> bool {
+        TABLE.iter().any(|(k, f, t, _)| *k == kind && *f == from && *t == to)
+            || RETRIES.iter().any(|(k, f)| *k == kind && *f == from && to == Some(from))
+    }
+
+    #[test]
+    fn every_transition_matches_the_table() {
+        for kind in TRANSITIONS {
+            for from in STATUSES {
+                let expected = in_table(kind, from, kind.to());
+                assert_eq!(kind.from().contains(&from), expected, "{:?} from {:?}", kind, from);
+            }
+        }
+    }
+
+    #[test]
+    fn every_table_row_has_a_transition() {
+        for (kind, from, to, _) in TABLE {
+            assert!(kind.from().contains(&from), "{:?} should start from {:?}", kind, from);
+            assert_eq!(kind.to(), to, "{:?} should end in {:?}", kind, to);
+        }
+    }
+
+    #[test]
+    fn only_table_status_changes_are_legal() {
+        let targets = STATUSES.iter().copied().map(Some).chain([None]);
+
+        for from in STATUSES {
+            for to in targets.clone() {
+                let expected = TABLE.iter().any(|(_, f, t, _)| *f == from && *t == to)
+                    || RETRIES.iter().any(|(_, f)| *f == from && to == Some(from));
+                assert_eq!(
+                    is_legal(from, to),
+                    expected,
+                    "{:?} → {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_transition_allows_only_its_actors() {
+        for (kind, _, _, actors) in TABLE {
+            for actor in ACTORS {
+                assert_eq!(kind.allows(actor), actors.contains(&actor), "{:?} by {:?}", kind, actor);
+            }
+        }
+    }
+
+    #[test]
+    fn settled_tickets_are_final() {
+        for from in [Sold, Refunded, Cancelled] {
+            for kind in TRANSITIONS {
+                assert!(!kind.from().contains(&from), "{:?} leaves {:?}", kind, from);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_skips_verification_or_payment() {
+        assert!(!is_legal(Unverified, Some(Sold)));
+        assert!(!is_legal(Unverified, Some(Reserved)));
+        assert!(!is_legal(Verified, Some(Paid)));
+        assert!(!is_legal(Reserved, Some(Sold)));
+        assert!(!is_legal(Reserved, Some(Refunded)));
+    }
+
+    #[test]
+    fn batch_transitions_skip_locked_rows() {
+        let actor = TicketActor::System("test");
+        let batch = [
+            Transition::VerificationTimeout { timeout_minutes: 10 },
+            Transition::ExpireListing,
+            Transition::ExpireReservation { window_minutes: 7 },
+            Transition::RefundOverdue { deadline_hours: 48 },
+        ];
+
+        for transition in batch {
+            let sql = transition.query(&actor).into_sql();
+            assert!(sql.contains("FOR UPDATE SKIP LOCKED"), "{}", sql);
+        }
+
+        let sql = Transition::Refund { ticket_id: Uuid::nil() }.query(&actor).into_sql();
+        assert!(sql.contains("WHERE id = $"), "{}", sql);
+        assert!(!sql.contains("SKIP LOCKED"), "{}", sql);
+    }
+
+    #[test]
+    fn only_listing_expiry_deletes() {
+        let actor = TicketActor::System("test");
+        let sql = Transition::ExpireListing.query(&actor).into_sql();
+        assert!(sql.starts_with("DELETE FROM tickets"), "{}", sql);
+
+        for kind in TRANSITIONS {
+            assert_eq!(kind.to().is_none(), kind == TransitionKind::ExpireListing, "{:?}", kind);
+        }
+    }
+}
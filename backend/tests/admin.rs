@@ -0,0 +1,46 @@
+//! Roles and scoped admin API keys
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use serde_json::json;
+
+use common::TestApp;
+
+#[tokio::test]
+async fn scoped_keys_cannot_grant_roles_beyond_their_scopes() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let user = app.create_user(UserRole::User).await;
+
+    let (status, body) = app
+        .post(
+            "/api/admin/api-keys",
+            Some(&admin.token),
+            Some(json!({ "name": "user-admin", "scopes": ["users:manage"] })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "create api key: {}", body);
+    let key = body["key"].as_str().unwrap().to_string();
+
+    // Both roles carry permissions the key wasn't given
+    let path = format!("/api/admin/users/{}/role", user.id);
+    for role in ["admin", "moderator"] {
+        let (status, body) = app.patch(&path, Some(&key), Some(json!({ "role": role }))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}: {}", role, body);
+    }
+    let role: UserRole = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(role, UserRole::User);
+
+    let (status, body) = app.patch(&path, Some(&key), Some(json!({ "role": "user" }))).await;
+    assert_eq!(status, StatusCode::OK, "demote: {}", body);
+
+    let (status, body) = app.patch(&path, Some(&admin.token), Some(json!({ "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::OK, "promote: {}", body);
+    assert_eq!(body["role"], "Admin");
+}
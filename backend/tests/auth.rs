@@ -0,0 +1,273 @@
+//! Registration, email verification and sessions
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::TestApp;
+
+const RESEND_MESSAGE: &str = "If the account exists and is not yet verified, a new verification code has been sent.";
+
+/// Register a new account, returning its email and the verification code (DEV_MODE)
+async fn register(app: &TestApp) -> (String, String) {
+    let email = format!("{}@msu.edu", Uuid::new_v4().simple());
+    let (status, body) = app
+        .post(
+            "/api/auth/register",
+            None,
+            Some(json!({ "email": email, "password": "correct-horse-battery" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "register: {}", body);
+
+    let code = body["verification_code"].as_str().expect("code in DEV_MODE").to_string();
+    // Development mode only adds the code to the response, it is still mailed
+    let mail = app.mail_to(&email, 1).await;
+    assert_eq!(after(&mail[0].text_body, "Your verification code is: "), code);
+    (email, code)
+}
+
+/// Register and verify an account, returning its email and password
+async fn verified_account(app: &TestApp) -> (String, String) {
+    let (email, code) = register(app).await;
+    let (status, body) = verify(app, &email, &code).await;
+    assert_eq!(status, StatusCode::OK, "verify: {}", body);
+    (email, "correct-horse-battery".to_string())
+}
+
+/// Log in, returning the access token and refresh token
+async fn login(app: &TestApp, email: &str, password: &str) -> (String, String) {
+    let (status, body) = app
+        .post("/api/auth/login", None, Some(json!({ "email": email, "password": password })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "login: {}", body);
+    (
+        body["token"].as_str().unwrap().to_string(),
+        body["refresh_token"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> (StatusCode, Value) {
+    app.post("/api/auth/refresh", None, Some(json!({ "refresh_token": refresh_token })))
+        .await
+}
+
+/// Whether an access token is still accepted
+async fn authorized(app: &TestApp, token: &str) -> bool {
+    let (status, _) = app.get("/api/me/purchases", Some(token)).await;
+    status == StatusCode::OK
+}
+
+async fn verify(app: &TestApp, email: &str, code: &str) -> (StatusCode, Value) {
+    app.post("/api/auth/verify-email", None, Some(json!({ "email": email, "code": code })))
+        .await
+}
+
+async fn resend(app: &TestApp, email: &str) -> (StatusCode, Value) {
+    app.post("/api/auth/resend-verification", None, Some(json!({ "email": email })))
+        .await
+}
+
+/// Let the resend cooldown pass by backdating the last email
+async fn end_resend_cooldown(app: &TestApp, email: &str) {
+    sqlx::query("UPDATE users SET verification_code_sent_at = NOW() - INTERVAL '10 minutes' WHERE email = $1")
+        .bind(email)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn wrong_codes_lock_verification() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let (email, code) = register(&app).await;
+
+    // Real codes are never below 100000
+    for _ in 1..5 {
+        let (status, _) = verify(&app, &email, "000000").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, body) = verify(&app, &email, "000000").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "fifth wrong code: {}", body);
+
+    // The right code no longer helps, and no new one is sent while locked
+    let (status, _) = verify(&app, &email, &code).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    end_resend_cooldown(&app, &email).await;
+    let (status, body) = resend(&app, &email).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "message": RESEND_MESSAGE }));
+
+    // Once the lockout ends a new code can be requested and used
+    sqlx::query("UPDATE users SET verification_locked_until = NOW() - INTERVAL '1 second' WHERE email = $1")
+        .bind(&email)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let (status, _) = verify(&app, &email, &code).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "the old code was invalidated");
+
+    let (status, body) = resend(&app, &email).await;
+    assert_eq!(status, StatusCode::OK);
+    let new_code = body["verification_code"].as_str().unwrap();
+    let (status, body) = verify(&app, &email, new_code).await;
+    assert_eq!(status, StatusCode::OK, "verify: {}", body);
+}
+
+#[tokio::test]
+async fn resends_wait_for_the_cooldown() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let (email, code) = register(&app).await;
+
+    let (status, body) = resend(&app, &email).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "message": RESEND_MESSAGE }));
+
+    // Skipped within the cooldown; the next resend replaces the first code
+    end_resend_cooldown(&app, &email).await;
+    let (status, body) = resend(&app, &email).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["message"], RESEND_MESSAGE);
+    let new_code = body["verification_code"].as_str().unwrap();
+    assert_ne!(new_code, code);
+
+    let (status, _) = verify(&app, &email, &code).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = verify(&app, &email, new_code).await;
+    assert_eq!(status, StatusCode::OK, "verify: {}", body);
+}
+
+#[tokio::test]
+async fn resend_answers_the_same_for_every_email() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let verified = app.create_user(UserRole::User).await;
+    let (cooling_down, _) = register(&app).await;
+
+    for email in [format!("{}@msu.edu", Uuid::new_v4().simple()), verified.email, cooling_down] {
+        let (status, body) = resend(&app, &email).await;
+        assert_eq!(status, StatusCode::OK, "resend for {}", email);
+        assert_eq!(body, json!({ "message": RESEND_MESSAGE }), "resend for {}", email);
+    }
+}
+
+#[tokio::test]
+async fn refresh_tokens_work_once_and_reuse_revokes_the_session() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let (email, password) = verified_account(&app).await;
+    let (_, first_refresh) = login(&app, &email, &password).await;
+
+    let (status, body) = refresh(&app, &first_refresh).await;
+    assert_eq!(status, StatusCode::OK, "refresh: {}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+    let second_refresh = body["refresh_token"].as_str().unwrap().to_string();
+    assert_ne!(second_refresh, first_refresh);
+    assert!(authorized(&app, &token).await);
+
+    // Replaying the rotated-away token means it was copied: the whole session goes
+    let (status, _) = refresh(&app, &first_refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(&app, &second_refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(!authorized(&app, &token).await);
+}
+
+#[tokio::test]
+async fn logout_revokes_only_the_current_session() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let (email, password) = verified_account(&app).await;
+    let (token, refresh_token) = login(&app, &email, &password).await;
+    let (other_token, _) = login(&app, &email, &password).await;
+
+    let (status, body) = app.post("/api/auth/logout", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK, "logout: {}", body);
+    assert_eq!(body["sessions_revoked"], 1);
+
+    assert!(!authorized(&app, &token).await);
+    let (status, _) = refresh(&app, &refresh_token).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(authorized(&app, &other_token).await);
+}
+
+#[tokio::test]
+async fn password_reset_revokes_every_session() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let (email, password) = verified_account(&app).await;
+    let sessions = [login(&app, &email, &password).await, login(&app, &email, &password).await];
+
+    let (status, body) = app
+        .post("/api/auth/forgot-password", None, Some(json!({ "email": email })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "forgot-password: {}", body);
+    let reset_token = body["reset_token"].as_str().expect("token in DEV_MODE");
+    let (status, body) = app
+        .post(
+            "/api/auth/reset-password",
+            None,
+            Some(json!({ "token": reset_token, "new_password": "a-brand-new-passphrase" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "reset-password: {}", body);
+
+    for (token, refresh_token) in &sessions {
+        assert!(!authorized(&app, token).await);
+        let (status, _) = refresh(&app, refresh_token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let (status, _) = app
+        .post("/api/auth/login", None, Some(json!({ "email": email, "password": password })))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    login(&app, &email, "a-brand-new-passphrase").await;
+}
+
+/// The value following `label` in an email body, up to the next whitespace
+fn after<'a>(body: &'a str, label: &str) -> &'a str {
+    let start = body.find(label).unwrap_or_else(|| panic!("{:?} not in {:?}", label, body)) + label.len();
+    body[start..].split_whitespace().next().unwrap()
+}
+
+#[tokio::test]
+async fn codes_are_only_sent_by_email_outside_dev_mode() {
+    let Some(app) = TestApp::spawn_without_dev_mode().await else { return };
+    let email = format!("{}@msu.edu", Uuid::new_v4().simple());
+
+    let (status, body) = app
+        .post(
+            "/api/auth/register",
+            None,
+            Some(json!({ "email": email, "password": "correct-horse-battery" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "register: {}", body);
+    assert!(body["verification_code"].is_null());
+
+    let mail = app.mail_to(&email, 1).await;
+    assert_eq!(mail[0].subject, "Your verification code");
+    let code = after(&mail[0].text_body, "Your verification code is: ");
+    assert!(mail[0].html_body.contains(code));
+    let (status, body) = verify(&app, &email, code).await;
+    assert_eq!(status, StatusCode::OK, "verify: {}", body);
+
+    let (status, body) = app
+        .post("/api/auth/forgot-password", None, Some(json!({ "email": email })))
+        .await;
+    assert_eq!(status, StatusCode::OK, "forgot-password: {}", body);
+    assert!(body["reset_token"].is_null());
+
+    let mail = app.mail_to(&email, 2).await;
+    assert_eq!(mail[1].subject, "Reset your password");
+    let reset_token = after(&mail[1].text_body, "?token=");
+    let (status, body) = app
+        .post(
+            "/api/auth/reset-password",
+            None,
+            Some(json!({ "token": reset_token, "new_password": "a-brand-new-passphrase" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "reset-password: {}", body);
+    login(&app, &email, "a-brand-new-passphrase").await;
+}
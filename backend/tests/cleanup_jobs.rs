@@ -0,0 +1,291 @@
+//! Background cleanup jobs in `utils::cleanup`
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use backend::utils::cleanup::{
+    cleanup_expired_reservations, cleanup_expired_unverified, cleanup_password_reset_tokens,
+    cleanup_sessions, cleanup_stuck_verifying, cleanup_undelivered_paid,
+};
+use backend::utils::payment_gateway::MemoryPaymentGateway;
+use backend::utils::refunds::process_pending_refunds;
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use common::{payment_intent_object, StripeStub, TestApp};
+
+#[tokio::test]
+async fn expired_unverified_listings_are_removed() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let game = app.create_game(&admin).await;
+    let expired = app.list_ticket(&seller, game.0, "1", 1000).await;
+    let live = app.list_ticket(&seller, game.0, "2", 1000).await;
+
+    sqlx::query("UPDATE tickets SET transfer_deadline = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(expired)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    assert_eq!(cleanup_expired_unverified(&app.pool).await.unwrap(), 1);
+    assert_eq!(app.ticket_status(expired).await, None);
+    assert_eq!(app.ticket_status(live).await.as_deref(), Some("unverified"));
+
+    let events = app.ticket_events(expired).await;
+    assert_eq!(events.last().unwrap().1, None);
+}
+
+#[tokio::test]
+async fn stuck_verifications_are_reset() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.list_ticket(&seller, game.0, "3", 1000).await;
+
+    let (status, body) = app
+        .post(
+            "/api/tickets/claim",
+            Some(&bot),
+            Some(json!({ "event_name": game.1, "seat_section": "101", "seat_row": "A", "seat_number": "3" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "claim: {}", body);
+
+    assert_eq!(cleanup_stuck_verifying(&app.pool, 60).await.unwrap(), 0);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verifying"));
+
+    assert_eq!(cleanup_stuck_verifying(&app.pool, 0).await.unwrap(), 1);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("unverified"));
+}
+
+#[tokio::test]
+async fn expired_reservations_are_released() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "4", 1000).await;
+
+    let (status, _) = app
+        .post(&format!("/api/tickets/{}/reserve", ticket_id), Some(&buyer.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(cleanup_expired_reservations(&app.pool, 7).await.unwrap(), 0);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("reserved"));
+
+    assert_eq!(cleanup_expired_reservations(&app.pool, 0).await.unwrap(), 1);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verified"));
+
+    let reserved_by: Option<uuid::Uuid> = sqlx::query_scalar("SELECT reserved_by FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(reserved_by, None);
+}
+
+#[tokio::test]
+async fn undelivered_paid_tickets_are_refunded() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "5", 1800).await;
+
+    let path = format!("/api/tickets/{}", ticket_id);
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let pi = StripeStub::get().payment_intent_for_ticket(ticket_id).unwrap();
+    let pi_id = pi["id"].as_str().unwrap();
+    let authorized = payment_intent_object(pi_id, 1800, "requires_capture", pi["metadata"].clone());
+    let (status, _) = app.webhook("payment_intent.amount_capturable_updated", authorized).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+
+    assert_eq!(cleanup_undelivered_paid(&app.pool, 24).await.unwrap(), 0);
+    assert_eq!(cleanup_undelivered_paid(&app.pool, 0).await.unwrap(), 1);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunding"));
+
+    assert_eq!(process_pending_refunds(&app.pool, app.payments.as_ref()).await.unwrap(), 1);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunded"));
+
+    let refunds: Vec<_> = StripeStub::get()
+        .requests_for(pi_id)
+        .into_iter()
+        .filter(|r| r.path == "/v1/refunds")
+        .collect();
+    assert_eq!(refunds.len(), 1);
+}
+
+/// A paid ticket moved to 'refunding' whose intent is recorded with `intent_status`
+async fn refunding_ticket(app: &TestApp, intent_status: &str) -> Uuid {
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "6", 1500).await;
+
+    let path = format!("/api/tickets/{}", ticket_id);
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    sqlx::query("UPDATE payment_intents SET status = $2::payment_intent_status WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .bind(intent_status)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE tickets SET status = 'refunding' WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    ticket_id
+}
+
+async fn refund_state(app: &TestApp, ticket_id: Uuid) -> (i32, Option<String>, bool) {
+    sqlx::query_as("SELECT refund_attempts, refund_last_error, refund_failed_at IS NOT NULL FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn failed_refunds_back_off_then_give_up() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    // Recorded as captured, but the gateway only authorized it, so every refund fails
+    let ticket_id = refunding_ticket(&app, "captured").await;
+
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 1);
+    let (attempts, last_error, failed) = refund_state(&app, ticket_id).await;
+    assert_eq!(attempts, 1);
+    assert!(last_error.unwrap().contains("has not been captured"));
+    assert!(!failed);
+
+    // Not due again until the backoff has passed
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 0);
+
+    for _ in 1..5 {
+        sqlx::query("UPDATE tickets SET refund_next_attempt_at = NOW() WHERE id = $1")
+            .bind(ticket_id)
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 1);
+    }
+
+    let (attempts, _, failed) = refund_state(&app, ticket_id).await;
+    assert_eq!(attempts, 5);
+    assert!(failed);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunding"));
+
+    sqlx::query("UPDATE tickets SET refund_next_attempt_at = NOW() WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 0);
+    assert!(memory.refunded().is_empty());
+}
+
+#[tokio::test]
+async fn refunds_requested_through_the_api_are_leased_from_the_job() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    // Recorded as captured, but the gateway only authorized it, so the refund fails
+    let ticket_id = refunding_ticket(&app, "captured").await;
+    sqlx::query("UPDATE tickets SET status = 'paid' WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, _) = app
+        .post(&format!("/api/tickets/{}/refund", ticket_id), Some(&admin.token), None)
+        .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunding"));
+
+    // The request's own attempt counts, and the job waits out the backoff after it
+    let (attempts, _, failed) = refund_state(&app, ticket_id).await;
+    assert_eq!(attempts, 1);
+    assert!(!failed);
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 0);
+
+    sqlx::query("UPDATE tickets SET refund_next_attempt_at = NOW() WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 1);
+    let (attempts, _, _) = refund_state(&app, ticket_id).await;
+    assert_eq!(attempts, 2);
+}
+
+#[tokio::test]
+async fn disputed_refunds_are_left_for_manual_handling() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    let ticket_id = refunding_ticket(&app, "disputed").await;
+
+    assert_eq!(process_pending_refunds(&app.pool, memory.as_ref()).await.unwrap(), 1);
+    let (attempts, last_error, failed) = refund_state(&app, ticket_id).await;
+    assert_eq!(attempts, 1);
+    assert!(last_error.unwrap().contains("disputed"));
+    assert!(failed);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunding"));
+    assert!(memory.refunded().is_empty());
+}
+
+#[tokio::test]
+async fn stale_sessions_and_reset_tokens_are_removed() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let user = app.create_user(UserRole::User).await;
+    let other = app.create_user(UserRole::User).await;
+
+    sqlx::query("UPDATE sessions SET expires_at = NOW() - INTERVAL '2 days' WHERE user_id = $1")
+        .bind(user.id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, repeat('a', 64), NOW() - INTERVAL '2 days'),
+               ($1, repeat('b', 64), NOW() + INTERVAL '1 hour')
+        "#,
+    )
+    .bind(user.id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    assert_eq!(cleanup_sessions(&app.pool).await.unwrap(), 1);
+    assert_eq!(cleanup_password_reset_tokens(&app.pool).await.unwrap(), 1);
+
+    let (status, _) = app.get("/api/tickets/my-listings", Some(&other.token)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get("/api/tickets/my-listings", Some(&user.token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,689 @@
+//! Integration test harness
+//!
+//! Each `TestApp` gets its own freshly migrated database on the Postgres server at
+//! `TEST_DATABASE_URL` (or `DATABASE_URL`), dropped again when the app goes out of
+//! scope. Tests are skipped when neither is set. By default payments go through the
+//! real Stripe gateway to `StripeStub`, a local HTTP server shared by every test in
+//! the binary; `spawn_with_payments` swaps in another gateway. Mail is kept in a
+//! `MemoryMailer` per app, and development mode is on unless the app is spawned
+//! with `spawn_without_dev_mode`.
+
+#![allow(dead_code)]
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::State,
+    http::{HeaderMap, Method, Request, StatusCode, Uri},
+    response::{IntoResponse, Json},
+    Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{
+    pool::PoolOptions,
+    postgres::{PgConnectOptions, PgConnection},
+    ConnectOptions, Connection, PgPool, Postgres,
+};
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    sync::{Arc, Mutex, Once, OnceLock},
+    time::Duration,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use backend::models::user::UserRole;
+use backend::state::AppState;
+use backend::utils::payment_gateway::PaymentGateway;
+use backend::utils::stripe::StripeGateway;
+use backend::utils::{
+    jwt::generate_token,
+    mailer::{DevMode, EmailMessage, MemoryMailer},
+    sessions::create_session,
+};
+
+pub const WEBHOOK_SECRET: &str = "whsec_integration_tests";
+
+static ENV: Once = Once::new();
+
+fn server_url() -> Option<String> {
+    env::var("TEST_DATABASE_URL")
+        .or_else(|_| env::var("DATABASE_URL"))
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+}
+
+/// Configure the process environment the backend reads its settings from
+fn init_env() {
+    ENV.call_once(|| {
+        let stripe = StripeStub::get();
+
+        env::set_var("JWT_SECRET", "integration-test-secret");
+        env::set_var("STRIPE_SECRET_KEY", "sk_test_integration");
+        env::set_var("STRIPE_WEBHOOK_SECRET", WEBHOOK_SECRET);
+        env::set_var("STRIPE_API_BASE_URL", &stripe.base_url);
+        env::set_var("STRIPE_CONNECT_REFRESH_URL", "http://localhost/refresh");
+        env::set_var("STRIPE_CONNECT_RETURN_URL", "http://localhost/return");
+        env::set_var("LISTING_CUTOFF_MINUTES", "60");
+        env::set_var("RATE_LIMIT_REQUESTS", "100000");
+        env::remove_var("BOOTSTRAP_ADMIN_EMAIL");
+    });
+}
+
+/// A user with a live session
+pub struct TestUser {
+    pub id: Uuid,
+    pub email: String,
+    pub token: String,
+}
+
+/// The router backed by a throwaway database
+pub struct TestApp {
+    pub pool: PgPool,
+    pub payments: Arc<dyn PaymentGateway>,
+    pub mailer: Arc<MemoryMailer>,
+    pub router: Router,
+    server_url: String,
+    database: String,
+}
+
+impl TestApp {
+    /// Create and migrate a new database and build the router on it
+    ///
+    /// Returns None (and the test should return early) if no Postgres server is configured.
+    pub async fn spawn() -> Option<TestApp> {
+        init_env();
+        let payments = StripeGateway::from_env().expect("Failed to create Stripe gateway");
+
+        Self::spawn_with_payments(Arc::new(payments)).await
+    }
+
+    /// Like `spawn`, with the given payment gateway in the app state
+    pub async fn spawn_with_payments(payments: Arc<dyn PaymentGateway>) -> Option<TestApp> {
+        Self::spawn_with(payments, DevMode(true)).await
+    }
+
+    /// Like `spawn`, without development mode, so secrets only go out by email
+    pub async fn spawn_without_dev_mode() -> Option<TestApp> {
+        init_env();
+        let payments = StripeGateway::from_env().expect("Failed to create Stripe gateway");
+
+        Self::spawn_with(Arc::new(payments), DevMode(false)).await
+    }
+
+    async fn spawn_with(payments: Arc<dyn PaymentGateway>, dev_mode: DevMode) -> Option<TestApp> {
+        let Some(server_url) = server_url() else {
+            eprintln!("TEST_DATABASE_URL/DATABASE_URL not set, skipping integration test");
+            return None;
+        };
+
+        init_env();
+
+        let database = format!("backend_test_{}", Uuid::new_v4().simple());
+        let mut admin = PgConnection::connect(&server_url)
+            .await
+            .expect("Failed to connect to the test Postgres server");
+        sqlx::query(&format!(r#"CREATE DATABASE "{}""#, database))
+            .execute(&mut admin)
+            .await
+            .expect("Failed to create test database");
+        admin.close().await.ok();
+
+        let options = PgConnectOptions::from_str(&server_url)
+            .expect("Invalid test database URL")
+            .database(&database)
+            .disable_statement_logging();
+        let pool = PoolOptions::<Postgres>::new()
+            .max_connections(20)
+            .connect_with(options)
+            .await
+            .expect("Failed to connect to test database");
+
+        backend::db::run_migrations(&pool)
+            .await
+            .expect("Failed to migrate test database");
+
+        let mailer = Arc::new(MemoryMailer::default());
+        let router = backend::routes::create_router(AppState {
+            pool: pool.clone(),
+            payments: payments.clone(),
+            mailer: mailer.clone(),
+            dev_mode,
+        });
+
+        Some(TestApp {
+            pool,
+            payments,
+            mailer,
+            router,
+            server_url,
+            database,
+        })
+    }
+
+    /// Send a request through the router, returning the status and JSON body (Null if empty)
+    pub async fn request(&self, method: Method, path: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        let mut builder = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {}", token));
+        }
+
+        let request = match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .expect("Failed to build request");
+
+        send(&self.router, request).await
+    }
+
+    pub async fn get(&self, path: &str, token: Option<&str>) -> (StatusCode, Value) {
+        self.request(Method::GET, path, token, None).await
+    }
+
+    pub async fn post(&self, path: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        self.request(Method::POST, path, token, body).await
+    }
+
+    pub async fn patch(&self, path: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+        self.request(Method::PATCH, path, token, body).await
+    }
+
+    /// Deliver a signed Stripe webhook event
+    pub async fn webhook(&self, event_type: &str, object: Value) -> (StatusCode, Value) {
+        let payload = webhook_event(event_type, object).to_string();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/webhooks/stripe")
+            .header("content-type", "application/json")
+            .header("stripe-signature", sign_webhook(&payload, Utc::now().timestamp()))
+            .body(Body::from(payload))
+            .expect("Failed to build webhook request");
+
+        send(&self.router, request).await
+    }
+
+    /// Insert a verified user with the given role and start a session for them
+    pub async fn create_user(&self, role: UserRole) -> TestUser {
+        let email = format!("{}@msu.edu", Uuid::new_v4().simple());
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO users (email, password_hash, email_verified, role)
+            VALUES ($1, 'not-a-bcrypt-hash', TRUE, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(&email)
+        .bind(role)
+        .fetch_one(&self.pool)
+        .await
+        .expect("Failed to insert user");
+
+        let (session_id, _) = create_session(&self.pool, id)
+            .await
+            .expect("Failed to create session");
+        let token = generate_token(&id.to_string(), &email, role, session_id).expect("Failed to sign token");
+
+        TestUser { id, email, token }
+    }
+
+    /// Create a bot credential through the admin API, returning its key
+    pub async fn create_bot(&self, admin: &TestUser, scopes: &[&str]) -> String {
+        let (status, body) = self
+            .post(
+                "/api/admin/bot-credentials",
+                Some(&admin.token),
+                Some(json!({ "name": format!("bot-{}", Uuid::new_v4().simple()), "scopes": scopes })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "create bot credential: {}", body);
+
+        body["key"].as_str().expect("bot key").to_string()
+    }
+
+    /// Create a game a week out through the admin API
+    pub async fn create_game(&self, admin: &TestUser) -> (Uuid, String) {
+        let name = format!("Game {}", Uuid::new_v4().simple());
+        let (status, body) = self
+            .post(
+                "/api/games",
+                Some(&admin.token),
+                Some(json!({
+                    "sport_type": "football",
+                    "name": name,
+                    "game_time": Utc::now() + chrono::Duration::days(7),
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "create game: {}", body);
+
+        (uuid(&body["id"]), name)
+    }
+
+    /// List a ticket for a seat through the API
+    pub async fn list_ticket(&self, seller: &TestUser, game_id: Uuid, seat_number: &str, price: i32) -> Uuid {
+        let (status, body) = self
+            .post(
+                "/api/tickets",
+                Some(&seller.token),
+                Some(json!({
+                    "game_id": game_id,
+                    "level": "lower",
+                    "seat_section": "101",
+                    "seat_row": "A",
+                    "seat_number": seat_number,
+                    "price": price,
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "create ticket: {}", body);
+
+        uuid(&body["id"])
+    }
+
+    /// List a ticket and have the bot claim and verify it
+    pub async fn verified_ticket(
+        &self,
+        seller: &TestUser,
+        bot_key: &str,
+        game: &(Uuid, String),
+        seat_number: &str,
+        price: i32,
+    ) -> Uuid {
+        let ticket_id = self.list_ticket(seller, game.0, seat_number, price).await;
+
+        let (status, body) = self
+            .post(
+                "/api/tickets/claim",
+                Some(bot_key),
+                Some(json!({
+                    "event_name": game.1,
+                    "seat_section": "101",
+                    "seat_row": "A",
+                    "seat_number": seat_number,
+                })),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "claim ticket: {}", body);
+
+        let (status, body) = self
+            .patch(&format!("/api/tickets/{}/verify", ticket_id), Some(bot_key), None)
+            .await;
+        assert_eq!(status, StatusCode::OK, "verify ticket: {}", body);
+
+        ticket_id
+    }
+
+    pub async fn ticket_status(&self, ticket_id: Uuid) -> Option<String> {
+        sqlx::query_scalar::<_, String>("SELECT status::TEXT FROM tickets WHERE id = $1")
+            .bind(ticket_id)
+            .fetch_optional(&self.pool)
+            .await
+            .expect("Failed to read ticket status")
+    }
+
+    /// (from, to, reason) of every recorded status change of a ticket, oldest first
+    pub async fn ticket_events(&self, ticket_id: Uuid) -> Vec<(Option<String>, Option<String>, Option<String>)> {
+        sqlx::query_as(
+            r#"
+            SELECT from_status::TEXT, to_status::TEXT, reason
+            FROM ticket_events
+            WHERE ticket_id = $1
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(ticket_id)
+        .fetch_all(&self.pool)
+        .await
+        .expect("Failed to read ticket events")
+    }
+
+    /// Messages sent to `to` so far, waiting briefly for background sends to land
+    pub async fn mail_to(&self, to: &str, count: usize) -> Vec<EmailMessage> {
+        for _ in 0..100 {
+            let sent: Vec<_> = self.mailer.sent().into_iter().filter(|m| m.to == to).collect();
+            if sent.len() >= count {
+                return sent;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("expected {} email(s) to {}", count, to);
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let server_url = self.server_url.clone();
+        let database = self.database.clone();
+
+        // Drop runs inside the test's runtime, so the database is dropped from a
+        // separate thread with its own runtime
+        let dropped = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build runtime")
+                .block_on(async move {
+                    let mut admin = PgConnection::connect(&server_url).await?;
+                    sqlx::query(&format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, database))
+                        .execute(&mut admin)
+                        .await?;
+                    admin.close().await
+                })
+        })
+        .join();
+
+        if !matches!(dropped, Ok(Ok(()))) {
+            eprintln!("Failed to drop test database {}", self.database);
+        }
+    }
+}
+
+async fn send(router: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = router.clone().oneshot(request).await.expect("Router failed");
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("Failed to read response body");
+
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into()))
+    };
+
+    (status, body)
+}
+
+pub fn uuid(value: &Value) -> Uuid {
+    value
+        .as_str()
+        .and_then(|v| Uuid::parse_str(v).ok())
+        .unwrap_or_else(|| panic!("Expected a UUID, got {}", value))
+}
+
+/// `Stripe-Signature` header value for a webhook payload sent at `timestamp`
+pub fn sign_webhook(payload: &str, timestamp: i64) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes()).expect("HMAC key");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    format!("t={},v1={}", timestamp, signature)
+}
+
+/// A Stripe event wrapping `object`, as delivered to the webhook endpoint
+pub fn webhook_event(event_type: &str, object: Value) -> Value {
+    json!({
+        "id": format!("evt_{}", Uuid::new_v4().simple()),
+        "object": "event",
+        "api_version": "2023-10-16",
+        "created": Utc::now().timestamp(),
+        "data": { "object": object },
+        "livemode": false,
+        "pending_webhooks": 1,
+        "request": null,
+        "type": event_type,
+    })
+}
+
+/// A PaymentIntent object as returned by the Stripe API
+pub fn payment_intent_object(id: &str, amount: i64, status: &str, metadata: Value) -> Value {
+    json!({
+        "id": id,
+        "object": "payment_intent",
+        "amount": amount,
+        "amount_capturable": if status == "requires_capture" { amount } else { 0 },
+        "amount_received": if status == "succeeded" { amount } else { 0 },
+        "capture_method": "manual",
+        "client_secret": format!("{}_secret_test", id),
+        "confirmation_method": "automatic",
+        "created": Utc::now().timestamp(),
+        "currency": "usd",
+        "livemode": false,
+        "metadata": metadata,
+        "payment_method_types": ["card"],
+        "status": status,
+    })
+}
+
+/// A request received by the Stripe stub
+#[derive(Debug, Clone)]
+pub struct StubRequest {
+    pub method: Method,
+    pub path: String,
+    pub form: HashMap<String, String>,
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Default)]
+struct StubState {
+    payment_intents: HashMap<String, Value>,
+    requests: Vec<StubRequest>,
+    idempotent_responses: HashMap<String, (StatusCode, Value)>,
+}
+
+/// Local stand-in for the Stripe API
+///
+/// Supports the calls the backend makes: payment intent create/retrieve/list/
+/// capture/cancel, refunds, transfers, connected accounts and account links. A
+/// repeated `Idempotency-Key` gets the first response back, as Stripe does.
+pub struct StripeStub {
+    pub base_url: String,
+    state: Arc<Mutex<StubState>>,
+}
+
+impl StripeStub {
+    /// The stub shared by every test in this binary, started on first use
+    pub fn get() -> &'static StripeStub {
+        static STUB: OnceLock<StripeStub> = OnceLock::new();
+        STUB.get_or_init(StripeStub::start)
+    }
+
+    fn start() -> StripeStub {
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind Stripe stub");
+        listener.set_nonblocking(true).expect("Failed to configure Stripe stub");
+        let base_url = format!("http://{}/", listener.local_addr().expect("Stripe stub address"));
+
+        // Its own runtime, as each test's runtime stops when the test ends
+        let app = Router::new().fallback(stub_handler).with_state(state.clone());
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build Stripe stub runtime")
+                .block_on(async move {
+                    let listener = tokio::net::TcpListener::from_std(listener).expect("Stripe stub listener");
+                    axum::serve(listener, app).await.expect("Stripe stub stopped");
+                });
+        });
+
+        StripeStub { base_url, state }
+    }
+
+    /// Requests whose path mentions `needle` (e.g. a payment intent id)
+    pub fn requests_for(&self, needle: &str) -> Vec<StubRequest> {
+        let state = self.state.lock().expect("stub lock poisoned");
+        state
+            .requests
+            .iter()
+            .filter(|r| r.path.contains(needle) || r.form.values().any(|v| v == needle))
+            .cloned()
+            .collect()
+    }
+
+    /// The payment intent the backend created for a ticket
+    pub fn payment_intent_for_ticket(&self, ticket_id: Uuid) -> Option<Value> {
+        let state = self.state.lock().expect("stub lock poisoned");
+        state
+            .payment_intents
+            .values()
+            .find(|pi| pi["metadata"]["ticket_id"] == ticket_id.to_string())
+            .cloned()
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn parse_form(body: &str) -> HashMap<String, String> {
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+async fn stub_handler(
+    State(state): State<Arc<Mutex<StubState>>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let path = uri.path().to_string();
+    let form = parse_form(&body);
+    let idempotency_key = headers
+        .get("idempotency-key")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let now = Utc::now().timestamp();
+    let mut state = state.lock().expect("stub lock poisoned");
+
+    state.requests.push(StubRequest {
+        method: method.clone(),
+        path: path.clone(),
+        form: form.clone(),
+        idempotency_key: idempotency_key.clone(),
+    });
+
+    if let Some((status, body)) = idempotency_key
+        .as_ref()
+        .and_then(|key| state.idempotent_responses.get(key))
+    {
+        return (*status, Json(body.clone()));
+    }
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let response = match (method.as_str(), segments.as_slice()) {
+        ("POST", ["v1", "payment_intents"]) => {
+            let id = format!("pi_{}", Uuid::new_v4().simple());
+            let amount = form.get("amount").and_then(|a| a.parse().ok()).unwrap_or(0);
+            let metadata: serde_json::Map<String, Value> = form
+                .iter()
+                .filter_map(|(k, v)| {
+                    k.strip_prefix("metadata[")
+                        .and_then(|k| k.strip_suffix(']'))
+                        .map(|k| (k.to_string(), Value::String(v.clone())))
+                })
+                .collect();
+            let pi = payment_intent_object(&id, amount, "requires_payment_method", Value::Object(metadata));
+            state.payment_intents.insert(id, pi.clone());
+            Some(pi)
+        }
+        ("GET", ["v1", "payment_intents"]) => {
+            let data: Vec<Value> = state.payment_intents.values().cloned().collect();
+            Some(json!({ "object": "list", "data": data, "has_more": false, "url": "/v1/payment_intents" }))
+        }
+        ("GET", ["v1", "payment_intents", id]) => state.payment_intents.get(*id).cloned(),
+        ("POST", ["v1", "payment_intents", id, action @ ("capture" | "cancel")]) => {
+            let status = if *action == "capture" { "succeeded" } else { "canceled" };
+            let pi = state
+                .payment_intents
+                .entry(id.to_string())
+                .or_insert_with(|| payment_intent_object(id, 0, status, json!({})));
+            pi["status"] = json!(status);
+            if *action == "capture" {
+                pi["latest_charge"] = json!(format!("ch_{}", Uuid::new_v4().simple()));
+            }
+            Some(pi.clone())
+        }
+        ("POST", ["v1", "refunds"]) => Some(json!({
+            "id": format!("re_{}", Uuid::new_v4().simple()),
+            "object": "refund",
+            "amount": 0,
+            "created": now,
+            "currency": "usd",
+            "payment_intent": form.get("payment_intent"),
+            "status": "succeeded",
+        })),
+        ("POST", ["v1", "transfers"]) => {
+            let id = format!("tr_{}", Uuid::new_v4().simple());
+            Some(json!({
+                "id": id,
+                "object": "transfer",
+                "amount": form.get("amount").and_then(|a| a.parse::<i64>().ok()).unwrap_or(0),
+                "amount_reversed": 0,
+                "created": now,
+                "currency": form.get("currency").cloned().unwrap_or_else(|| "usd".to_string()),
+                "destination": form.get("destination"),
+                "livemode": false,
+                "metadata": {},
+                "reversals": { "object": "list", "data": [], "has_more": false, "url": format!("/v1/transfers/{}/reversals", id) },
+                "reversed": false,
+                "transfer_group": form.get("transfer_group"),
+            }))
+        }
+        ("POST", ["v1", "accounts"]) => Some(json!({
+            "id": format!("acct_{}", Uuid::new_v4().simple()),
+            "object": "account",
+        })),
+        ("POST", ["v1", "account_links"]) => Some(json!({
+            "object": "account_link",
+            "created": now,
+            "expires_at": now + 300,
+            "url": "https://connect.stripe.com/setup/test",
+        })),
+        _ => None,
+    };
+
+    let (status, body) = match response {
+        Some(body) => (StatusCode::OK, body),
+        None => (
+            StatusCode::NOT_FOUND,
+            json!({ "error": { "type": "invalid_request_error", "message": format!("No stub for {} {}", method, path) } }),
+        ),
+    };
+
+    if let Some(key) = idempotency_key {
+        state.idempotent_responses.insert(key, (status, body.clone()));
+    }
+
+    (status, Json(body))
+}
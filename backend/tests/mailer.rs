@@ -0,0 +1,59 @@
+//! Background email delivery in `utils::mailer`
+
+use async_trait::async_trait;
+use backend::error::{AppError, Result};
+use backend::utils::mailer::{send_in_background, verification_code_email, EmailMessage, Mailer, MemoryMailer};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Fails the first `failures` sends, then delivers to the inner memory mailer
+struct FlakyMailer {
+    failures: u32,
+    attempts: AtomicU32,
+    inner: MemoryMailer,
+}
+
+impl FlakyMailer {
+    fn new(failures: u32) -> Arc<Self> {
+        Arc::new(FlakyMailer {
+            failures,
+            attempts: AtomicU32::new(0),
+            inner: MemoryMailer::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FlakyMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<()> {
+        if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err(AppError::Internal(anyhow::anyhow!("relay unavailable")));
+        }
+        self.inner.send(message).await
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn failed_sends_are_retried() {
+    let mailer = FlakyMailer::new(2);
+    send_in_background(mailer.clone(), verification_code_email("buyer@msu.edu", "123456"));
+
+    // Backs off 2s then 4s between attempts
+    tokio::time::sleep(Duration::from_secs(7)).await;
+    assert_eq!(mailer.attempts.load(Ordering::SeqCst), 3);
+    let sent = mailer.inner.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "buyer@msu.edu");
+    assert!(sent[0].text_body.contains("123456"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn sends_are_dropped_after_the_last_attempt() {
+    let mailer = FlakyMailer::new(u32::MAX);
+    send_in_background(mailer.clone(), verification_code_email("buyer@msu.edu", "123456"));
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(mailer.attempts.load(Ordering::SeqCst), 3);
+    assert!(mailer.inner.sent().is_empty());
+}
@@ -0,0 +1,147 @@
+//! Seller payouts in `utils::payouts`
+
+mod common;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use backend::error::Result;
+use backend::models::user::UserRole;
+use backend::utils::payment_gateway::{
+    CheckoutPaymentIntent, MemoryPaymentGateway, PaymentGateway, PaymentIntentSnapshot,
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use uuid::Uuid;
+
+use common::{payment_intent_object, TestApp};
+
+/// Holds the payout (as a dispute would) while its transfer is in flight
+#[derive(Default)]
+struct DisputedDuringTransfer {
+    inner: MemoryPaymentGateway,
+    pool: OnceLock<PgPool>,
+}
+
+#[async_trait]
+impl PaymentGateway for DisputedDuringTransfer {
+    async fn create_payment_intent(
+        &self,
+        amount: i32,
+        ticket_id: Uuid,
+        buyer_id: Uuid,
+        reserved_at: DateTime<Utc>,
+    ) -> Result<CheckoutPaymentIntent> {
+        self.inner.create_payment_intent(amount, ticket_id, buyer_id, reserved_at).await
+    }
+
+    async fn retrieve_payment_intent(&self, payment_intent_id: &str) -> Result<PaymentIntentSnapshot> {
+        self.inner.retrieve_payment_intent(payment_intent_id).await
+    }
+
+    async fn capture_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        self.inner.capture_payment_intent(payment_intent_id).await
+    }
+
+    async fn cancel_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        self.inner.cancel_payment_intent(payment_intent_id).await
+    }
+
+    async fn refund_payment_intent(&self, payment_intent_id: &str) -> Result<()> {
+        self.inner.refund_payment_intent(payment_intent_id).await
+    }
+
+    async fn list_payment_intents_since(&self, since: DateTime<Utc>) -> Result<Vec<PaymentIntentSnapshot>> {
+        self.inner.list_payment_intents_since(since).await
+    }
+
+    async fn create_connected_account(&self, email: &str) -> Result<String> {
+        self.inner.create_connected_account(email).await
+    }
+
+    async fn create_account_onboarding_link(&self, account_id: &str) -> Result<String> {
+        self.inner.create_account_onboarding_link(account_id).await
+    }
+
+    async fn create_transfer(
+        &self,
+        idempotency_key: &str,
+        destination: &str,
+        amount: i32,
+        currency: &str,
+        transfer_group: &str,
+        source_transaction: &str,
+    ) -> Result<String> {
+        sqlx::query("UPDATE payouts SET status = 'held' WHERE ticket_id = $1::UUID")
+            .bind(transfer_group)
+            .execute(self.pool.get().expect("pool set after spawn"))
+            .await?;
+
+        self.inner
+            .create_transfer(idempotency_key, destination, amount, currency, transfer_group, source_transaction)
+            .await
+    }
+}
+
+#[tokio::test]
+async fn payouts_held_during_the_transfer_stay_held() {
+    let payments = Arc::new(DisputedDuringTransfer::default());
+    let Some(app) = TestApp::spawn_with_payments(payments.clone()).await else { return };
+    payments.pool.set(app.pool.clone()).unwrap();
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify", "tickets:mark_sold"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "1", 3000).await;
+    sqlx::query("UPDATE users SET stripe_account_id = 'acct_seller' WHERE id = $1")
+        .bind(seller.id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let path = format!("/api/tickets/{}", ticket_id);
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let pi_id: String = sqlx::query_scalar("SELECT id FROM payment_intents WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    let metadata = json!({ "ticket_id": ticket_id.to_string(), "buyer_id": buyer.id.to_string() });
+    let (status, _) = app
+        .webhook(
+            "payment_intent.amount_capturable_updated",
+            payment_intent_object(&pi_id, 3000, "requires_capture", metadata),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Marking sold starts the transfer in the background
+    let (status, body) = app.patch(&format!("{}/sold", path), Some(&bot), None).await;
+    assert_eq!(status, StatusCode::OK, "mark sold: {}", body);
+
+    let mut payout = (String::new(), None);
+    for _ in 0..100 {
+        payout = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT status::TEXT, stripe_transfer_id FROM payouts WHERE ticket_id = $1",
+        )
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        if payout.1.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    // The hold wins, and the transfer is kept on record for reversal
+    let (status, transfer_id) = payout;
+    assert_eq!(status, "held");
+    assert!(transfer_id.unwrap().starts_with("tr_mem_"));
+}
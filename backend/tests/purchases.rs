@@ -0,0 +1,98 @@
+//! Buyer's reservations and purchase history
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{payment_intent_object, uuid, StripeStub, TestApp};
+
+fn purchase(body: &Value, ticket_id: Uuid) -> &Value {
+    body["purchases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|purchase| uuid(&purchase["ticket_id"]) == ticket_id)
+        .unwrap_or_else(|| panic!("ticket {} missing from {}", ticket_id, body))
+}
+
+#[tokio::test]
+async fn purchases_show_reservation_payment_and_transfer() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let other = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify", "tickets:mark_sold"]).await;
+    let game = app.create_game(&admin).await;
+
+    let held = app.verified_ticket(&seller, &bot, &game, "1", 1200).await;
+    let bought = app.verified_ticket(&seller, &bot, &game, "2", 3400).await;
+    let lapsed = app.verified_ticket(&seller, &bot, &game, "3", 900).await;
+    let someone_elses = app.verified_ticket(&seller, &bot, &game, "4", 900).await;
+
+    for ticket_id in [held, bought, lapsed] {
+        let (status, _) = app
+            .post(&format!("/api/tickets/{}/reserve", ticket_id), Some(&buyer.token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = app
+        .post(&format!("/api/tickets/{}/reserve", someone_elses), Some(&other.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for ticket_id in [held, bought] {
+        let (status, _) = app
+            .post(&format!("/api/tickets/{}/checkout", ticket_id), Some(&buyer.token), None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let pi = StripeStub::get().payment_intent_for_ticket(bought).unwrap();
+    let authorized = payment_intent_object(pi["id"].as_str().unwrap(), 3400, "requires_capture", pi["metadata"].clone());
+    let (status, _) = app.webhook("payment_intent.amount_capturable_updated", authorized).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.patch(&format!("/api/tickets/{}/sold", bought), Some(&bot), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    sqlx::query("UPDATE tickets SET reserved_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+        .bind(lapsed)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app.get("/api/me/purchases", Some(&buyer.token)).await;
+    assert_eq!(status, StatusCode::OK, "purchases: {}", body);
+    assert_eq!(body["purchases"].as_array().unwrap().len(), 2);
+
+    let reservation = purchase(&body, held);
+    assert_eq!(reservation["status"], "Reserved");
+    assert_eq!(reservation["price"], 1200);
+    assert_eq!(reservation["payment_status"], "Created");
+    let seconds_left = reservation["reservation_seconds_left"].as_i64().unwrap();
+    assert!(seconds_left > 0 && seconds_left <= 7 * 60, "{}", seconds_left);
+    assert!(reservation["reservation_expires_at"].is_string());
+    assert!(reservation["sold_at"].is_null());
+
+    let sale = purchase(&body, bought);
+    assert_eq!(sale["status"], "Sold");
+    assert_eq!(sale["payment_status"], "Captured");
+    assert!(sale["sold_at"].is_string());
+    assert!(sale["reservation_expires_at"].is_null());
+
+    let (_, body) = app.get("/api/me/purchases?status=sold", Some(&buyer.token)).await;
+    assert_eq!(body["purchases"].as_array().unwrap().len(), 1);
+    assert_eq!(uuid(&body["purchases"][0]["ticket_id"]), bought);
+
+    let (_, body) = app.get("/api/me/purchases", Some(&other.token)).await;
+    assert_eq!(body["purchases"].as_array().unwrap().len(), 1);
+    assert_eq!(uuid(&body["purchases"][0]["ticket_id"]), someone_elses);
+
+    let (status, _) = app.get("/api/me/purchases?status=verified", Some(&buyer.token)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app.get("/api/me/purchases", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
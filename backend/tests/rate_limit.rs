@@ -0,0 +1,139 @@
+//! Rate limiting layer in `utils::rate_limit`, on a bare router
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{HeaderMap, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use backend::models::user::UserRole;
+use backend::utils::jwt::generate_token;
+use backend::utils::rate_limit::RateLimitLayer;
+use std::{env, net::SocketAddr, sync::Mutex};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const LIMIT: u64 = 3;
+
+/// A router allowing `LIMIT` requests a minute per key
+///
+/// The layer reads its settings when built, so building is serialized to keep
+/// one test's `RATE_LIMIT_TRUST_PROXY` from leaking into another's.
+fn limited_router(trust_proxy: bool) -> Router {
+    static ENV: Mutex<()> = Mutex::new(());
+    let _env = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    env::set_var("JWT_SECRET", "rate-limit-test-secret");
+    env::set_var("RATE_LIMIT_TEST_REQUESTS", LIMIT.to_string());
+    env::set_var("RATE_LIMIT_TEST_WINDOW_SECONDS", "60");
+    env::set_var("RATE_LIMIT_TRUST_PROXY", trust_proxy.to_string());
+
+    Router::new()
+        .route("/", get(|| async { "ok" }))
+        .layer(RateLimitLayer::new("test"))
+}
+
+fn user_token() -> String {
+    let user_id = Uuid::new_v4();
+    generate_token(&user_id.to_string(), "limited@msu.edu", UserRole::User, Uuid::new_v4()).unwrap()
+}
+
+/// Send a request from `peer`, optionally as a user and through a proxy
+async fn send(router: &Router, peer: &str, token: Option<&str>, forwarded_for: Option<&str>) -> (StatusCode, HeaderMap) {
+    let mut request = Request::builder().uri("/");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    if let Some(forwarded_for) = forwarded_for {
+        request = request.header("x-forwarded-for", forwarded_for);
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(format!("{}:4000", peer).parse::<SocketAddr>().unwrap()));
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    (response.status(), response.headers().clone())
+}
+
+fn header(headers: &HeaderMap, name: &str) -> u64 {
+    headers[name].to_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn every_limited_response_carries_the_rate_limit_headers() {
+    let router = limited_router(false);
+
+    for remaining in (0..LIMIT).rev() {
+        let (status, headers) = send(&router, "10.0.0.1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header(&headers, "x-ratelimit-limit"), LIMIT);
+        assert_eq!(header(&headers, "x-ratelimit-remaining"), remaining);
+        // One request frees up every 60 / LIMIT seconds
+        assert_eq!(header(&headers, "x-ratelimit-reset"), (LIMIT - remaining) * 20);
+    }
+
+    let (status, headers) = send(&router, "10.0.0.1", None, None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, "x-ratelimit-limit"), LIMIT);
+    assert_eq!(header(&headers, "x-ratelimit-remaining"), 0);
+    let retry_after = header(&headers, "retry-after");
+    assert!((1..=20).contains(&retry_after), "retry-after: {}", retry_after);
+    assert_eq!(header(&headers, "x-ratelimit-reset"), retry_after + (LIMIT - 1) * 20);
+}
+
+#[tokio::test]
+async fn users_are_limited_separately_wherever_they_connect_from() {
+    let router = limited_router(false);
+    let (alice, bob) = (user_token(), user_token());
+
+    // Alice's quota follows her across addresses
+    for peer in ["10.0.1.1", "10.0.1.2", "10.0.1.3"] {
+        let (status, _) = send(&router, peer, Some(&alice), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = send(&router, "10.0.1.4", Some(&alice), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // ... and doesn't touch Bob's, or that of her address
+    let (status, _) = send(&router, "10.0.1.1", Some(&bob), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&router, "10.0.1.1", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn anonymous_callers_are_limited_by_peer_address_by_default() {
+    let router = limited_router(false);
+
+    for _ in 0..LIMIT {
+        let (status, _) = send(&router, "10.0.2.1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Without a trusted proxy, X-Forwarded-For is the caller's to forge
+    let (status, _) = send(&router, "10.0.2.1", None, Some("192.0.2.7")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = send(&router, "10.0.2.2", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn behind_a_trusted_proxy_the_last_forwarded_address_is_the_key() {
+    let router = limited_router(true);
+    let proxy = "10.0.3.1";
+
+    for _ in 0..LIMIT {
+        let (status, _) = send(&router, proxy, None, Some("192.0.2.10")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Entries before the proxy's own are forgeable, so they don't change the key
+    let (status, _) = send(&router, proxy, None, Some("198.51.100.1, 192.0.2.10")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other clients behind the same proxy keep their own quota
+    let (status, _) = send(&router, proxy, None, Some("192.0.2.11")).await;
+    assert_eq!(status, StatusCode::OK);
+}
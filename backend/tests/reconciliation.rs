@@ -0,0 +1,148 @@
+//! Stripe reconciliation against the in-memory payment gateway
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use backend::utils::payment_gateway::MemoryPaymentGateway;
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use common::{TestApp, TestUser};
+
+async fn run(app: &TestApp, admin: &TestUser, lookback_hours: i64) -> Value {
+    let (status, body) = app
+        .post(
+            &format!("/api/admin/reconciliation?lookback_hours={}", lookback_hours),
+            Some(&admin.token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "reconciliation: {}", body);
+    body
+}
+
+/// Open discrepancies as (payment intent, kind)
+async fn open_discrepancies(app: &TestApp, admin: &TestUser) -> Vec<(String, String)> {
+    let (status, body) = app.get("/api/admin/discrepancies", Some(&admin.token)).await;
+    assert_eq!(status, StatusCode::OK, "discrepancies: {}", body);
+    let mut open: Vec<(String, String)> = body["discrepancies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["payment_intent_id"].as_str().unwrap().to_string(), d["kind"].as_str().unwrap().to_string()))
+        .collect();
+    open.sort();
+    open
+}
+
+#[tokio::test]
+async fn mismatches_are_recorded_until_fixed() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "3", 2500).await;
+
+    let path = format!("/api/tickets/{}", ticket_id);
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let pi_id: String = sqlx::query_scalar("SELECT id FROM payment_intents WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    // The gateway authorized the intent but no webhook told us, and a row Stripe never saw
+    let missing_id = format!("pi_missing_{}", Uuid::new_v4().simple());
+    sqlx::query(
+        r#"
+        INSERT INTO payment_intents (id, ticket_id, buyer_id, amount, currency, status, created_at)
+        VALUES ($1, $2, $3, 2500, 'usd', 'created', NOW() - INTERVAL '1 hour')
+        "#,
+    )
+    .bind(&missing_id)
+    .bind(ticket_id)
+    .bind(buyer.id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let summary = run(&app, &admin, 48).await;
+    assert_eq!(summary["checked"], 2);
+    assert_eq!(summary["discrepancies"], 2);
+    assert_eq!(
+        open_discrepancies(&app, &admin).await,
+        vec![
+            (pi_id.clone(), "StatusMismatch".to_string()),
+            (missing_id.clone(), "MissingAtStripe".to_string()),
+        ]
+    );
+
+    // Seen again: still one open row each
+    let summary = run(&app, &admin, 48).await;
+    assert_eq!(summary["resolved"], 0);
+    assert_eq!(open_discrepancies(&app, &admin).await.len(), 2);
+
+    sqlx::query("UPDATE payment_intents SET status = 'capturable' WHERE id = $1")
+        .bind(&pi_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let summary = run(&app, &admin, 48).await;
+    assert_eq!(summary["discrepancies"], 1);
+    assert_eq!(summary["resolved"], 1);
+    assert_eq!(
+        open_discrepancies(&app, &admin).await,
+        vec![(missing_id, "MissingAtStripe".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn discrepancies_outside_the_window_expire() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+
+    // Left open by earlier runs, for intents no run lists any more
+    for (payment_intent_id, hours_ago) in [("pi_long_gone", 100), ("pi_still_scheduled", 10)] {
+        sqlx::query(
+            r#"
+            INSERT INTO stripe_discrepancies (payment_intent_id, kind, stripe_value, first_seen_at, last_seen_at)
+            VALUES ($1, 'missing_locally', 'succeeded', NOW() - INTERVAL '1 hour' * $2, NOW() - INTERVAL '1 hour' * $2)
+            "#,
+        )
+        .bind(payment_intent_id)
+        .bind(hours_ago)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    }
+
+    // A short manual run leaves what the scheduled 48 hour run still covers
+    let summary = run(&app, &admin, 1).await;
+    assert_eq!(summary["expired"], 1);
+    assert_eq!(
+        open_discrepancies(&app, &admin).await,
+        vec![("pi_still_scheduled".to_string(), "MissingLocally".to_string())]
+    );
+
+    let (_, body) = app
+        .get("/api/admin/discrepancies?include_resolved=true", Some(&admin.token))
+        .await;
+    let expired = body["discrepancies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["payment_intent_id"] == "pi_long_gone")
+        .unwrap();
+    assert_eq!(expired["expired"], true);
+    assert!(expired["resolved_at"].is_string());
+}
@@ -0,0 +1,212 @@
+//! Concurrent callers racing for the same ticket
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use futures::future::join_all;
+use serde_json::json;
+
+use common::{StripeStub, TestApp};
+
+const BUYERS: usize = 8;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_reservations_have_exactly_one_winner() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "1", 2000).await;
+
+    let mut buyers = Vec::new();
+    for _ in 0..BUYERS {
+        buyers.push(app.create_user(UserRole::User).await);
+    }
+
+    let path = format!("/api/tickets/{}/reserve", ticket_id);
+    let results = join_all(buyers.iter().map(|buyer| app.post(&path, Some(&buyer.token), None))).await;
+
+    let winners: Vec<_> = buyers
+        .iter()
+        .zip(&results)
+        .filter(|(_, (status, _))| *status == StatusCode::OK)
+        .map(|(buyer, _)| buyer.id)
+        .collect();
+    assert_eq!(winners.len(), 1, "results: {:?}", results);
+    assert!(results
+        .iter()
+        .all(|(status, _)| *status == StatusCode::OK || *status == StatusCode::CONFLICT));
+
+    let reserved_by: uuid::Uuid = sqlx::query_scalar("SELECT reserved_by FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(reserved_by, winners[0]);
+    assert_eq!(app.ticket_events(ticket_id).await.len(), 4);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn reservation_cap_holds_under_parallel_requests() {
+    // MAX_RESERVATIONS_PER_USER default
+    const CAP: usize = 3;
+
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let mut paths = Vec::new();
+    for seat in 0..BUYERS {
+        let ticket_id = app.verified_ticket(&seller, &bot, &game, &format!("{}", 10 + seat), 2000).await;
+        paths.push(format!("/api/tickets/{}/reserve", ticket_id));
+    }
+
+    let results = join_all(paths.iter().map(|path| app.post(path, Some(&buyer.token), None))).await;
+
+    let reserved = results.iter().filter(|(status, _)| *status == StatusCode::OK).count();
+    assert_eq!(reserved, CAP, "results: {:?}", results);
+    for (status, body) in results.iter().filter(|(status, _)| *status != StatusCode::OK) {
+        assert_eq!(*status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        assert_eq!(body["error"], format!("Maximum {} concurrent reservations allowed", CAP));
+    }
+
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tickets WHERE reserved_by = $1")
+        .bind(buyer.id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(held, CAP as i64);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_checkouts_share_one_payment_intent() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "4", 2000).await;
+
+    let (status, _) = app
+        .post(&format!("/api/tickets/{}/reserve", ticket_id), Some(&buyer.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let path = format!("/api/tickets/{}/checkout", ticket_id);
+    let results = join_all((0..BUYERS).map(|_| app.post(&path, Some(&buyer.token), None))).await;
+
+    assert!(results.iter().all(|(status, _)| *status == StatusCode::OK), "results: {:?}", results);
+    let client_secret = &results[0].1["client_secret"];
+    assert!(results.iter().all(|(_, body)| body["client_secret"] == *client_secret));
+
+    let intents: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, currency FROM payment_intents WHERE ticket_id = $1")
+            .bind(ticket_id)
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(intents.len(), 1, "intents: {:?}", intents);
+    assert_eq!(intents[0].1, "usd");
+
+    // Every create carried the reservation's key, so Stripe made a single intent
+    let creates: Vec<_> = StripeStub::get()
+        .requests_for(&ticket_id.to_string())
+        .into_iter()
+        .filter(|request| request.path == "/v1/payment_intents")
+        .collect();
+    assert!(!creates.is_empty());
+    assert!(creates[0].idempotency_key.is_some());
+    assert!(creates.iter().all(|request| request.idempotency_key == creates[0].idempotency_key));
+    let stripe_intent = StripeStub::get().payment_intent_for_ticket(ticket_id).unwrap();
+    assert_eq!(stripe_intent["id"], intents[0].0);
+}
+
+#[tokio::test]
+async fn sellers_cannot_reserve_their_own_tickets() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "3", 2000).await;
+
+    let (status, body) = app
+        .post(&format!("/api/tickets/{}/reserve", ticket_id), Some(&seller.token), None)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "reserve: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verified"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_claims_for_one_seat_have_exactly_one_winner() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.list_ticket(&seller, game.0, "7", 1000).await;
+
+    let mut bots = Vec::new();
+    for _ in 0..4 {
+        bots.push(app.create_bot(&admin, &["tickets:verify"]).await);
+    }
+
+    let claim = json!({
+        "event_name": game.1,
+        "seat_section": "101",
+        "seat_row": "A",
+        "seat_number": "7",
+    });
+    let results = join_all(
+        bots.iter()
+            .map(|bot| app.post("/api/tickets/claim", Some(bot), Some(claim.clone()))),
+    )
+    .await;
+
+    let claimed: Vec<_> = results
+        .iter()
+        .filter(|(status, _)| *status == StatusCode::OK)
+        .collect();
+    assert_eq!(claimed.len(), 1, "results: {:?}", results);
+    assert_eq!(claimed[0].1["ticket_id"], ticket_id.to_string());
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verifying"));
+}
+
+#[tokio::test]
+async fn expired_reservation_can_be_taken_over() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let first = app.create_user(UserRole::User).await;
+    let second = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "2", 2000).await;
+    let path = format!("/api/tickets/{}/reserve", ticket_id);
+
+    let (status, _) = app.post(&path, Some(&first.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post(&path, Some(&second.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    sqlx::query("UPDATE tickets SET reserved_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app.post(&path, Some(&second.token), None).await;
+    assert_eq!(status, StatusCode::OK, "takeover: {}", body);
+
+    let reserved_by: uuid::Uuid = sqlx::query_scalar("SELECT reserved_by FROM tickets WHERE id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(reserved_by, second.id);
+}
@@ -0,0 +1,428 @@
+//! End-to-end seller → bot → buyer → webhook flow
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use backend::models::user::UserRole;
+use backend::error::AppError;
+use backend::utils::payment_gateway::{MemoryPaymentGateway, PaymentGateway};
+use backend::utils::payouts::process_due_payouts;
+use backend::utils::ticket_events::TicketActor;
+use backend::utils::ticket_state::{apply_transition, Transition};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use common::{payment_intent_object, sign_webhook, webhook_event, StripeStub, TestApp};
+
+/// Reserve a ticket and check out, returning the payment intent the backend created
+async fn reserve_and_checkout(app: &TestApp, buyer: &common::TestUser, ticket_id: Uuid) -> Value {
+    let (status, body) = app
+        .post(&format!("/api/tickets/{}/reserve", ticket_id), Some(&buyer.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "reserve: {}", body);
+
+    let (status, body) = app
+        .post(&format!("/api/tickets/{}/checkout", ticket_id), Some(&buyer.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "checkout: {}", body);
+    assert!(body["client_secret"].as_str().is_some());
+
+    StripeStub::get()
+        .payment_intent_for_ticket(ticket_id)
+        .expect("checkout should create a payment intent")
+}
+
+/// The authorized intent as Stripe would send it in a webhook
+fn authorized(pi: &Value) -> Value {
+    payment_intent_object(
+        pi["id"].as_str().unwrap(),
+        pi["amount"].as_i64().unwrap(),
+        "requires_capture",
+        pi["metadata"].clone(),
+    )
+}
+
+async fn intent_status(app: &TestApp, payment_intent_id: &str) -> String {
+    sqlx::query_scalar::<_, String>("SELECT status::TEXT FROM payment_intents WHERE id = $1")
+        .bind(payment_intent_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn seller_to_buyer_happy_path() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify", "tickets:mark_sold"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "1", 2500).await;
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verified"));
+
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+    assert_eq!(pi["amount"], 2500);
+    assert_eq!(pi["metadata"]["buyer_id"], buyer.id.to_string());
+
+    let (status, body) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK, "webhook: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+
+    // The gatekeeper queued a capture and the first attempt went straight to Stripe
+    let captures = StripeStub::get().requests_for(&format!("{}/capture", pi_id));
+    assert_eq!(captures.len(), 1);
+    assert_eq!(intent_status(&app, pi_id).await, "captured");
+
+    let (status, body) = app
+        .patch(&format!("/api/tickets/{}/sold", ticket_id), Some(&bot), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "mark sold: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("sold"));
+
+    let payout: (Uuid, i32) = sqlx::query_as("SELECT seller_id, amount FROM payouts WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(payout, (seller.id, 2500));
+
+    // Once the seller has onboarded, the transfer draws on the buyer's charge
+    sqlx::query("UPDATE users SET stripe_account_id = 'acct_seller' WHERE id = $1")
+        .bind(seller.id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    // Whether or not the attempt mark_sold started has run yet, make one now
+    sqlx::query("UPDATE payouts SET next_attempt_at = NOW() WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    process_due_payouts(&app.pool, app.payments.as_ref()).await.unwrap();
+    let charge_id = StripeStub::get().payment_intent_for_ticket(ticket_id).unwrap()["latest_charge"].clone();
+    let transfers = StripeStub::get().requests_for(&ticket_id.to_string());
+    let transfer = transfers.iter().find(|r| r.path == "/v1/transfers").unwrap();
+    assert_eq!(transfer.form.get("source_transaction").map(String::as_str), charge_id.as_str());
+    assert_eq!(transfer.form.get("destination").map(String::as_str), Some("acct_seller"));
+
+    let events: Vec<_> = app
+        .ticket_events(ticket_id)
+        .await
+        .into_iter()
+        .map(|(from, to, _)| (from, to))
+        .collect();
+    let step = |from: Option<&str>, to: &str| (from.map(String::from), Some(to.to_string()));
+    assert_eq!(
+        events,
+        vec![
+            step(None, "unverified"),
+            step(Some("unverified"), "verifying"),
+            step(Some("verifying"), "verified"),
+            step(Some("verified"), "reserved"),
+            step(Some("reserved"), "paid"),
+            step(Some("paid"), "sold"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn webhook_after_reservation_expired_cancels_the_authorization() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "2", 4000).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+
+    sqlx::query("UPDATE tickets SET reserved_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK, "webhook: {}", body);
+
+    assert_ne!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+    assert_eq!(StripeStub::get().requests_for(&format!("{}/cancel", pi_id)).len(), 1);
+    assert!(StripeStub::get().requests_for(&format!("{}/capture", pi_id)).is_empty());
+    assert_eq!(intent_status(&app, pi_id).await, "cancelled");
+}
+
+#[tokio::test]
+async fn payment_failure_puts_the_ticket_back_on_sale() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "3", 1500).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+
+    let mut failed = payment_intent_object(
+        pi["id"].as_str().unwrap(),
+        1500,
+        "requires_payment_method",
+        pi["metadata"].clone(),
+    );
+    failed["last_payment_error"] = json!({ "type": "card_error", "code": "card_declined", "message": "Your card was declined." });
+
+    let (status, body) = app.webhook("payment_intent.payment_failed", failed).await;
+    assert_eq!(status, StatusCode::OK, "webhook: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verified"));
+    assert_eq!(intent_status(&app, pi["id"].as_str().unwrap()).await, "failed");
+}
+
+#[tokio::test]
+async fn duplicate_webhook_is_only_processed_once() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "4", 3000).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+
+    let (status, _) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["duplicate"], true);
+
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+    assert_eq!(StripeStub::get().requests_for(&format!("{}/capture", pi_id)).len(), 1);
+
+    let operations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stripe_operations WHERE payment_intent_id = $1")
+        .bind(pi_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(operations, 1);
+}
+
+#[tokio::test]
+async fn webhook_with_bad_signature_is_rejected() {
+    let Some(app) = TestApp::spawn().await else { return };
+
+    let payload = webhook_event("payment_intent.succeeded", json!({})).to_string();
+    let signature = sign_webhook(&payload, chrono::Utc::now().timestamp());
+    let tampered = payload.replace("payment_intent.succeeded", "payment_intent.canceled");
+
+    let request = axum::http::Request::builder()
+        .method("POST")
+        .uri("/api/webhooks/stripe")
+        .header("stripe-signature", signature)
+        .body(axum::body::Body::from(tampered))
+        .unwrap();
+    let response = tower::ServiceExt::oneshot(app.router.clone(), request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn buyer_releasing_a_reservation_cancels_the_payment_intent() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let other = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "6", 2700).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+    let path = format!("/api/tickets/{}/reserve", ticket_id);
+
+    // Only the buyer holding the reservation can give it up
+    let (status, _) = app.request(Method::DELETE, &path, Some(&other.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = app.request(Method::DELETE, &path, Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK, "release: {}", body);
+    assert_eq!(body["status"], "Verified");
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("verified"));
+
+    assert_eq!(StripeStub::get().requests_for(&format!("{}/cancel", pi_id)).len(), 1);
+    assert_eq!(intent_status(&app, pi_id).await, "cancelled");
+
+    let (status, _) = app.request(Method::DELETE, &path, Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Straight back on sale
+    let (status, _) = app.post(&path, Some(&other.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, to, reason) = app.ticket_events(ticket_id).await.into_iter().rev().nth(1).unwrap();
+    assert_eq!(to.as_deref(), Some("verified"));
+    assert_eq!(reason.as_deref(), Some("reservation released by buyer"));
+}
+
+#[tokio::test]
+async fn in_memory_gateway_captures_and_refunds() {
+    let memory = Arc::new(MemoryPaymentGateway::default());
+    let Some(app) = TestApp::spawn_with_payments(memory.clone()).await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "5", 2200).await;
+
+    let path = format!("/api/tickets/{}", ticket_id);
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK, "checkout: {}", body);
+
+    let pi_id: String = sqlx::query_scalar("SELECT id FROM payment_intents WHERE ticket_id = $1")
+        .bind(ticket_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    let pi = memory.retrieve_payment_intent(&pi_id).await.unwrap();
+    assert_eq!(body["client_secret"].as_str(), pi.client_secret.as_deref());
+
+    // Checking out again hands back the same intent
+    let (status, again) = app.post(&format!("{}/checkout", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["client_secret"], body["client_secret"]);
+
+    let metadata = json!({ "ticket_id": ticket_id.to_string(), "buyer_id": buyer.id.to_string() });
+    let (status, _) = app
+        .webhook(
+            "payment_intent.amount_capturable_updated",
+            payment_intent_object(&pi_id, 2200, "requires_capture", metadata),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+    assert_eq!(memory.retrieve_payment_intent(&pi_id).await.unwrap().status, "succeeded");
+
+    let (status, body) = app.post(&format!("{}/refund", path), Some(&admin.token), None).await;
+    assert_eq!(status, StatusCode::OK, "refund: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunded"));
+    assert_eq!(memory.refunded(), vec![pi_id]);
+
+    let (_, body) = app
+        .get(&format!("/api/admin/audit-log?action=ticket.refund&target_id={}", ticket_id), Some(&admin.token))
+        .await;
+    assert_eq!(body["entries"][0]["details"]["outcome"], "refunded");
+}
+
+#[tokio::test]
+async fn refunding_before_capture_cancels_the_authorization() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "8", 1900).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+    let (status, _) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+
+    // As if the capture had failed and were waiting on an outbox retry
+    sqlx::query("UPDATE payment_intents SET status = 'capturable' WHERE id = $1")
+        .bind(pi_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query(
+        "UPDATE stripe_operations SET status = 'pending', next_attempt_at = NOW() + INTERVAL '1 hour' WHERE payment_intent_id = $1",
+    )
+    .bind(pi_id)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+
+    let (status, body) = app
+        .post(&format!("/api/tickets/{}/refund", ticket_id), Some(&admin.token), None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "refund: {}", body);
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("refunded"));
+    assert_eq!(intent_status(&app, pi_id).await, "cancelled");
+
+    let stub = StripeStub::get();
+    assert_eq!(stub.requests_for(&format!("{}/cancel", pi_id)).len(), 1);
+    assert!(stub.requests_for(pi_id).iter().all(|request| request.path != "/v1/refunds"));
+
+    let operations: Vec<(String, String)> = sqlx::query_as(
+        "SELECT operation::TEXT, status::TEXT FROM stripe_operations WHERE payment_intent_id = $1 ORDER BY created_at",
+    )
+    .bind(pi_id)
+    .fetch_all(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        operations,
+        vec![
+            ("capture".to_string(), "cancelled".to_string()),
+            ("cancel".to_string(), "succeeded".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn disputed_charges_are_not_refunded() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "9", 2100).await;
+    let pi = reserve_and_checkout(&app, &buyer, ticket_id).await;
+    let pi_id = pi["id"].as_str().unwrap();
+    let (status, _) = app.webhook("payment_intent.amount_capturable_updated", authorized(&pi)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    sqlx::query("UPDATE payment_intents SET status = 'disputed' WHERE id = $1")
+        .bind(pi_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app
+        .post(&format!("/api/tickets/{}/refund", ticket_id), Some(&admin.token), None)
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "refund: {}", body);
+    assert!(body["error"].as_str().unwrap().contains("disputed"));
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("paid"));
+    assert!(StripeStub::get().requests_for(pi_id).iter().all(|request| request.path != "/v1/refunds"));
+}
+
+#[tokio::test]
+async fn transitions_the_actor_may_not_trigger_are_denied() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.list_ticket(&seller, game.0, "10", 1000).await;
+
+    // Only the bot verifies a transfer
+    let result = apply_transition(&app.pool, &TicketActor::Buyer(buyer.id), &Transition::Verify { ticket_id }).await;
+    let error = result.unwrap_err();
+    assert!(matches!(error, AppError::PermissionDenied));
+    assert_eq!(error.to_string(), "You don't have permission to perform this action");
+    assert_eq!(app.ticket_status(ticket_id).await.as_deref(), Some("unverified"));
+}
@@ -0,0 +1,196 @@
+//! Browsing tickets on sale: search, paging and the detail page
+
+mod common;
+
+use axum::http::StatusCode;
+use backend::models::user::UserRole;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{uuid, TestApp};
+
+fn prices(body: &Value) -> Vec<i64> {
+    body["tickets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|ticket| ticket["price"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn filters_narrow_the_results_and_the_total() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let football = app.create_game(&admin).await;
+    let hockey = app.create_game(&admin).await;
+
+    for (seat, price) in [("1", 3000), ("2", 1000), ("3", 2000)] {
+        app.verified_ticket(&seller, &bot, &football, seat, price).await;
+    }
+    let hockey_ticket = app.verified_ticket(&seller, &bot, &hockey, "4", 1500).await;
+    // Listed but never verified, so never shown
+    app.list_ticket(&seller, football.0, "5", 500).await;
+
+    sqlx::query("UPDATE games SET sport_type = 'hockey' WHERE id = $1")
+        .bind(hockey.0)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE tickets SET seat_section = '202' WHERE id = $1")
+        .bind(hockey_ticket)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, body) = app.get("/api/tickets", None).await;
+    assert_eq!(status, StatusCode::OK, "search: {}", body);
+    assert_eq!(body["total"], 4);
+    assert_eq!(body["next_cursor"], Value::Null);
+
+    let (_, body) = app
+        .get(&format!("/api/tickets?game_id={}&sort=price", football.0), None)
+        .await;
+    assert_eq!(prices(&body), vec![1000, 2000, 3000]);
+    assert_eq!(body["total"], 3);
+
+    let (_, body) = app.get("/api/tickets?sport_type=hockey", None).await;
+    assert_eq!(uuid(&body["tickets"][0]["id"]), hockey_ticket);
+    assert_eq!(body["total"], 1);
+
+    let (_, body) = app.get("/api/tickets?min_price=1500&max_price=2500&sort=price", None).await;
+    assert_eq!(prices(&body), vec![1500, 2000]);
+    assert_eq!(body["total"], 2);
+
+    let (_, body) = app.get("/api/tickets?level=lower&section=202", None).await;
+    assert_eq!(prices(&body), vec![1500]);
+
+    let name = hockey.1.to_uppercase();
+    let (_, body) = app.get(&format!("/api/tickets?q={}", &name[5..]), None).await;
+    assert_eq!(prices(&body), vec![1500]);
+
+    // LIKE wildcards in the search text are not wildcards
+    let (_, body) = app.get("/api/tickets?q=%25", None).await;
+    assert_eq!(body["total"], 0);
+
+    for query in ["min_price=2000&max_price=1000", "sort=cheapest", "sport_type=curling", "limit=0"] {
+        let (status, body) = app.get(&format!("/api/tickets?{}", query), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", query, body);
+    }
+}
+
+#[tokio::test]
+async fn cursor_pages_through_every_ticket_once() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+
+    // Two tickets share a price so the ID tiebreak is exercised
+    for (seat, price) in [("1", 1000), ("2", 4000), ("3", 2500), ("4", 2500), ("5", 3000)] {
+        app.verified_ticket(&seller, &bot, &game, seat, price).await;
+    }
+
+    let mut seen: Vec<Uuid> = Vec::new();
+    let mut seen_prices = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let path = match &cursor {
+            Some(cursor) => format!("/api/tickets?sort=price_desc&limit=2&cursor={}", cursor),
+            None => "/api/tickets?sort=price_desc&limit=2".to_string(),
+        };
+        let (status, body) = app.get(&path, None).await;
+        assert_eq!(status, StatusCode::OK, "page: {}", body);
+        assert_eq!(body["total"], 5);
+
+        seen_prices.extend(prices(&body));
+        seen.extend(body["tickets"].as_array().unwrap().iter().map(|ticket| uuid(&ticket["id"])));
+
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => break,
+        }
+    }
+
+    assert_eq!(seen_prices, vec![4000, 3000, 2500, 2500, 1000]);
+    let mut unique = seen.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), 5);
+
+    // A cursor only makes sense for the order it was issued in
+    let (_, body) = app.get("/api/tickets?sort=price_desc&limit=2", None).await;
+    let cursor = body["next_cursor"].as_str().unwrap();
+    let (status, _) = app.get(&format!("/api/tickets?sort=date&cursor={}", cursor), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app.get("/api/tickets?cursor=garbage", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn detail_shows_the_game_and_hides_the_reservation_from_others() {
+    let Some(app) = TestApp::spawn().await else { return };
+    let admin = app.create_user(UserRole::Admin).await;
+    let seller = app.create_user(UserRole::User).await;
+    let buyer = app.create_user(UserRole::User).await;
+    let other = app.create_user(UserRole::User).await;
+    let bot = app.create_bot(&admin, &["tickets:verify"]).await;
+    let game = app.create_game(&admin).await;
+    let ticket_id = app.verified_ticket(&seller, &bot, &game, "1", 2000).await;
+    let unlisted = app.list_ticket(&seller, game.0, "2", 2000).await;
+    let path = format!("/api/tickets/{}", ticket_id);
+
+    let (status, body) = app.get(&path, None).await;
+    assert_eq!(status, StatusCode::OK, "detail: {}", body);
+    assert_eq!(uuid(&body["game"]["id"]), game.0);
+    assert_eq!(body["game"]["sport_type"], "Football");
+    assert!(body["game"]["cutoff_time"].is_string());
+    assert_eq!(body["availability"], "Available");
+    assert_eq!(body["is_seller"], false);
+
+    let (status, _) = app.post(&format!("{}/reserve", path), Some(&buyer.token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    for caller in [None, Some(&other.token)] {
+        let (_, body) = app.get(&path, caller.map(String::as_str)).await;
+        assert_eq!(body["availability"], "Reserved");
+        assert_eq!(body["is_reserver"], false);
+        for private in ["status", "reserved_by", "reserved_at", "price_at_reservation", "reservation_expires_at"] {
+            assert!(body.get(private).is_none(), "{} leaked: {}", private, body);
+        }
+    }
+
+    let (_, body) = app.get(&path, Some(&buyer.token)).await;
+    assert_eq!(body["is_reserver"], true);
+    assert_eq!(body["is_seller"], false);
+    assert_eq!(body["status"], "Reserved");
+    assert_eq!(uuid(&body["reserved_by"]), buyer.id);
+    assert!(body["reservation_expires_at"].is_string());
+
+    let (_, body) = app.get(&path, Some(&seller.token)).await;
+    assert_eq!(body["is_seller"], true);
+    assert_eq!(uuid(&body["reserved_by"]), buyer.id);
+
+    // A lapsed reservation puts the ticket back on sale before cleanup runs
+    sqlx::query("UPDATE tickets SET reserved_at = NOW() - INTERVAL '1 hour' WHERE id = $1")
+        .bind(ticket_id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let (_, body) = app.get(&path, Some(&buyer.token)).await;
+    assert_eq!(body["availability"], "Available");
+    assert_eq!(body["is_reserver"], false);
+
+    // Listings that never went on sale are only visible to their seller
+    let (status, _) = app.get(&format!("/api/tickets/{}", unlisted), Some(&other.token)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = app.get(&format!("/api/tickets/{}", unlisted), Some(&seller.token)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "Unverified");
+
+    let (status, _) = app.get(&format!("/api/tickets/{}", Uuid::new_v4()), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
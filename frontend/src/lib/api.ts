@@ -2,44 +2,90 @@ import {
   Game,
   Ticket,
   LoginResponse,
+  RefreshResponse,
   RegisterResponse,
   ReservationResponse,
-  CreateTicketRequest
+  CreateTicketRequest,
+  Purchase,
+  TicketDetail,
+  TicketSearchParams,
+  TicketSearchResponse
 } from '@/types';
 
 const API_BASE = process.env.NEXT_PUBLIC_API_URL || 'http://localhost:3000';
 
 class ApiClient {
   private token: string | null = null;
+  private refreshToken: string | null = null;
+  // Shared so concurrent 401s trigger a single refresh (refresh tokens are single-use)
+  private refreshing: Promise<boolean> | null = null;
 
   constructor() {
     if (typeof window !== 'undefined') {
       this.token = localStorage.getItem('token');
+      this.refreshToken = localStorage.getItem('refresh_token');
     }
   }
 
-  setToken(token: string) {
+  setToken(token: string, refreshToken?: string) {
     this.token = token;
+    if (refreshToken) {
+      this.refreshToken = refreshToken;
+    }
     if (typeof window !== 'undefined') {
       localStorage.setItem('token', token);
+      if (refreshToken) {
+        localStorage.setItem('refresh_token', refreshToken);
+      }
     }
   }
 
   clearToken() {
     this.token = null;
+    this.refreshToken = null;
     if (typeof window !== 'undefined') {
       localStorage.removeItem('token');
+      localStorage.removeItem('refresh_token');
+    }
+  }
+
+  private async refreshAccessToken(): Promise<boolean> {
+    if (!this.refreshToken) {
+      return false;
+    }
+
+    if (!this.refreshing) {
+      this.refreshing = (async () => {
+        const res = await fetch(`${API_BASE}/api/auth/refresh`, {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json' },
+          body: JSON.stringify({ refresh_token: this.refreshToken }),
+        });
+
+        if (!res.ok) {
+          this.clearToken();
+          return false;
+        }
+
+        const data: RefreshResponse = await res.json();
+        this.setToken(data.token, data.refresh_token);
+        return true;
+      })().finally(() => {
+        this.refreshing = null;
+      });
     }
+
+    return this.refreshing;
   }
 
   getToken() {
     return this.token;
   }
 
-  private async request<T>(endpoint: string, options?: RequestInit): Promise<T> {
+  private async request<T>(endpoint: string, options?: RequestInit, retry = true): Promise<T> {
     const headers: HeadersInit = {
       'Content-Type': 'application/json',
-      ...(this.token && { Authorization: this.token }),
+      ...(this.token && { Authorization: `Bearer ${this.token}` }),
     };
 
     const res = await fetch(`${API_BASE}${endpoint}`, {
@@ -47,6 +93,11 @@ class ApiClient {
       headers: { ...headers, ...options?.headers },
     });
 
+    // Access tokens are short-lived; refresh once and replay the request
+    if (res.status === 401 && retry && this.token && (await this.refreshAccessToken())) {
+      return this.request(endpoint, options, false);
+    }
+
     if (!res.ok) {
       const error = await res.json().catch(() => ({ error: 'Request failed' }));
       throw new Error(error.error || 'Request failed');
@@ -73,12 +124,18 @@ class ApiClient {
       method: 'POST',
       body: JSON.stringify({ email, password }),
     });
-    this.setToken(response.token);
+    this.setToken(response.token, response.refresh_token);
     return response;
   }
 
-  logout() {
-    this.clearToken();
+  async logout() {
+    try {
+      await this.request('/api/auth/logout', { method: 'POST' }, false);
+    } catch {
+      // Already expired or revoked; nothing to revoke server-side
+    } finally {
+      this.clearToken();
+    }
   }
 
   // Games
@@ -87,8 +144,19 @@ class ApiClient {
   }
 
   // Tickets
-  async getTickets(): Promise<{ tickets: Ticket[] }> {
-    return this.request('/api/tickets');
+  async getTickets(params: TicketSearchParams = {}): Promise<TicketSearchResponse> {
+    const search = new URLSearchParams();
+    for (const [key, value] of Object.entries(params)) {
+      if (value !== undefined && value !== '') {
+        search.set(key, String(value));
+      }
+    }
+    const query = search.toString();
+    return this.request(`/api/tickets${query ? `?${query}` : ''}`);
+  }
+
+  async getTicket(ticketId: string): Promise<TicketDetail> {
+    return this.request(`/api/tickets/${ticketId}`);
   }
 
   async getMyListings(status?: string): Promise<{ tickets: Ticket[] }> {
@@ -96,6 +164,11 @@ class ApiClient {
     return this.request(`/api/tickets/my-listings${query}`);
   }
 
+  async getPurchases(status?: string): Promise<{ purchases: Purchase[] }> {
+    const query = status ? `?status=${status}` : '';
+    return this.request(`/api/me/purchases${query}`);
+  }
+
   async createTicket(data: CreateTicketRequest): Promise<Ticket> {
     return this.request('/api/tickets', {
       method: 'POST',
@@ -108,6 +181,12 @@ class ApiClient {
       method: 'POST',
     });
   }
+
+  async releaseReservation(ticketId: string): Promise<{ ticket_id: string; status: 'Verified' }> {
+    return this.request(`/api/tickets/${ticketId}/reserve`, {
+      method: 'DELETE',
+    });
+  }
 }
 
 export const api = new ApiClient();
@@ -41,7 +41,7 @@ export function AuthProvider({ children }: { children: ReactNode }) {
   };
 
   const logout = () => {
-    api.logout();
+    void api.logout();
     setUser(null);
     localStorage.removeItem('user');
   };
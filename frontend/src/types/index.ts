@@ -2,6 +2,7 @@ export interface User {
   id: string;
   email: string;
   email_verified: boolean;
+  role: 'User' | 'Moderator' | 'Admin';
 }
 
 export interface Game {
@@ -38,6 +39,77 @@ export interface Ticket {
   created_at: string;
 }
 
+export type TicketAvailability = 'Available' | 'Reserved' | 'Sold' | 'Unavailable';
+
+export interface TicketDetail {
+  id: string;
+  seller_id: string;
+  game: Game;
+  level: string;
+  seat_section: string;
+  seat_row: string;
+  seat_number: string;
+  price: number;
+  availability: TicketAvailability;
+  created_at: string;
+  is_seller: boolean;
+  is_reserver: boolean;
+  // Only present for the seller and the current reserver
+  status?: TicketStatus;
+  reserved_by?: string;
+  reserved_at?: string;
+  price_at_reservation?: number;
+  reservation_expires_at?: string;
+}
+
+export interface TicketSearchParams {
+  q?: string;
+  game_id?: string;
+  sport_type?: string;
+  min_price?: number;
+  max_price?: number;
+  level?: string;
+  section?: string;
+  date_from?: string;
+  date_to?: string;
+  sort?: 'date' | 'price' | 'price_desc' | 'newest';
+  limit?: number;
+  cursor?: string;
+}
+
+export interface TicketSearchResponse {
+  tickets: Ticket[];
+  total: number;
+  next_cursor: string | null;
+}
+
+export type PaymentIntentStatus =
+  | 'Created'
+  | 'Failed'
+  | 'Capturable'
+  | 'Captured'
+  | 'Cancelled'
+  | 'Refunded'
+  | 'Disputed';
+
+export interface Purchase {
+  ticket_id: string;
+  game_id: string;
+  event_name: string;
+  event_date: string;
+  level: string;
+  seat_section: string;
+  seat_row: string;
+  seat_number: string;
+  price: number;
+  status: TicketStatus;
+  reserved_at: string;
+  reservation_expires_at: string | null;
+  reservation_seconds_left: number | null;
+  payment_status: PaymentIntentStatus | null;
+  sold_at: string | null;
+}
+
 export interface ReservationResponse {
   ticket_id: string;
   status: 'Reserved';
@@ -47,9 +119,15 @@ export interface ReservationResponse {
 
 export interface LoginResponse {
   token: string;
+  refresh_token: string;
   user: User;
 }
 
+export interface RefreshResponse {
+  token: string;
+  refresh_token: string;
+}
+
 export interface RegisterResponse {
   message: string;
   verification_code?: string;